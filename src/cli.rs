@@ -0,0 +1,429 @@
+// Headless command-line mode for scripts and CI.
+//
+// Subcommands reuse the saved connections and the database clients of the
+// app without opening a window. Results go to stdout as CSV, JSON or an
+// aligned text table; problems go to stderr with an exit code a script can
+// branch on (see `Failure::exit_code`). Passwords come from the keyring or
+// the vault like in the app, or from `SLICK_DATAUI_PASSWORD` for connections
+// that ask for one. A vault is unlocked with `SLICK_DATAUI_VAULT_PASSPHRASE`.
+use crate::db::{CellValue, Column, DatabaseClient, QueryResult, Table};
+use crate::sql::lexer::{statement_ranges, tokenize};
+use crate::state::{ConnectionConfig, DatabaseType};
+use crate::vault::Vault;
+use anyhow::{Context as _, Result, anyhow};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const PASSWORD_ENV_VAR: &str = "SLICK_DATAUI_PASSWORD";
+const VAULT_PASSPHRASE_ENV_VAR: &str = "SLICK_DATAUI_VAULT_PASSPHRASE";
+
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "A database client. Run without a command to open the app.",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// SQLite files, `postgres://` URLs or `.sql` scripts to open in the app.
+    #[arg(value_name = "FILE_OR_URL")]
+    pub open: Vec<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run SQL against a saved connection and print the results.
+    Query {
+        /// Name of the saved connection.
+        #[arg(long = "conn")]
+        connection: String,
+        /// Read the SQL from this file instead of the argument or stdin.
+        #[arg(long, conflicts_with = "sql")]
+        file: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+        /// The SQL to run; read from stdin when neither this nor --file is given.
+        sql: Option<String>,
+    },
+    /// List the saved connections.
+    ListConnections,
+    /// Print the columns of a table.
+    Describe {
+        /// Name of the saved connection.
+        #[arg(long = "conn")]
+        connection: String,
+        #[arg(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+        table: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Csv,
+    Json,
+    Table,
+}
+
+/// Why a command failed, which decides the exit code.
+#[derive(Debug)]
+enum Failure {
+    /// Bad input: an unknown connection, an unreadable file, no SQL.
+    Usage(anyhow::Error),
+    /// The database couldn't be reached or refused the login.
+    Connection(anyhow::Error),
+    /// A statement failed.
+    Query(anyhow::Error),
+}
+
+impl Failure {
+    /// 2 matches clap's own exit code for bad arguments.
+    fn exit_code(&self) -> i32 {
+        match self {
+            Failure::Query(_) => 1,
+            Failure::Usage(_) => 2,
+            Failure::Connection(_) => 3,
+        }
+    }
+
+    fn error(&self) -> &anyhow::Error {
+        match self {
+            Failure::Usage(e) | Failure::Connection(e) | Failure::Query(e) => e,
+        }
+    }
+}
+
+/// Run a subcommand and return the process exit code.
+pub fn run(command: Command) -> i32 {
+    let mut stdout = io::stdout().lock();
+    let result = match command {
+        Command::Query {
+            connection,
+            file,
+            format,
+            sql,
+        } => query(&connection, file, sql, format, &mut stdout),
+        Command::ListConnections => list_connections(&mut stdout),
+        Command::Describe {
+            connection,
+            format,
+            table,
+        } => describe(&connection, &table, format, &mut stdout),
+    };
+    match result.and_then(|()| stdout.flush().map_err(|e| Failure::Usage(e.into()))) {
+        Ok(()) => 0,
+        Err(failure) => {
+            eprintln!("error: {:#}", failure.error());
+            failure.exit_code()
+        }
+    }
+}
+
+fn load_connections() -> Result<Vec<ConnectionConfig>, Failure> {
+    let mut vault = Vault::load().map_err(Failure::Usage)?;
+    if vault.exists()
+        && let Ok(passphrase) = std::env::var(VAULT_PASSPHRASE_ENV_VAR)
+    {
+        vault
+            .unlock(&passphrase)
+            .map_err(|e| Failure::Usage(e.context("Failed to unlock the credential vault")))?;
+    }
+    let loaded = crate::persistence::load_connections(&vault).map_err(Failure::Usage)?;
+    for problem in &loaded.skipped {
+        eprintln!("warning: {}", problem);
+    }
+    Ok(loaded.connections)
+}
+
+fn open(name: &str) -> Result<Arc<dyn DatabaseClient>, Failure> {
+    let conn = load_connections()?
+        .into_iter()
+        .find(|c| c.name == name)
+        .ok_or_else(|| Failure::Usage(anyhow!("No saved connection named '{}'", name)))?;
+    let password = if conn.password.is_empty() {
+        std::env::var(PASSWORD_ENV_VAR).unwrap_or_default()
+    } else {
+        conn.password.clone()
+    };
+    if (conn.ask_password || conn.needs_credentials) && password.is_empty() {
+        return Err(Failure::Usage(anyhow!(
+            "Connection '{}' needs a password; set {}",
+            name,
+            PASSWORD_ENV_VAR
+        )));
+    }
+    let url = conn
+        .connect_url(&password)
+        .map_err(|e| Failure::Usage(e.context("Invalid connection string")))?;
+    crate::db::TOKIO_RUNTIME
+        .block_on(conn.open_client(&url))
+        .map_err(|e| Failure::Connection(anyhow!(crate::error::AppError::from_error(&e))))
+}
+
+fn read_sql(file: Option<PathBuf>, sql: Option<String>) -> Result<String> {
+    match (file, sql) {
+        (Some(path), _) => std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display())),
+        (None, Some(sql)) => Ok(sql),
+        (None, None) => {
+            let mut sql = String::new();
+            io::stdin().read_to_string(&mut sql)?;
+            Ok(sql)
+        }
+    }
+}
+
+/// The statements of a script, leaving out empty ones and lone comments.
+fn statements(sql: &str) -> Vec<&str> {
+    statement_ranges(sql)
+        .into_iter()
+        .map(|range| sql[range].trim())
+        .filter(|statement| tokenize(statement).iter().any(|t| !t.is_trivia()))
+        .collect()
+}
+
+fn query(
+    connection: &str,
+    file: Option<PathBuf>,
+    sql: Option<String>,
+    format: Format,
+    out: &mut impl Write,
+) -> Result<(), Failure> {
+    let sql = read_sql(file, sql).map_err(Failure::Usage)?;
+    let statements = statements(&sql);
+    if statements.is_empty() {
+        return Err(Failure::Usage(anyhow!("No SQL to run")));
+    }
+    let client = open(connection)?;
+    let result = (|| {
+        for (i, statement) in statements.iter().enumerate() {
+            let result = crate::db::TOKIO_RUNTIME
+                .block_on(client.execute_query(statement))
+                .map_err(|e| Failure::Query(anyhow!(client.describe_error(&e))))?;
+            if i > 0 && format != Format::Json {
+                writeln!(out).map_err(|e| Failure::Usage(e.into()))?;
+            }
+            write_result(&result, format, out).map_err(|e| Failure::Usage(e.into()))?;
+            if result.is_truncated() {
+                eprintln!(
+                    "warning: output stopped after {} rows at the result memory budget",
+                    result.row_count()
+                );
+            }
+        }
+        Ok(())
+    })();
+    crate::db::TOKIO_RUNTIME.block_on(client.close());
+    result
+}
+
+fn list_connections(out: &mut impl Write) -> Result<(), Failure> {
+    for conn in load_connections()? {
+        let db_type = match conn.db_type {
+            DatabaseType::Sqlite => "sqlite",
+            DatabaseType::Postgres => "postgres",
+        };
+        writeln!(out, "{}\t{}", conn.name, db_type).map_err(|e| Failure::Usage(e.into()))?;
+    }
+    Ok(())
+}
+
+fn describe(
+    connection: &str,
+    table: &str,
+    format: Format,
+    out: &mut impl Write,
+) -> Result<(), Failure> {
+    let client = open(connection)?;
+    let columns = crate::db::TOKIO_RUNTIME.block_on(client.get_columns(&Table::unqualified(table)));
+    crate::db::TOKIO_RUNTIME.block_on(client.close());
+    let columns = columns.map_err(|e| Failure::Query(anyhow!(client.describe_error(&e))))?;
+    // PostgreSQL reports no columns rather than an error for a missing table.
+    if columns.is_empty() {
+        return Err(Failure::Query(anyhow!("Table '{}' not found", table)));
+    }
+    write_result(&columns_result(&columns), format, out).map_err(|e| Failure::Usage(e.into()))
+}
+
+fn columns_result(columns: &[Column]) -> QueryResult {
+    let yes_no = |b: bool| if b { "YES" } else { "NO" }.to_string();
+    QueryResult::from_rows(
+        ["column", "type", "nullable", "primary_key"]
+            .map(str::to_string)
+            .to_vec(),
+        columns
+            .iter()
+            .map(|c| {
+                vec![
+                    c.name.clone(),
+                    c.data_type.clone(),
+                    yes_no(c.is_nullable),
+                    yes_no(c.is_primary_key),
+                ]
+            })
+            .collect(),
+    )
+}
+
+fn write_result(result: &QueryResult, format: Format, out: &mut impl Write) -> io::Result<()> {
+    match format {
+        Format::Csv => write_csv(result, out),
+        Format::Json => write_json(result, out),
+        Format::Table => write_table(result, out),
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn write_csv(result: &QueryResult, out: &mut impl Write) -> io::Result<()> {
+    let header: Vec<String> = result.columns().iter().map(|c| csv_field(c)).collect();
+    writeln!(out, "{}", header.join(","))?;
+    for row in result.rows() {
+        let row = row.map_err(io::Error::other)?;
+        let fields: Vec<String> = row.iter().map(|v| csv_field(&v.to_string())).collect();
+        writeln!(out, "{}", fields.join(","))?;
+    }
+    Ok(())
+}
+
+/// One array of objects keyed by column name, on a single line. Numbers and
+/// booleans keep their JSON types; NULL becomes `null`.
+fn write_json(result: &QueryResult, out: &mut impl Write) -> io::Result<()> {
+    let rows: Vec<serde_json::Map<String, serde_json::Value>> = result
+        .rows()
+        .map(|row| {
+            Ok(result
+                .columns()
+                .iter()
+                .zip(row.map_err(io::Error::other)?)
+                .map(|(column, value)| {
+                    let value = match value {
+                        CellValue::Null => serde_json::Value::Null,
+                        CellValue::Int(v) => v.into(),
+                        CellValue::Float(v) => v.into(),
+                        CellValue::Bool(v) => v.into(),
+                        CellValue::Text(v) => v.into(),
+                    };
+                    (column.clone(), value)
+                })
+                .collect())
+        })
+        .collect::<io::Result<_>>()?;
+    serde_json::to_writer(&mut *out, &rows)?;
+    writeln!(out)
+}
+
+fn write_table(result: &QueryResult, out: &mut impl Write) -> io::Result<()> {
+    if result.columns().is_empty() {
+        return writeln!(out, "OK");
+    }
+    let rows: Vec<Vec<String>> = result
+        .rows()
+        .map(|row| {
+            let row = row.map_err(io::Error::other)?;
+            Ok(row.iter().map(ToString::to_string).collect())
+        })
+        .collect::<io::Result<_>>()?;
+    let mut widths: Vec<usize> = result.columns().iter().map(|c| c.chars().count()).collect();
+    for row in &rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+    let line = |row: &[String]| {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(value, width)| format!("{:<width$}", value, width = width))
+            .collect();
+        cells.join(" | ").trim_end().to_string()
+    };
+    writeln!(out, "{}", line(result.columns()))?;
+    let rule: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+    writeln!(out, "{}", rule.join("-+-"))?;
+    for row in &rows {
+        writeln!(out, "{}", line(row))?;
+    }
+    let count = rows.len();
+    writeln!(out, "({} row{})", count, if count == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::ResultBuilder;
+
+    fn result() -> QueryResult {
+        let mut builder = ResultBuilder::new(vec!["id".to_string(), "name".to_string()]);
+        builder.push_row([CellValue::Int(1), CellValue::Text("a, \"b\"".to_string())]);
+        builder.push_row([CellValue::Int(2), CellValue::Null]);
+        builder.finish()
+    }
+
+    fn render(format: Format) -> String {
+        let mut out = Vec::new();
+        write_result(&result(), format, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_output_formats() {
+        assert_eq!(render(Format::Csv), "id,name\n1,\"a, \"\"b\"\"\"\n2,NULL\n");
+        assert_eq!(
+            render(Format::Json),
+            "[{\"id\":1,\"name\":\"a, \\\"b\\\"\"},{\"id\":2,\"name\":null}]\n"
+        );
+        assert_eq!(
+            render(Format::Table),
+            "id | name\n---+-------\n1  | a, \"b\"\n2  | NULL\n(2 rows)\n"
+        );
+    }
+
+    #[test]
+    fn test_statements_skip_empty() {
+        let sql = "SELECT 1; -- trailing comment\n;\nSELECT ';' ;\n";
+        assert_eq!(statements(sql), vec!["SELECT 1", "SELECT ';'"]);
+        assert!(statements("-- nothing\n").is_empty());
+    }
+
+    #[test]
+    fn test_cli_arguments() {
+        let cli = Cli::try_parse_from([
+            "slick-dataui",
+            "query",
+            "--conn",
+            "local",
+            "--format",
+            "csv",
+            "SELECT 1",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Query { ref connection, format: Format::Csv, sql: Some(_), file: None })
+                if connection == "local"
+        ));
+        assert!(
+            Cli::try_parse_from(["slick-dataui", "query", "--conn", "x", "--file", "a", "SQL"])
+                .is_err()
+        );
+        assert!(
+            Cli::try_parse_from(["slick-dataui"])
+                .unwrap()
+                .command
+                .is_none()
+        );
+        let cli = Cli::try_parse_from(["slick-dataui", "app.db", "report.sql"]).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.open, vec!["app.db", "report.sql"]);
+    }
+}
@@ -0,0 +1,331 @@
+// Column layouts for the results grid, remembered per table and per query.
+//
+// A layout lists columns in display order with their width and whether they
+// are hidden or pinned to the left. It is matched to a result by column name,
+// so a layout saved for a table still applies after a column is added or
+// dropped. Layouts live in `column_layouts.json`, most recently used first.
+use crate::db::{QueryResult, Table};
+use crate::vault::write_private;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+const LAYOUTS_FILE_NAME: &str = "column_layouts.json";
+
+/// Least recently used layouts beyond this are forgotten.
+const MAX_LAYOUTS: usize = 500;
+
+/// Rows measured when sizing a column to its content.
+const SAMPLE_ROWS: usize = 200;
+const CHAR_WIDTH: f32 = 7.5;
+const CELL_PADDING: f32 = 32.;
+const MIN_WIDTH: f32 = 60.;
+const MAX_WIDTH: f32 = 480.;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnState {
+    pub name: String,
+    /// Set once the user resizes the column; sized to its content until then.
+    #[serde(default)]
+    pub width: Option<f32>,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+impl ColumnState {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            width: None,
+            hidden: false,
+            pinned: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ColumnLayout {
+    pub columns: Vec<ColumnState>,
+}
+
+/// A result column placed by a layout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutColumn {
+    /// Position of the column in the result.
+    pub index: usize,
+    pub state: ColumnState,
+    /// Width that fits the sampled content.
+    pub auto_width: f32,
+}
+
+impl LayoutColumn {
+    pub fn width(&self) -> f32 {
+        self.state.width.unwrap_or(self.auto_width)
+    }
+}
+
+impl ColumnLayout {
+    /// Every column of `result` exactly once, in display order: the saved
+    /// ones as saved, then new ones in result order, pinned columns first.
+    /// Repeated names are matched in order.
+    pub fn resolve(&self, result: &QueryResult) -> Vec<LayoutColumn> {
+        let names = result.columns();
+        let mut placed = vec![false; names.len()];
+        let mut columns = Vec::with_capacity(names.len());
+        for state in &self.columns {
+            let found = names
+                .iter()
+                .enumerate()
+                .position(|(ix, name)| !placed[ix] && *name == state.name);
+            if let Some(index) = found {
+                placed[index] = true;
+                columns.push((index, state.clone()));
+            }
+        }
+        for (index, name) in names.iter().enumerate() {
+            if !placed[index] {
+                columns.push((index, ColumnState::new(name)));
+            }
+        }
+
+        let mut columns: Vec<LayoutColumn> = columns
+            .into_iter()
+            .map(|(index, state)| LayoutColumn {
+                auto_width: auto_width(result, index),
+                index,
+                state,
+            })
+            .collect();
+        pin_first(&mut columns);
+        columns
+    }
+
+    pub fn from_columns(columns: &[LayoutColumn]) -> Self {
+        Self {
+            columns: columns.iter().map(|c| c.state.clone()).collect(),
+        }
+    }
+}
+
+/// Keep pinned columns ahead of the rest, otherwise in the same order.
+pub fn pin_first(columns: &mut [LayoutColumn]) {
+    columns.sort_by_key(|c| !c.state.pinned);
+}
+
+/// Move the `from`th visible column to be the `to`th, as the grid does when
+/// a header is dragged. A column dragged across the pinned edge stays on its
+/// own side of it.
+pub fn move_visible(columns: &mut Vec<LayoutColumn>, from: usize, to: usize) {
+    let visible: Vec<usize> = (0..columns.len())
+        .filter(|&ix| !columns[ix].state.hidden)
+        .collect();
+    let (Some(&from), Some(&to)) = (visible.get(from), visible.get(to)) else {
+        return;
+    };
+    let column = columns.remove(from);
+    columns.insert(to, column);
+    pin_first(columns);
+}
+
+/// Width for a column from its name and the first rows of its values.
+pub fn auto_width(result: &QueryResult, column: usize) -> f32 {
+    let header = result
+        .columns()
+        .get(column)
+        .map_or(0, |c| c.chars().count());
+    let widest = (0..result.row_count().min(SAMPLE_ROWS))
+        .map(|row| result.cell(row, column).chars().count())
+        .fold(header, usize::max);
+    (widest as f32 * CHAR_WIDTH + CELL_PADDING).clamp(MIN_WIDTH, MAX_WIDTH)
+}
+
+/// Key for the layout of a table browsed from the sidebar.
+pub fn table_key(connection: &str, table: &Table) -> String {
+    match &table.schema {
+        Some(schema) => format!("table:{}/{}.{}", connection, schema, table.name),
+        None => format!("table:{}/{}", connection, table.name),
+    }
+}
+
+/// Key for the layout of an ad-hoc query; whitespace doesn't matter.
+pub fn query_key(connection: &str, query: &str) -> String {
+    let query: Vec<&str> = query.split_whitespace().collect();
+    format!("query:{}/{}", connection, query.join(" "))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SavedLayout {
+    key: String,
+    #[serde(flatten)]
+    layout: ColumnLayout,
+}
+
+#[derive(Debug, Default)]
+pub struct LayoutStore {
+    /// Most recently used first, as stored on disk.
+    layouts: Vec<SavedLayout>,
+    path: Option<PathBuf>,
+}
+
+impl LayoutStore {
+    /// Load the layouts from the app's config directory.
+    pub fn load() -> Result<Self> {
+        let path = crate::persistence::get_config_dir()?.join(LAYOUTS_FILE_NAME);
+        Self::open(path)
+    }
+
+    fn open(path: PathBuf) -> Result<Self> {
+        let layouts = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            layouts,
+            path: Some(path),
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ColumnLayout> {
+        self.layouts
+            .iter()
+            .find(|l| l.key == key)
+            .map(|l| &l.layout)
+    }
+
+    /// Remember `layout` for `key` and write the file.
+    pub fn set(&mut self, key: &str, layout: ColumnLayout) -> Result<()> {
+        self.layouts.retain(|l| l.key != key);
+        self.layouts.insert(
+            0,
+            SavedLayout {
+                key: key.to_string(),
+                layout,
+            },
+        );
+        self.layouts.truncate(MAX_LAYOUTS);
+        self.write()
+    }
+
+    pub fn remove(&mut self, key: &str) -> Result<()> {
+        self.layouts.retain(|l| l.key != key);
+        self.write()
+    }
+
+    fn write(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        write_private(path, &serde_json::to_string_pretty(&self.layouts)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result() -> QueryResult {
+        let columns = ["id", "name", "email", "id"].map(str::to_string).to_vec();
+        let rows = vec![
+            ["1", "Ada", "ada@example.com", "7"]
+                .map(str::to_string)
+                .to_vec(),
+        ];
+        QueryResult::from_rows(columns, rows)
+    }
+
+    fn names(columns: &[LayoutColumn]) -> Vec<(usize, &str)> {
+        columns
+            .iter()
+            .map(|c| (c.index, c.state.name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_resolve_layout() {
+        let result = result();
+        let columns = ColumnLayout::default().resolve(&result);
+        assert_eq!(
+            names(&columns),
+            vec![(0, "id"), (1, "name"), (2, "email"), (3, "id")]
+        );
+        assert_eq!(columns[0].width(), MIN_WIDTH);
+        assert_eq!(columns[2].width(), 15. * CHAR_WIDTH + CELL_PADDING);
+
+        // Saved columns keep their place; "dropped" is gone, the second "id"
+        // is new and goes last, and the pinned column moves to the front.
+        let saved = ColumnLayout {
+            columns: vec![
+                ColumnState::new("email"),
+                ColumnState::new("dropped"),
+                ColumnState::new("id"),
+                ColumnState {
+                    width: Some(200.),
+                    pinned: true,
+                    ..ColumnState::new("name")
+                },
+            ],
+        };
+        let columns = saved.resolve(&result);
+        assert_eq!(
+            names(&columns),
+            vec![(1, "name"), (2, "email"), (0, "id"), (3, "id")]
+        );
+        assert_eq!(columns[0].width(), 200.);
+    }
+
+    #[test]
+    fn test_move_visible() {
+        let result = result();
+        let mut columns = ColumnLayout::default().resolve(&result);
+        columns[1].state.hidden = true;
+        columns[3].state.pinned = true;
+        pin_first(&mut columns);
+        // Visible: id(3, pinned), id(0), email(2).
+        move_visible(&mut columns, 2, 1);
+        assert_eq!(
+            names(&columns),
+            vec![(3, "id"), (2, "email"), (0, "id"), (1, "name")]
+        );
+        // Can't be dragged in front of a pinned column.
+        move_visible(&mut columns, 2, 0);
+        assert_eq!(
+            names(&columns),
+            vec![(3, "id"), (0, "id"), (2, "email"), (1, "name")]
+        );
+    }
+
+    #[test]
+    fn test_layout_store() {
+        let path =
+            std::env::temp_dir().join(format!("slick-dataui-layouts-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut store = LayoutStore::open(path.clone()).unwrap();
+        let layout = ColumnLayout {
+            columns: vec![ColumnState::new("id")],
+        };
+        let key = table_key("local", &Table::unqualified("users"));
+        let other_schema = Table {
+            name: "users".to_string(),
+            schema: Some("audit".to_string()),
+        };
+        assert_ne!(table_key("local", &other_schema), key);
+        store.set(&key, layout.clone()).unwrap();
+        store
+            .set(&query_key("local", "SELECT 1"), ColumnLayout::default())
+            .unwrap();
+        assert_eq!(
+            query_key("local", " SELECT\n  1 "),
+            query_key("local", "SELECT 1")
+        );
+
+        let mut store = LayoutStore::open(path.clone()).unwrap();
+        assert_eq!(store.get(&key), Some(&layout));
+        store.remove(&key).unwrap();
+        assert_eq!(LayoutStore::open(path.clone()).unwrap().get(&key), None);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -5,10 +5,26 @@ use once_cell::sync::Lazy;
 pub(crate) static TOKIO_RUNTIME: Lazy<tokio::runtime::Runtime> =
     Lazy::new(|| tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime"));
 
+mod paging;
+mod pg_url;
 mod postgres;
+mod probe;
+mod quote;
+mod result;
+mod search;
+mod sort;
 mod sqlite;
 mod types;
 
+pub use paging::{DEFAULT_PAGE_SIZE, PAGE_SIZES, PageNav, TablePager};
+pub use pg_url::{DEFAULT_PORT, FieldError, PgField, PgUrlParts, split_password, with_password};
 pub use postgres::PostgresClient;
+pub use probe::{ProbeError, ProbeReport, probe_postgres, probe_sqlite};
+pub use quote::{quote_identifier, quote_table};
+pub use result::{
+    CellRef, CellValue, QueryResult, ResultBuilder, ResultSettings, set_result_limits,
+};
+pub use search::{Found, Matcher, SearchOptions, search};
+pub use sort::{SortKey, sort_order, update_sort_keys};
 pub use sqlite::SqliteClient;
-pub use types::{Column, DatabaseClient, QueryResult, Table};
+pub use types::{Column, DatabaseClient, ErrorLocation, RowCount, Table};
@@ -0,0 +1,422 @@
+// Server-side paging for browsing a table without fetching every row.
+//
+// Tables with a primary key are paged with keyset predicates
+// (`WHERE (pk) > (last seen)`), which stays fast on deep pages. The cursor is
+// read back from the displayed cells, so only keys whose type the clients
+// decode exactly qualify. Tables without one, with keys of other types
+// (uuid, timestamps, numeric, ...), or sorted by a user-chosen column fall
+// back to LIMIT/OFFSET.
+use super::quote::{quote_identifier, quote_literal, quote_table};
+use super::sort::{SortKey, order_by_subquery};
+use super::{Column, QueryResult, RowCount, Table};
+
+/// Key types both clients decode to their exact text, lowercased and
+/// without a length. Anything else may come back as NULL or rounded, which
+/// would turn into a wrong cursor.
+const EXACT_KEY_TYPES: [&str; 14] = [
+    "integer",
+    "int",
+    "int4",
+    "bigint",
+    "int8",
+    "serial",
+    "bigserial",
+    "text",
+    "varchar",
+    "character varying",
+    "character",
+    "char",
+    "bpchar",
+    "name",
+];
+
+/// Page sizes offered by the results footer.
+pub const PAGE_SIZES: [usize; 4] = [50, 100, 500, 1000];
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageNav {
+    First,
+    Previous,
+    Next,
+    Last,
+}
+
+/// A generated page query. `reversed` means the rows come back in
+/// descending key order and must be flipped before display.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageQuery {
+    pub sql: String,
+    pub reversed: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct TablePager {
+    pub table: Table,
+    /// Primary key columns, empty when the table has none.
+    pub key_columns: Vec<Column>,
+    pub page_size: usize,
+    /// Zero-based index of the page currently shown.
+    pub page: usize,
+    pub total: Option<RowCount>,
+    /// User-chosen ordering, applied by the database.
+    pub sort_keys: Vec<SortKey>,
+    first_key: Option<Vec<String>>,
+    last_key: Option<Vec<String>>,
+    at_end: bool,
+}
+
+impl TablePager {
+    pub fn new(table: &Table, columns: &[Column], page_size: usize) -> Self {
+        Self {
+            table: table.clone(),
+            key_columns: columns
+                .iter()
+                .filter(|c| c.is_primary_key)
+                .cloned()
+                .collect(),
+            page_size,
+            page: 0,
+            total: None,
+            sort_keys: Vec::new(),
+            first_key: None,
+            last_key: None,
+            at_end: false,
+        }
+    }
+
+    pub fn uses_keyset(&self) -> bool {
+        !self.key_columns.is_empty()
+            && self.sort_keys.is_empty()
+            && self
+                .key_columns
+                .iter()
+                .all(|c| is_exact_key_type(&c.data_type))
+    }
+
+    pub fn page_count(&self) -> Option<usize> {
+        self.total
+            .map(|t| (t.rows as usize).div_ceil(self.page_size).max(1))
+    }
+
+    pub fn has_previous(&self) -> bool {
+        self.page > 0
+    }
+
+    pub fn has_next(&self) -> bool {
+        !self.at_end
+    }
+
+    /// Change the page size; the caller should reload from the first page.
+    pub fn set_page_size(&mut self, page_size: usize) {
+        self.page_size = page_size;
+        self.rewind();
+    }
+
+    /// Change the ordering; the caller should reload from the first page.
+    pub fn set_sort_keys(&mut self, sort_keys: Vec<SortKey>) {
+        self.sort_keys = sort_keys;
+        self.rewind();
+    }
+
+    fn rewind(&mut self) {
+        self.page = 0;
+        self.first_key = None;
+        self.last_key = None;
+        self.at_end = false;
+    }
+
+    /// Build the SQL that fetches the page reached by `nav`.
+    pub fn query(&self, nav: PageNav) -> PageQuery {
+        let table = quote_table(&self.table);
+
+        if !self.uses_keyset() {
+            let offset = self.target_page(nav) * self.page_size;
+            let mut sql = format!("SELECT * FROM {}", table);
+            if !self.sort_keys.is_empty() {
+                // Break ties on the primary key so rows do not shift between pages.
+                let mut keys = self.sort_keys.clone();
+                for column in &self.key_columns {
+                    if !keys.iter().any(|k| k.column == column.name) {
+                        keys.push(SortKey {
+                            column: column.name.clone(),
+                            ascending: true,
+                        });
+                    }
+                }
+                sql = order_by_subquery(&sql, &keys);
+            }
+            return PageQuery {
+                sql: format!("{} LIMIT {} OFFSET {}", sql, self.page_size, offset),
+                reversed: false,
+            };
+        }
+
+        let (predicate, descending, limit) = match nav {
+            PageNav::First => (None, false, self.page_size),
+            PageNav::Next => match &self.last_key {
+                Some(key) => (Some(self.key_predicate(">", key)), false, self.page_size),
+                None => (None, false, self.page_size),
+            },
+            PageNav::Previous => match &self.first_key {
+                Some(key) if self.page > 0 => {
+                    (Some(self.key_predicate("<", key)), true, self.page_size)
+                }
+                _ => (None, false, self.page_size),
+            },
+            PageNav::Last => (None, true, self.last_page_len()),
+        };
+
+        let direction = if descending { "DESC" } else { "ASC" };
+        let order = self
+            .key_columns
+            .iter()
+            .map(|c| format!("{} {}", quote_identifier(&c.name), direction))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let mut sql = format!("SELECT * FROM {}", table);
+        if let Some(predicate) = predicate {
+            sql.push_str(&format!(" WHERE {}", predicate));
+        }
+        sql.push_str(&format!(" ORDER BY {} LIMIT {}", order, limit));
+
+        PageQuery {
+            sql,
+            reversed: descending,
+        }
+    }
+
+    /// Record a fetched page: restore display order and remember the
+    /// keyset cursors and position for the next navigation.
+    pub fn apply(&mut self, nav: PageNav, query: &PageQuery, result: &mut QueryResult) {
+        if query.reversed {
+            *result = result.reversed();
+        }
+
+        self.page = self.target_page(nav);
+
+        let key_indexes: Vec<usize> = self
+            .key_columns
+            .iter()
+            .filter_map(|k| result.columns().iter().position(|c| *c == k.name))
+            .collect();
+        let key_of = |row: usize| -> Vec<String> {
+            key_indexes.iter().map(|&i| result.cell(row, i)).collect()
+        };
+        let rows = result.row_count();
+        self.first_key = (rows > 0).then(|| key_of(0));
+        self.last_key = rows.checked_sub(1).map(key_of);
+
+        let reached_total = match self.total {
+            Some(total) if total.exact => (self.page + 1) * self.page_size >= total.rows as usize,
+            _ => false,
+        };
+        self.at_end = nav == PageNav::Last || rows < self.page_size || reached_total;
+    }
+
+    fn target_page(&self, nav: PageNav) -> usize {
+        match nav {
+            PageNav::First => 0,
+            PageNav::Previous => self.page.saturating_sub(1),
+            PageNav::Next if self.at_end => self.page,
+            PageNav::Next => self.page + 1,
+            PageNav::Last => self.page_count().map_or(self.page, |n| n - 1),
+        }
+    }
+
+    /// Rows on the final page, so keyset "Last" lines up with OFFSET pages
+    /// when the total is exact.
+    fn last_page_len(&self) -> usize {
+        match self.total {
+            Some(total) if total.exact && total.rows > 0 => {
+                match total.rows as usize % self.page_size {
+                    0 => self.page_size,
+                    rest => rest,
+                }
+            }
+            _ => self.page_size,
+        }
+    }
+
+    fn key_predicate(&self, op: &str, key: &[String]) -> String {
+        let values: Vec<String> = self
+            .key_columns
+            .iter()
+            .zip(key)
+            .map(|(c, v)| quote_literal(v, &c.data_type))
+            .collect();
+
+        if self.key_columns.len() == 1 {
+            format!(
+                "{} {} {}",
+                quote_identifier(&self.key_columns[0].name),
+                op,
+                values[0]
+            )
+        } else {
+            let names: Vec<String> = self
+                .key_columns
+                .iter()
+                .map(|c| quote_identifier(&c.name))
+                .collect();
+            format!("({}) {} ({})", names.join(", "), op, values.join(", "))
+        }
+    }
+}
+
+fn is_exact_key_type(data_type: &str) -> bool {
+    let data_type = data_type.to_lowercase();
+    let base = data_type.split('(').next().unwrap_or_default().trim();
+    EXACT_KEY_TYPES.contains(&base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{DatabaseClient, SqliteClient, TOKIO_RUNTIME};
+
+    fn column(name: &str, data_type: &str, pk: bool) -> Column {
+        Column {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            is_nullable: !pk,
+            is_primary_key: pk,
+        }
+    }
+
+    #[test]
+    fn test_offset_paging_without_primary_key() {
+        let logs = Table {
+            name: "logs".to_string(),
+            schema: Some("app".to_string()),
+        };
+        let mut pager = TablePager::new(&logs, &[column("line", "TEXT", false)], 50);
+        pager.total = Some(RowCount {
+            rows: 120,
+            exact: true,
+        });
+
+        assert_eq!(
+            pager.query(PageNav::First).sql,
+            "SELECT * FROM \"app\".\"logs\" LIMIT 50 OFFSET 0"
+        );
+        assert_eq!(
+            pager.query(PageNav::Last).sql,
+            "SELECT * FROM \"app\".\"logs\" LIMIT 50 OFFSET 100"
+        );
+    }
+
+    #[test]
+    fn test_keyset_paging_walks_table() {
+        let client =
+            TOKIO_RUNTIME.block_on(async { SqliteClient::new("sqlite::memory:").await.unwrap() });
+        TOKIO_RUNTIME.block_on(async {
+            client
+                .execute_query("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)")
+                .await
+                .unwrap();
+            for i in 1..=25 {
+                client
+                    .execute_query(&format!("INSERT INTO items (name) VALUES ('item {}')", i))
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let items = Table::unqualified("items");
+        let columns = TOKIO_RUNTIME.block_on(async { client.get_columns(&items).await.unwrap() });
+        let mut pager = TablePager::new(&items, &columns, 10);
+        pager.total =
+            Some(TOKIO_RUNTIME.block_on(async { client.count_rows(&items).await.unwrap() }));
+        assert!(pager.uses_keyset());
+        assert_eq!(pager.page_count(), Some(3));
+
+        let fetch = |pager: &mut TablePager, nav: PageNav| {
+            let query = pager.query(nav);
+            let mut result =
+                TOKIO_RUNTIME.block_on(async { client.execute_query(&query.sql).await.unwrap() });
+            pager.apply(nav, &query, &mut result);
+            result
+        };
+
+        let page = fetch(&mut pager, PageNav::First);
+        assert_eq!(page.cell(0, 0), "1");
+        assert!(pager.has_next());
+
+        let page = fetch(&mut pager, PageNav::Next);
+        assert_eq!(pager.page, 1);
+        assert_eq!(page.cell(0, 0), "11");
+
+        let page = fetch(&mut pager, PageNav::Last);
+        assert_eq!(pager.page, 2);
+        assert_eq!(page.row_count(), 5);
+        assert_eq!(page.cell(0, 0), "21");
+        assert!(!pager.has_next());
+
+        let page = fetch(&mut pager, PageNav::Previous);
+        assert_eq!(pager.page, 1);
+        assert_eq!(page.cell(0, 0), "11");
+        assert_eq!(page.cell(9, 0), "20");
+    }
+
+    #[test]
+    fn test_sorted_paging_uses_offset() {
+        let mut pager = TablePager::new(
+            &Table::unqualified("users"),
+            &[column("id", "INTEGER", true), column("name", "TEXT", false)],
+            25,
+        );
+        pager.set_sort_keys(vec![SortKey {
+            column: "name".to_string(),
+            ascending: false,
+        }]);
+
+        assert!(!pager.uses_keyset());
+        assert_eq!(
+            pager.query(PageNav::Next).sql,
+            "SELECT * FROM (\nSELECT * FROM \"users\"\n) q ORDER BY \"name\" DESC, \"id\" ASC LIMIT 25 OFFSET 25"
+        );
+    }
+
+    #[test]
+    fn test_inexact_key_types_use_offset() {
+        // Postgres decodes these as NULL, which would page on `id > 'NULL'`.
+        let pager = TablePager::new(
+            &Table::unqualified("events"),
+            &[column("id", "uuid", true), column("at", "timestamp", false)],
+            10,
+        );
+        assert!(!pager.uses_keyset());
+        assert_eq!(
+            pager.query(PageNav::Next).sql,
+            "SELECT * FROM \"events\" LIMIT 10 OFFSET 10"
+        );
+
+        let varchar = TablePager::new(
+            &Table::unqualified("codes"),
+            &[column("code", "character varying(8)", true)],
+            10,
+        );
+        assert!(varchar.uses_keyset());
+        let smallint = TablePager::new(
+            &Table::unqualified("flags"),
+            &[column("id", "smallint", true)],
+            10,
+        );
+        assert!(!smallint.uses_keyset());
+    }
+
+    #[test]
+    fn test_composite_key_predicate() {
+        let pager = TablePager::new(
+            &Table::unqualified("pairs"),
+            &[column("a", "INTEGER", true), column("b", "TEXT", true)],
+            10,
+        );
+        assert_eq!(
+            pager.key_predicate(">", &["1".to_string(), "x".to_string()]),
+            "(\"a\", \"b\") > (1, 'x')"
+        );
+    }
+}
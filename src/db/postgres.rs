@@ -1,8 +1,37 @@
 use super::TOKIO_RUNTIME;
-use crate::db::{Column, DatabaseClient, QueryResult, Table};
+use crate::db::{
+    CellValue, Column, DatabaseClient, ErrorLocation, QueryResult, ResultBuilder, RowCount, Table,
+    quote_table,
+};
 use anyhow::Result;
+use futures::TryStreamExt;
 use sqlx::Column as SqlxColumnTrait;
-use sqlx::{Pool, Postgres, Row, postgres::PgPoolOptions};
+use sqlx::postgres::{PgDatabaseError, PgErrorPosition, PgRow};
+use sqlx::{Pool, Postgres, Row, ValueRef, postgres::PgPoolOptions};
+
+/// Tables estimated above this many rows report `reltuples` instead of
+/// running a full `COUNT(*)`.
+const EXACT_COUNT_THRESHOLD: i64 = 100_000;
+
+/// Decode a cell as the first type that fits. Types without a decoder here
+/// are shown as NULL.
+fn decode_cell(row: &PgRow, i: usize) -> CellValue {
+    if row.try_get_raw(i).map_or(true, |v| v.is_null()) {
+        CellValue::Null
+    } else if let Ok(v) = row.try_get::<i32, _>(i) {
+        CellValue::Int(v.into())
+    } else if let Ok(v) = row.try_get::<i64, _>(i) {
+        CellValue::Int(v)
+    } else if let Ok(v) = row.try_get::<f64, _>(i) {
+        CellValue::Float(v)
+    } else if let Ok(v) = row.try_get::<String, _>(i) {
+        CellValue::Text(v)
+    } else if let Ok(v) = row.try_get::<bool, _>(i) {
+        CellValue::Bool(v)
+    } else {
+        CellValue::Null
+    }
+}
 
 pub struct PostgresClient {
     pool: Pool<Postgres>,
@@ -23,25 +52,36 @@ impl DatabaseClient for PostgresClient {
     async fn get_tables(&self) -> Result<Vec<Table>> {
         let pool = self.pool.clone();
         TOKIO_RUNTIME.spawn(async move {
-            let rows = sqlx::query("SELECT tablename FROM pg_catalog.pg_tables WHERE schemaname != 'pg_catalog' AND schemaname != 'information_schema'")
+            let rows = sqlx::query("SELECT schemaname, tablename FROM pg_catalog.pg_tables WHERE schemaname != 'pg_catalog' AND schemaname != 'information_schema'")
                 .fetch_all(&pool)
                 .await?;
 
             let tables = rows.into_iter().map(|row| Table {
-                name: row.get(0),
-                schema: Some("public".to_string()),
+                name: row.get(1),
+                schema: Some(row.get(0)),
             }).collect();
 
             Ok(tables)
         }).await?
     }
 
-    async fn get_columns(&self, table_name: &str) -> Result<Vec<Column>> {
+    async fn get_columns(&self, table: &Table) -> Result<Vec<Column>> {
         let pool = self.pool.clone();
-        let table_name = table_name.to_string();
+        let table = table.clone();
         TOKIO_RUNTIME.spawn(async move {
-            let rows = sqlx::query("SELECT column_name, data_type, is_nullable FROM information_schema.columns WHERE table_name = $1")
-                .bind(&table_name)
+            let rows = sqlx::query(
+                "SELECT c.column_name, c.data_type, c.is_nullable, \
+                 EXISTS (SELECT 1 FROM information_schema.table_constraints tc \
+                         JOIN information_schema.key_column_usage k \
+                           ON k.constraint_name = tc.constraint_name AND k.table_schema = tc.table_schema \
+                         WHERE tc.constraint_type = 'PRIMARY KEY' AND tc.table_schema = c.table_schema \
+                           AND tc.table_name = c.table_name AND k.column_name = c.column_name) AS is_primary_key \
+                 FROM information_schema.columns c \
+                 WHERE c.table_name = $1 AND ($2::text IS NULL OR c.table_schema = $2) \
+                 ORDER BY c.ordinal_position",
+            )
+                .bind(&table.name)
+                .bind(&table.schema)
                 .fetch_all(&pool)
                 .await?;
 
@@ -49,7 +89,7 @@ impl DatabaseClient for PostgresClient {
                 name: row.get("column_name"),
                 data_type: row.get("data_type"),
                 is_nullable: row.get::<String, _>("is_nullable") == "YES",
-                is_primary_key: false,
+                is_primary_key: row.get("is_primary_key"),
             }).collect();
 
             Ok(columns)
@@ -61,49 +101,91 @@ impl DatabaseClient for PostgresClient {
         let query = query.to_string();
         TOKIO_RUNTIME
             .spawn(async move {
-                let rows = sqlx::query(&query).fetch_all(&pool).await?;
-
-                if rows.is_empty() {
-                    return Ok(QueryResult {
-                        columns: vec![],
-                        rows: vec![],
+                // Rows are decoded as they stream in, so only the typed
+                // columns are ever held in memory.
+                let mut rows = sqlx::query(&query).fetch(&pool);
+                let mut builder: Option<ResultBuilder> = None;
+                while let Some(row) = rows.try_next().await? {
+                    let builder = builder.get_or_insert_with(|| {
+                        ResultBuilder::new(
+                            row.columns().iter().map(|c| c.name().to_string()).collect(),
+                        )
                     });
+                    if !builder.push_row((0..row.len()).map(|i| decode_cell(&row, i))) {
+                        break;
+                    }
                 }
+                Ok(builder.map_or_else(QueryResult::empty, ResultBuilder::finish))
+            })
+            .await?
+    }
 
-                let columns: Vec<String> = rows[0]
-                    .columns()
-                    .iter()
-                    .map(|c| c.name().to_string())
-                    .collect();
-                let mut result_rows = Vec::new();
-
-                for row in rows {
-                    let mut current_row = Vec::new();
-                    for (i, _) in columns.iter().enumerate() {
-                        // Try to get the value as different types and convert to string
-                        let val: String = if let Ok(v) = row.try_get::<i32, _>(i) {
-                            v.to_string()
-                        } else if let Ok(v) = row.try_get::<i64, _>(i) {
-                            v.to_string()
-                        } else if let Ok(v) = row.try_get::<f64, _>(i) {
-                            v.to_string()
-                        } else if let Ok(v) = row.try_get::<String, _>(i) {
-                            v
-                        } else if let Ok(v) = row.try_get::<bool, _>(i) {
-                            v.to_string()
-                        } else {
-                            "NULL".to_string()
-                        };
-                        current_row.push(val);
-                    }
-                    result_rows.push(current_row);
+    async fn count_rows(&self, table: &Table) -> Result<RowCount> {
+        let pool = self.pool.clone();
+        let quoted = quote_table(table);
+        TOKIO_RUNTIME
+            .spawn(async move {
+                // reltuples is -1 for tables that were never analyzed.
+                let estimate: Option<f32> = sqlx::query_scalar(
+                    "SELECT reltuples FROM pg_class WHERE oid = to_regclass($1)",
+                )
+                .bind(&quoted)
+                .fetch_optional(&pool)
+                .await?;
+
+                if let Some(estimate) = estimate
+                    && estimate as i64 > EXACT_COUNT_THRESHOLD
+                {
+                    return Ok(RowCount {
+                        rows: estimate as u64,
+                        exact: false,
+                    });
                 }
 
-                Ok(QueryResult {
-                    columns,
-                    rows: result_rows,
+                let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", quoted))
+                    .fetch_one(&pool)
+                    .await?;
+                Ok(RowCount {
+                    rows: count as u64,
+                    exact: true,
                 })
             })
             .await?
     }
+
+    async fn get_functions(&self) -> Result<Vec<String>> {
+        let pool = self.pool.clone();
+        TOKIO_RUNTIME
+            .spawn(async move {
+                // Built-ins come from the static dialect list; only user functions are introspected.
+                let functions = sqlx::query_scalar(
+                    "SELECT DISTINCT p.proname::text FROM pg_proc p \
+                     JOIN pg_namespace n ON n.oid = p.pronamespace \
+                     WHERE n.nspname NOT IN ('pg_catalog', 'information_schema') \
+                       AND n.nspname NOT LIKE 'pg_toast%' \
+                     ORDER BY 1",
+                )
+                .fetch_all(&pool)
+                .await?;
+                Ok(functions)
+            })
+            .await?
+    }
+
+    fn error_location(&self, error: &anyhow::Error) -> Option<ErrorLocation> {
+        let error = error.downcast_ref::<sqlx::Error>()?.as_database_error()?;
+        match error.try_downcast_ref::<PgDatabaseError>()?.position()? {
+            // PostgreSQL positions are one-based.
+            PgErrorPosition::Original(position) => {
+                Some(ErrorLocation::Offset(position.saturating_sub(1)))
+            }
+            // Inside a function body; nothing in the editor to point at.
+            PgErrorPosition::Internal { .. } => None,
+        }
+    }
+
+    async fn close(&self) {
+        let pool = self.pool.clone();
+        let _ = TOKIO_RUNTIME.spawn(async move { pool.close().await }).await;
+    }
 }
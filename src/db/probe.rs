@@ -0,0 +1,236 @@
+// One-off connection checks for the connection form's Test button.
+//
+// A probe opens a single connection with a short timeout, asks the server for
+// its version and closes again, so a mistyped connection is caught before it
+// is saved. Failures are sorted into the handful of causes worth telling the
+// user apart.
+use super::TOKIO_RUNTIME;
+use sqlx::postgres::PgConnectOptions;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeReport {
+    pub server_version: String,
+    /// From starting to connect until the test query returned.
+    pub latency: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeFailure {
+    InvalidUrl,
+    Dns,
+    Refused,
+    Timeout,
+    Auth,
+    Tls,
+    DatabaseMissing,
+    FileNotFound,
+    Other,
+}
+
+impl ProbeFailure {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::InvalidUrl => "Invalid connection string",
+            Self::Dns => "Host not found",
+            Self::Refused => "Connection refused",
+            Self::Timeout => "Timed out",
+            Self::Auth => "Authentication failed",
+            Self::Tls => "TLS error",
+            Self::DatabaseMissing => "Database does not exist",
+            Self::FileNotFound => "File not found",
+            Self::Other => "Connection failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeError {
+    pub kind: ProbeFailure,
+    pub message: String,
+}
+
+impl fmt::Display for ProbeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind.label(), self.message)
+    }
+}
+
+impl From<sqlx::Error> for ProbeError {
+    fn from(error: sqlx::Error) -> Self {
+        Self {
+            kind: classify(&error),
+            message: error.to_string(),
+        }
+    }
+}
+
+fn timed_out() -> ProbeError {
+    ProbeError {
+        kind: ProbeFailure::Timeout,
+        message: format!("no answer within {} seconds", PROBE_TIMEOUT.as_secs()),
+    }
+}
+
+/// Which of the user-facing causes an error from connecting comes from.
+fn classify(error: &sqlx::Error) -> ProbeFailure {
+    match error {
+        sqlx::Error::Configuration(_) => ProbeFailure::InvalidUrl,
+        sqlx::Error::Tls(_) => ProbeFailure::Tls,
+        sqlx::Error::PoolTimedOut => ProbeFailure::Timeout,
+        sqlx::Error::Io(e) => match e.kind() {
+            std::io::ErrorKind::ConnectionRefused => ProbeFailure::Refused,
+            std::io::ErrorKind::TimedOut => ProbeFailure::Timeout,
+            std::io::ErrorKind::NotFound => ProbeFailure::FileNotFound,
+            _ => {
+                // Resolver failures surface as uncategorized I/O errors whose
+                // wording depends on the platform.
+                let message = e.to_string();
+                if [
+                    "lookup address",
+                    "Name or service not known",
+                    "nodename nor servname",
+                    "No such host is known",
+                ]
+                .iter()
+                .any(|needle| message.contains(needle))
+                {
+                    ProbeFailure::Dns
+                } else {
+                    ProbeFailure::Other
+                }
+            }
+        },
+        sqlx::Error::Database(e) => match e.code().as_deref() {
+            // invalid_password, invalid_authorization_specification
+            Some("28P01" | "28000") => ProbeFailure::Auth,
+            // invalid_catalog_name
+            Some("3D000") => ProbeFailure::DatabaseMissing,
+            _ => ProbeFailure::Other,
+        },
+        _ => ProbeFailure::Other,
+    }
+}
+
+pub async fn probe_postgres(url: &str) -> Result<ProbeReport, ProbeError> {
+    let options = PgConnectOptions::from_str(url)?;
+    TOKIO_RUNTIME
+        .spawn(async move {
+            let start = Instant::now();
+            let probe = async {
+                let mut conn = options.connect().await?;
+                let version: String =
+                    sqlx::query_scalar("SELECT current_setting('server_version')")
+                        .fetch_one(&mut conn)
+                        .await?;
+                let latency = start.elapsed();
+                let _ = conn.close().await;
+                Ok::<_, ProbeError>(ProbeReport {
+                    server_version: format!("PostgreSQL {}", version),
+                    latency,
+                })
+            };
+            tokio::time::timeout(PROBE_TIMEOUT, probe)
+                .await
+                .unwrap_or_else(|_| Err(timed_out()))
+        })
+        .await
+        .unwrap_or_else(|e| Err(ProbeError::from(sqlx::Error::Protocol(e.to_string()))))
+}
+
+pub async fn probe_sqlite(url: &str) -> Result<ProbeReport, ProbeError> {
+    let options = SqliteConnectOptions::from_str(url)?;
+    let path = options.get_filename().to_path_buf();
+    TOKIO_RUNTIME
+        .spawn(async move {
+            let start = Instant::now();
+            let probe = async {
+                let mut conn = options.connect().await?;
+                let version: String = sqlx::query_scalar("SELECT sqlite_version()")
+                    .fetch_one(&mut conn)
+                    .await?;
+                let latency = start.elapsed();
+                let _ = conn.close().await;
+                Ok::<_, ProbeError>(ProbeReport {
+                    server_version: format!("SQLite {}", version),
+                    latency,
+                })
+            };
+            let result = tokio::time::timeout(PROBE_TIMEOUT, probe)
+                .await
+                .unwrap_or_else(|_| Err(timed_out()));
+            // SQLite only says it was unable to open the file.
+            result.map_err(|mut e| {
+                if !path.exists() {
+                    e.kind = ProbeFailure::FileNotFound;
+                }
+                e
+            })
+        })
+        .await
+        .unwrap_or_else(|e| Err(ProbeError::from(sqlx::Error::Protocol(e.to_string()))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    #[test]
+    fn test_classify() {
+        let io_error = |e: io::Error| classify(&sqlx::Error::Io(e));
+        assert_eq!(
+            io_error(io::ErrorKind::ConnectionRefused.into()),
+            ProbeFailure::Refused
+        );
+        assert_eq!(
+            io_error(io::Error::other(
+                "failed to lookup address information: Name or service not known"
+            )),
+            ProbeFailure::Dns
+        );
+        assert_eq!(
+            classify(&sqlx::Error::Configuration("bad".into())),
+            ProbeFailure::InvalidUrl
+        );
+        assert_eq!(
+            classify(&sqlx::Error::Tls("handshake".into())),
+            ProbeFailure::Tls
+        );
+    }
+
+    #[test]
+    fn test_probe_sqlite() {
+        TOKIO_RUNTIME.block_on(async {
+            let report = probe_sqlite("sqlite::memory:").await.unwrap();
+            assert!(report.server_version.starts_with("SQLite 3."));
+
+            let error = probe_sqlite("sqlite:///no/such/dir/missing.db")
+                .await
+                .unwrap_err();
+            assert_eq!(error.kind, ProbeFailure::FileNotFound);
+        });
+    }
+
+    #[test]
+    fn test_probe_postgres_refused() {
+        TOKIO_RUNTIME.block_on(async {
+            // Nothing listens on port 1.
+            let error = probe_postgres("postgres://user@127.0.0.1:1/db")
+                .await
+                .unwrap_err();
+            assert_eq!(error.kind, ProbeFailure::Refused);
+
+            let error = probe_postgres("postgres://user@host:notaport/db")
+                .await
+                .unwrap_err();
+            assert_eq!(error.kind, ProbeFailure::InvalidUrl);
+        });
+    }
+}
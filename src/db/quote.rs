@@ -0,0 +1,68 @@
+// Helpers for splicing identifiers and values into generated SQL.
+// Both SQLite and PostgreSQL accept standard double-quoted identifiers and
+// single-quoted string literals, so one implementation serves both dialects.
+use super::Table;
+
+/// Quote an identifier, doubling any embedded double quotes.
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Quote a table name, qualified with its schema when it has one.
+pub fn quote_table(table: &Table) -> String {
+    match &table.schema {
+        Some(schema) => format!(
+            "{}.{}",
+            quote_identifier(schema),
+            quote_identifier(&table.name)
+        ),
+        None => quote_identifier(&table.name),
+    }
+}
+
+/// Render a cell value as a SQL literal.
+///
+/// Values of numeric columns are emitted bare when they parse as numbers;
+/// everything else becomes a string literal and relies on the database to
+/// coerce it to the column type.
+pub fn quote_literal(value: &str, data_type: &str) -> String {
+    if is_numeric_type(data_type) && value.parse::<f64>().is_ok() {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "''"))
+    }
+}
+
+fn is_numeric_type(data_type: &str) -> bool {
+    let data_type = data_type.to_lowercase();
+    [
+        "int", "serial", "numeric", "decimal", "real", "double", "float",
+    ]
+    .iter()
+    .any(|t| data_type.contains(t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_identifier() {
+        assert_eq!(quote_identifier("users"), "\"users\"");
+        assert_eq!(quote_identifier("we\"ird"), "\"we\"\"ird\"");
+        let table = Table {
+            name: "users".to_string(),
+            schema: Some("app".to_string()),
+        };
+        assert_eq!(quote_table(&table), "\"app\".\"users\"");
+        assert_eq!(quote_table(&Table::unqualified("users")), "\"users\"");
+    }
+
+    #[test]
+    fn test_quote_literal() {
+        assert_eq!(quote_literal("42", "INTEGER"), "42");
+        assert_eq!(quote_literal("42", "text"), "'42'");
+        assert_eq!(quote_literal("O'Brien", "TEXT"), "'O''Brien'");
+        assert_eq!(quote_literal("abc", "bigint"), "'abc'");
+    }
+}
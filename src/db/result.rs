@@ -0,0 +1,871 @@
+// Columnar storage for query results.
+//
+// Rows are decoded into typed columns (integers, floats, booleans, text in
+// one buffer) held in chunks of up to `CHUNK_ROWS` rows. A `QueryResult` is a
+// handle on that storage behind an `Arc`: cloning it for a table view or a
+// tab costs nothing, and sorting or filtering produce a row order instead of
+// moving rows. Chunks count against a memory budget while the result is
+// built; past it, chunks are spilled to a temporary file and read back a
+// chunk at a time when scrolled to, or, with spilling off, the result stops
+// there and is marked truncated.
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Rows per chunk; a chunk also ends early when it alone outgrows the budget.
+const CHUNK_ROWS: usize = 16_384;
+/// Spilled chunks kept decoded, so rows sorted across a few chunks don't
+/// read the file again on every frame.
+const CACHED_CHUNKS: usize = 8;
+/// How NULL cells are displayed.
+const NULL_CELL: &str = "NULL";
+/// Shown in place of cells whose spilled chunk couldn't be read back.
+const UNREADABLE_CELL: &str = "⚠ unreadable";
+
+static MEMORY_BUDGET: AtomicU64 = AtomicU64::new(512 * 1024 * 1024);
+static SPILL_TO_DISK: AtomicBool = AtomicBool::new(true);
+/// Makes spill file names unique within the process.
+static SPILL_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// The `results` section of `settings.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResultSettings {
+    /// Memory a single result may use before it spills or stops.
+    pub memory_budget_mb: u64,
+    /// Keep rows past the budget in a temporary file instead of dropping them.
+    pub spill_to_disk: bool,
+}
+
+impl Default for ResultSettings {
+    fn default() -> Self {
+        Self {
+            memory_budget_mb: 512,
+            spill_to_disk: true,
+        }
+    }
+}
+
+/// Apply `settings` to results built from now on.
+pub fn set_result_limits(settings: &ResultSettings) {
+    MEMORY_BUDGET.store(settings.memory_budget_mb * 1024 * 1024, Ordering::Relaxed);
+    SPILL_TO_DISK.store(settings.spill_to_disk, Ordering::Relaxed);
+}
+
+/// One decoded cell.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CellValue {
+    Null,
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Text(String),
+}
+
+impl fmt::Display for CellValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CellValue::Null => f.write_str(NULL_CELL),
+            CellValue::Int(v) => write!(f, "{}", v),
+            CellValue::Float(v) => write!(f, "{}", v),
+            CellValue::Bool(v) => write!(f, "{}", v),
+            CellValue::Text(v) => f.write_str(v),
+        }
+    }
+}
+
+/// A cell read in place, without copying its text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CellRef<'a> {
+    Null,
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Text(&'a str),
+}
+
+impl CellRef<'_> {
+    pub fn to_value(self) -> CellValue {
+        match self {
+            CellRef::Null => CellValue::Null,
+            CellRef::Int(v) => CellValue::Int(v),
+            CellRef::Float(v) => CellValue::Float(v),
+            CellRef::Bool(v) => CellValue::Bool(v),
+            CellRef::Text(v) => CellValue::Text(v.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for CellRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CellRef::Null => f.write_str(NULL_CELL),
+            CellRef::Int(v) => write!(f, "{}", v),
+            CellRef::Float(v) => write!(f, "{}", v),
+            CellRef::Bool(v) => write!(f, "{}", v),
+            CellRef::Text(v) => f.write_str(v),
+        }
+    }
+}
+
+/// Values of one column within a chunk; NULL rows hold a placeholder.
+#[derive(Debug, Clone, PartialEq)]
+enum Values {
+    Int(Vec<i64>),
+    Float(Vec<f64>),
+    Bool(Vec<bool>),
+    /// Cell `i` is `data[offsets[i]..offsets[i + 1]]`.
+    Text {
+        offsets: Vec<usize>,
+        data: String,
+    },
+}
+
+impl Values {
+    fn for_value(value: &CellValue) -> Self {
+        match value {
+            CellValue::Int(_) => Values::Int(Vec::new()),
+            CellValue::Float(_) => Values::Float(Vec::new()),
+            CellValue::Bool(_) => Values::Bool(Vec::new()),
+            CellValue::Null | CellValue::Text(_) => Values::Text {
+                offsets: vec![0],
+                data: String::new(),
+            },
+        }
+    }
+
+    fn get(&self, row: usize) -> CellRef<'_> {
+        match self {
+            Values::Int(v) => CellRef::Int(v[row]),
+            Values::Float(v) => CellRef::Float(v[row]),
+            Values::Bool(v) => CellRef::Bool(v[row]),
+            Values::Text { offsets, data } => CellRef::Text(&data[offsets[row]..offsets[row + 1]]),
+        }
+    }
+
+    /// Append `value`, or hand it back if it doesn't fit this column's type.
+    fn push(&mut self, value: CellValue) -> Option<CellValue> {
+        match (self, value) {
+            (Values::Int(v), CellValue::Int(x)) => v.push(x),
+            (Values::Float(v), CellValue::Float(x)) => v.push(x),
+            (Values::Bool(v), CellValue::Bool(x)) => v.push(x),
+            (Values::Text { offsets, data }, CellValue::Text(x)) => {
+                data.push_str(&x);
+                offsets.push(data.len());
+            }
+            (values, CellValue::Null) => values.push_placeholder(),
+            (_, value) => return Some(value),
+        }
+        None
+    }
+
+    fn push_placeholder(&mut self) {
+        match self {
+            Values::Int(v) => v.push(0),
+            Values::Float(v) => v.push(0.0),
+            Values::Bool(v) => v.push(false),
+            Values::Text { offsets, data } => offsets.push(data.len()),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Values::Int(v) => v.len(),
+            Values::Float(v) => v.len(),
+            Values::Bool(v) => v.len(),
+            Values::Text { offsets, .. } => offsets.len() - 1,
+        }
+    }
+
+    fn byte_size(&self) -> usize {
+        match self {
+            Values::Int(v) => v.len() * 8,
+            Values::Float(v) => v.len() * 8,
+            Values::Bool(v) => v.len(),
+            Values::Text { offsets, data } => offsets.len() * 8 + data.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ColumnChunk {
+    nulls: Vec<bool>,
+    /// `None` until the first non-NULL value decides the type.
+    values: Option<Values>,
+}
+
+impl ColumnChunk {
+    fn get(&self, row: usize) -> CellValue {
+        self.get_ref(row).to_value()
+    }
+
+    fn get_ref(&self, row: usize) -> CellRef<'_> {
+        match &self.values {
+            Some(values) if !self.nulls[row] => values.get(row),
+            _ => CellRef::Null,
+        }
+    }
+
+    fn push(&mut self, value: CellValue) {
+        self.nulls.push(value == CellValue::Null);
+        if value == CellValue::Null && self.values.is_none() {
+            return;
+        }
+        let values = self.values.get_or_insert_with(|| {
+            let mut values = Values::for_value(&value);
+            for _ in 1..self.nulls.len() {
+                values.push_placeholder();
+            }
+            values
+        });
+        if let Some(value) = values.push(value) {
+            // SQLite columns can mix types; fall back to text for the chunk.
+            self.convert_to_text();
+            if let Some(values) = &mut self.values {
+                values.push(CellValue::Text(value.to_string()));
+            }
+        }
+    }
+
+    fn convert_to_text(&mut self) {
+        let rows = self.values.as_ref().map_or(0, Values::len);
+        let mut text = Values::Text {
+            offsets: vec![0],
+            data: String::new(),
+        };
+        for row in 0..rows {
+            match self.get(row) {
+                CellValue::Null => text.push_placeholder(),
+                value => {
+                    text.push(CellValue::Text(value.to_string()));
+                }
+            }
+        }
+        self.values = Some(text);
+    }
+
+    fn byte_size(&self) -> usize {
+        self.nulls.len() + self.values.as_ref().map_or(0, Values::byte_size)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Chunk {
+    rows: usize,
+    columns: Vec<ColumnChunk>,
+}
+
+impl Chunk {
+    fn new(column_count: usize) -> Self {
+        Self {
+            rows: 0,
+            columns: vec![
+                ColumnChunk {
+                    nulls: Vec::new(),
+                    values: None,
+                };
+                column_count
+            ],
+        }
+    }
+
+    fn byte_size(&self) -> usize {
+        self.columns.iter().map(ColumnChunk::byte_size).sum()
+    }
+
+    /// Stands in for a chunk that couldn't be read, marking every cell.
+    fn unreadable(column_count: usize, rows: usize) -> Self {
+        let mut chunk = Chunk::new(column_count);
+        for column in &mut chunk.columns {
+            for _ in 0..rows {
+                column.push(CellValue::Text(UNREADABLE_CELL.to_string()));
+            }
+        }
+        chunk.rows = rows;
+        chunk
+    }
+
+    fn reversed(&self) -> Self {
+        let mut chunk = Chunk::new(self.columns.len());
+        for row in (0..self.rows).rev() {
+            for (ix, column) in self.columns.iter().enumerate() {
+                chunk.columns[ix].push(column.get(row));
+            }
+            chunk.rows += 1;
+        }
+        chunk
+    }
+
+    fn encode(&self, out: &mut impl Write) -> Result<()> {
+        out.write_all(&(self.rows as u64).to_le_bytes())?;
+        for column in &self.columns {
+            let nulls: Vec<u8> = column.nulls.iter().map(|&n| n as u8).collect();
+            out.write_all(&nulls)?;
+            match &column.values {
+                None => out.write_all(&[0])?,
+                Some(Values::Int(v)) => {
+                    out.write_all(&[1])?;
+                    v.iter().try_for_each(|x| out.write_all(&x.to_le_bytes()))?;
+                }
+                Some(Values::Float(v)) => {
+                    out.write_all(&[2])?;
+                    v.iter().try_for_each(|x| out.write_all(&x.to_le_bytes()))?;
+                }
+                Some(Values::Bool(v)) => {
+                    out.write_all(&[3])?;
+                    out.write_all(&v.iter().map(|&x| x as u8).collect::<Vec<_>>())?;
+                }
+                Some(Values::Text { offsets, data }) => {
+                    out.write_all(&[4])?;
+                    out.write_all(&(data.len() as u64).to_le_bytes())?;
+                    out.write_all(data.as_bytes())?;
+                    offsets
+                        .iter()
+                        .try_for_each(|x| out.write_all(&(*x as u64).to_le_bytes()))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn decode(input: &mut impl Read, column_count: usize) -> Result<Self> {
+        fn u64_of(input: &mut impl Read) -> Result<u64> {
+            let mut buf = [0; 8];
+            input.read_exact(&mut buf)?;
+            Ok(u64::from_le_bytes(buf))
+        }
+        fn bytes(input: &mut impl Read, len: usize) -> Result<Vec<u8>> {
+            let mut buf = vec![0; len];
+            input.read_exact(&mut buf)?;
+            Ok(buf)
+        }
+
+        let rows = u64_of(input)? as usize;
+        let mut columns = Vec::with_capacity(column_count);
+        for _ in 0..column_count {
+            let nulls = bytes(input, rows)?.into_iter().map(|b| b != 0).collect();
+            let values = match bytes(input, 1)?[0] {
+                0 => None,
+                1 => Some(Values::Int(
+                    (0..rows)
+                        .map(|_| u64_of(input).map(|x| x as i64))
+                        .collect::<Result<_>>()?,
+                )),
+                2 => Some(Values::Float(
+                    (0..rows)
+                        .map(|_| u64_of(input).map(f64::from_bits))
+                        .collect::<Result<_>>()?,
+                )),
+                3 => Some(Values::Bool(
+                    bytes(input, rows)?.into_iter().map(|b| b != 0).collect(),
+                )),
+                4 => {
+                    let len = u64_of(input)? as usize;
+                    let data = String::from_utf8(bytes(input, len)?)?;
+                    let offsets = (0..=rows)
+                        .map(|_| u64_of(input).map(|x| x as usize))
+                        .collect::<Result<_>>()?;
+                    Some(Values::Text { offsets, data })
+                }
+                tag => bail!("Corrupt spill file: unknown column tag {}", tag),
+            };
+            columns.push(ColumnChunk { nulls, values });
+        }
+        Ok(Self { rows, columns })
+    }
+}
+
+/// Chunks written out past the memory budget. The file is removed when the
+/// result is dropped.
+struct SpillFile {
+    path: PathBuf,
+    file: Mutex<File>,
+    len: u64,
+}
+
+impl SpillFile {
+    fn create() -> Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "slick-dataui-{}-{}.spill",
+            std::process::id(),
+            SPILL_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut options = fs::OpenOptions::new();
+        options.create_new(true).read(true).write(true);
+        // Results can hold anything the user can query; keep them private.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let file = options.open(&path)?;
+        tracing::debug!(path = %path.display(), "Spilling query results to disk");
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+            len: 0,
+        })
+    }
+
+    /// Append `chunk` and return its offset.
+    fn write(&mut self, chunk: &Chunk) -> Result<u64> {
+        let offset = self.len;
+        let file = self.file.get_mut().unwrap_or_else(|e| e.into_inner());
+        file.seek(SeekFrom::Start(offset))?;
+        let mut writer = BufWriter::new(&mut *file);
+        chunk.encode(&mut writer)?;
+        writer.flush()?;
+        drop(writer);
+        self.len = file.stream_position()?;
+        Ok(offset)
+    }
+
+    fn read(&self, offset: u64, column_count: usize) -> Result<Chunk> {
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        file.seek(SeekFrom::Start(offset))?;
+        Chunk::decode(&mut BufReader::new(&mut *file), column_count)
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+enum ChunkSlot {
+    Resident(Arc<Chunk>),
+    Spilled(u64),
+}
+
+struct ResultData {
+    columns: Vec<String>,
+    /// First row of each chunk, for finding the chunk holding a row.
+    starts: Vec<usize>,
+    chunks: Vec<ChunkSlot>,
+    row_count: usize,
+    spill: Option<SpillFile>,
+    /// Spilled chunks read lately, most recent first.
+    cache: Mutex<VecDeque<(usize, Arc<Chunk>)>>,
+    /// Why a spilled chunk last failed to read back.
+    read_error: Mutex<Option<String>>,
+    truncated: bool,
+}
+
+/// Rows returned by a query, shared rather than copied.
+#[derive(Clone)]
+pub struct QueryResult(Arc<ResultData>);
+
+impl fmt::Debug for QueryResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueryResult")
+            .field("columns", &self.0.columns)
+            .field("rows", &self.0.row_count)
+            .field("spilled", &self.0.spill.is_some())
+            .field("truncated", &self.0.truncated)
+            .finish()
+    }
+}
+
+impl QueryResult {
+    /// No columns and no rows, as returned by statements like `INSERT`.
+    pub fn empty() -> Self {
+        ResultBuilder::new(Vec::new()).finish()
+    }
+
+    /// A result of text cells; `"NULL"` cells are stored as NULL.
+    pub fn from_rows(columns: Vec<String>, rows: Vec<Vec<String>>) -> Self {
+        let mut builder = ResultBuilder::new(columns);
+        for row in rows {
+            builder.push_row(row.into_iter().map(|cell| {
+                if cell == NULL_CELL {
+                    CellValue::Null
+                } else {
+                    CellValue::Text(cell)
+                }
+            }));
+        }
+        builder.finish()
+    }
+
+    pub fn columns(&self) -> &[String] {
+        &self.0.columns
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.0.row_count
+    }
+
+    /// Whether rows were left out because the memory budget ran out.
+    pub fn is_truncated(&self) -> bool {
+        self.0.truncated
+    }
+
+    /// Why some rows couldn't be read back from the spill file, if they
+    /// couldn't; their cells show as unreadable.
+    pub fn read_error(&self) -> Option<String> {
+        self.0
+            .read_error
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn value(&self, row: usize, column: usize) -> CellValue {
+        if row >= self.0.row_count || column >= self.0.columns.len() {
+            return CellValue::Null;
+        }
+        let ix = self.chunk_of(row);
+        self.shown_chunk(ix)
+            .columns
+            .get(column)
+            .map_or(CellValue::Null, |c| c.get(row - self.0.starts[ix]))
+    }
+
+    /// Every cell of `row`, looking its chunk up once.
+    pub fn row(&self, row: usize) -> Vec<CellValue> {
+        if row >= self.0.row_count {
+            return vec![CellValue::Null; self.0.columns.len()];
+        }
+        let ix = self.chunk_of(row);
+        let chunk = self.shown_chunk(ix);
+        let row = row - self.0.starts[ix];
+        chunk.columns.iter().map(|c| c.get(row)).collect()
+    }
+
+    /// A cell as displayed, with NULL as `"NULL"`.
+    pub fn cell(&self, row: usize, column: usize) -> String {
+        self.value(row, column).to_string()
+    }
+
+    /// Every row in order, reading each spilled chunk once. A chunk that
+    /// can't be read back yields an error in place of its rows.
+    pub fn rows(&self) -> impl Iterator<Item = Result<Vec<CellValue>>> + '_ {
+        (0..self.0.chunks.len()).flat_map(move |ix| match self.chunk(ix) {
+            Ok(chunk) => (0..chunk.rows)
+                .map(|row| Ok(chunk.columns.iter().map(|c| c.get(row)).collect()))
+                .collect(),
+            Err(e) => vec![Err(e)],
+        })
+    }
+
+    /// Call `f` with the row, column and value of every cell in `columns`,
+    /// going chunk by chunk in storage order so each spilled chunk is read
+    /// once.
+    pub fn scan(
+        &self,
+        columns: &[usize],
+        mut f: impl FnMut(usize, usize, CellRef<'_>),
+    ) -> Result<()> {
+        for ix in 0..self.0.chunks.len() {
+            let chunk = self.chunk(ix)?;
+            let start = self.0.starts[ix];
+            for &column in columns {
+                let Some(values) = chunk.columns.get(column) else {
+                    continue;
+                };
+                for row in 0..chunk.rows {
+                    f(start + row, column, values.get_ref(row));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The rows in the opposite order, for pages fetched backwards. Pages
+    /// are small, so this copies.
+    pub fn reversed(&self) -> Self {
+        let mut builder = ResultBuilder::new(self.0.columns.clone());
+        for ix in (0..self.0.chunks.len()).rev() {
+            builder.push_chunk(self.shown_chunk(ix).reversed());
+        }
+        builder.finish()
+    }
+
+    /// Chunk `ix` for display, with its cells marked unreadable if it can't
+    /// be read back.
+    fn shown_chunk(&self, ix: usize) -> Arc<Chunk> {
+        self.chunk(ix).unwrap_or_else(|_| {
+            Arc::new(Chunk::unreadable(self.0.columns.len(), self.chunk_len(ix)))
+        })
+    }
+
+    fn chunk(&self, ix: usize) -> Result<Arc<Chunk>> {
+        let offset = match &self.0.chunks[ix] {
+            ChunkSlot::Resident(chunk) => return Ok(chunk.clone()),
+            ChunkSlot::Spilled(offset) => *offset,
+        };
+        let mut cache = self.0.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(pos) = cache.iter().position(|(cached, _)| *cached == ix) {
+            let entry = cache.remove(pos).expect("position is in range");
+            let chunk = entry.1.clone();
+            cache.push_front(entry);
+            return Ok(chunk);
+        }
+        let column_count = self.0.columns.len();
+        let read = match &self.0.spill {
+            Some(spill) => spill.read(offset, column_count),
+            None => Err(anyhow!("Spill file missing")),
+        };
+        let chunk = match read {
+            Ok(chunk) => Arc::new(chunk),
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to read spilled results");
+                *self.0.read_error.lock().unwrap_or_else(|e| e.into_inner()) =
+                    Some(format!("{:#}", e));
+                return Err(e.context("Failed to read spilled results"));
+            }
+        };
+        cache.truncate(CACHED_CHUNKS - 1);
+        cache.push_front((ix, chunk.clone()));
+        Ok(chunk)
+    }
+
+    /// The chunk holding `row`.
+    fn chunk_of(&self, row: usize) -> usize {
+        self.0.starts.partition_point(|&start| start <= row) - 1
+    }
+
+    fn chunk_len(&self, ix: usize) -> usize {
+        let end = self
+            .0
+            .starts
+            .get(ix + 1)
+            .copied()
+            .unwrap_or(self.0.row_count);
+        end - self.0.starts[ix]
+    }
+}
+
+/// Collects decoded rows into a `QueryResult`, keeping to the memory budget.
+pub struct ResultBuilder {
+    columns: Vec<String>,
+    starts: Vec<usize>,
+    chunks: Vec<ChunkSlot>,
+    current: Chunk,
+    row_count: usize,
+    resident_bytes: usize,
+    budget: usize,
+    spill_to_disk: bool,
+    spill: Option<SpillFile>,
+    truncated: bool,
+}
+
+impl ResultBuilder {
+    pub fn new(columns: Vec<String>) -> Self {
+        let budget = MEMORY_BUDGET.load(Ordering::Relaxed) as usize;
+        Self::with_limits(columns, budget, SPILL_TO_DISK.load(Ordering::Relaxed))
+    }
+
+    fn with_limits(columns: Vec<String>, budget: usize, spill_to_disk: bool) -> Self {
+        Self {
+            current: Chunk::new(columns.len()),
+            columns,
+            starts: Vec::new(),
+            chunks: Vec::new(),
+            row_count: 0,
+            resident_bytes: 0,
+            budget,
+            spill_to_disk,
+            spill: None,
+            truncated: false,
+        }
+    }
+
+    /// Add a row. Returns false once the budget is spent and further rows
+    /// are dropped, so the caller can stop fetching.
+    pub fn push_row(&mut self, row: impl IntoIterator<Item = CellValue>) -> bool {
+        if self.truncated {
+            return false;
+        }
+        let mut row = row.into_iter();
+        for column in &mut self.current.columns {
+            column.push(row.next().unwrap_or(CellValue::Null));
+        }
+        self.current.rows += 1;
+        self.row_count += 1;
+
+        let current_bytes = self.current.byte_size();
+        if self.resident_bytes + current_bytes > self.budget && !self.spill_to_disk {
+            self.truncated = true;
+            tracing::info!(rows = self.row_count, "Result reached the memory budget");
+            return false;
+        }
+        if self.current.rows >= CHUNK_ROWS || current_bytes > self.budget {
+            self.seal();
+        }
+        true
+    }
+
+    fn push_chunk(&mut self, chunk: Chunk) {
+        self.seal();
+        self.row_count += chunk.rows;
+        self.current = chunk;
+        self.seal();
+    }
+
+    /// Close the current chunk, spilling it if the budget is spent. Once one
+    /// chunk has gone to disk the rest follow, so a small tail can't take
+    /// memory the earlier chunks were denied.
+    fn seal(&mut self) {
+        if self.current.rows == 0 {
+            return;
+        }
+        let chunk = std::mem::replace(&mut self.current, Chunk::new(self.columns.len()));
+        self.starts.push(self.row_count - chunk.rows);
+        let size = chunk.byte_size();
+        let over_budget = self.resident_bytes + size > self.budget;
+        if self.spill_to_disk && (self.spill.is_some() || over_budget) {
+            match self.spill_chunk(&chunk) {
+                Ok(offset) => {
+                    self.chunks.push(ChunkSlot::Spilled(offset));
+                    return;
+                }
+                // Keep it in memory rather than lose the rows.
+                Err(e) => tracing::error!(error = %e, "Failed to spill results to disk"),
+            }
+        }
+        self.resident_bytes += size;
+        self.chunks.push(ChunkSlot::Resident(Arc::new(chunk)));
+    }
+
+    fn spill_chunk(&mut self, chunk: &Chunk) -> Result<u64> {
+        let spill = match self.spill.take() {
+            Some(spill) => spill,
+            None => SpillFile::create()?,
+        };
+        self.spill.insert(spill).write(chunk)
+    }
+
+    pub fn finish(mut self) -> QueryResult {
+        self.seal();
+        QueryResult(Arc::new(ResultData {
+            columns: self.columns,
+            starts: self.starts,
+            chunks: self.chunks,
+            row_count: self.row_count,
+            spill: self.spill,
+            cache: Mutex::new(VecDeque::new()),
+            read_error: Mutex::new(None),
+            truncated: self.truncated,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(n: i64) -> impl Iterator<Item = Vec<CellValue>> {
+        (0..n).map(|i| {
+            vec![
+                CellValue::Int(i),
+                if i % 3 == 0 {
+                    CellValue::Null
+                } else {
+                    CellValue::Text(format!("row {}", i))
+                },
+            ]
+        })
+    }
+
+    #[test]
+    fn test_typed_columns() {
+        let mut builder = ResultBuilder::new(vec!["id".into(), "value".into()]);
+        builder.push_row([CellValue::Null, CellValue::Float(1.5)]);
+        builder.push_row([CellValue::Int(2), CellValue::Bool(true)]);
+        let result = builder.finish();
+        assert_eq!(result.row_count(), 2);
+        assert_eq!(result.value(0, 0), CellValue::Null);
+        assert_eq!(result.value(1, 0), CellValue::Int(2));
+        // Mixed types in one column fall back to text.
+        assert_eq!(result.value(0, 1), CellValue::Text("1.5".into()));
+        assert_eq!(result.cell(1, 1), "true");
+        assert_eq!(result.cell(5, 0), NULL_CELL);
+
+        let reversed = result.reversed();
+        assert_eq!(reversed.value(0, 0), CellValue::Int(2));
+        assert_eq!(reversed.cell(1, 1), "1.5");
+        assert_eq!(QueryResult::empty().row_count(), 0);
+    }
+
+    #[test]
+    fn test_spill_to_disk() {
+        let count = CHUNK_ROWS as i64 * 3 + 10;
+        let mut builder =
+            ResultBuilder::with_limits(vec!["id".into(), "name".into()], 1 << 19, true);
+        for row in rows(count) {
+            assert!(builder.push_row(row));
+        }
+        let result = builder.finish();
+        // The first chunks fit the budget; the rest went to disk.
+        assert!(matches!(result.0.chunks[0], ChunkSlot::Resident(_)));
+        assert!(matches!(
+            result.0.chunks.last(),
+            Some(ChunkSlot::Spilled(_))
+        ));
+        assert!(!result.is_truncated());
+        assert_eq!(result.row_count(), count as usize);
+        assert_eq!(
+            result.value(CHUNK_ROWS + 1, 1),
+            CellValue::Text(format!("row {}", CHUNK_ROWS + 1))
+        );
+        assert_eq!(result.value(CHUNK_ROWS * 3, 1), CellValue::Null);
+        assert_eq!(
+            result.row(CHUNK_ROWS * 2 + 1),
+            rows(count).nth(CHUNK_ROWS * 2 + 1).unwrap()
+        );
+        // Going back and forth between chunks doesn't read them again.
+        let cached = result.0.cache.lock().unwrap().len();
+        assert!(cached >= 2);
+        result.value(CHUNK_ROWS + 2, 0);
+        assert_eq!(result.0.cache.lock().unwrap().len(), cached);
+        assert!(result.rows().zip(rows(count)).all(|(a, b)| a.unwrap() == b));
+
+        let path = result.0.spill.as_ref().unwrap().path.clone();
+        assert!(path.exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // A damaged spill file shows up as an error, not as NULLs.
+        result.0.cache.lock().unwrap().clear();
+        result
+            .0
+            .spill
+            .as_ref()
+            .unwrap()
+            .file
+            .lock()
+            .unwrap()
+            .set_len(8)
+            .unwrap();
+        let last = count as usize - 1;
+        assert_eq!(result.cell(last, 0), UNREADABLE_CELL);
+        assert!(result.read_error().is_some());
+        assert!(result.rows().any(|row| row.is_err()));
+        drop(result);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_budget_without_spill() {
+        let mut builder = ResultBuilder::with_limits(vec!["id".into(), "name".into()], 4096, false);
+        let accepted = rows(10_000)
+            .take_while(|row| builder.push_row(row.clone()))
+            .count();
+        let result = builder.finish();
+        assert!(result.is_truncated());
+        assert!(accepted < 10_000);
+        assert_eq!(result.row_count(), accepted + 1);
+    }
+}
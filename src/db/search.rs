@@ -0,0 +1,223 @@
+// Find and filter within a result set, without re-running the query.
+//
+// Cells are matched as the grid shows them, so NULL matches "NULL" and
+// numbers match their text. The result is scanned as stored, so a spilled
+// result is read once however it is sorted, counting the matches in each
+// row; the counts are then laid out in the order the rows are shown.
+use super::{CellRef, QueryResult};
+use anyhow::{Context as _, Result};
+use regex::{Regex, RegexBuilder};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchOptions {
+    pub text: String,
+    pub case_sensitive: bool,
+    /// Take `text` as a regular expression rather than literally.
+    pub regex: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Matcher(Regex);
+
+impl Matcher {
+    /// `None` when there is nothing to search for.
+    pub fn new(options: &SearchOptions) -> Result<Option<Self>> {
+        if options.text.is_empty() {
+            return Ok(None);
+        }
+        let pattern = if options.regex {
+            options.text.clone()
+        } else {
+            regex::escape(&options.text)
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(!options.case_sensitive)
+            .build()
+            .context("Invalid regular expression")?;
+        Ok(Some(Self(regex)))
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.0.is_match(text)
+    }
+}
+
+/// What a search found, laid out in display order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Found {
+    /// Result rows to show, in order, when filtering to the rows that match.
+    pub order: Option<Vec<usize>>,
+    /// Display rows with at least one matching cell.
+    pub rows: Vec<usize>,
+    /// Matching cells ahead of each of `rows`, for numbering matches.
+    pub cells_before: Vec<usize>,
+    /// Matching cells in all.
+    pub cells: usize,
+}
+
+/// Search `columns` of `result`, whose rows are shown in `order` (as stored
+/// when `None`), and with `filter` keep only the rows that match. Returns
+/// `None` once `cancel` is set.
+pub fn search(
+    result: &QueryResult,
+    order: Option<&[usize]>,
+    columns: &[usize],
+    matcher: &Matcher,
+    filter: bool,
+    cancel: &AtomicBool,
+) -> Result<Option<Found>> {
+    let mut counts = vec![0u32; result.row_count()];
+    let mut text = String::new();
+    result.scan(columns, |row, _, cell| {
+        if cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        let matched = match cell {
+            CellRef::Text(v) => matcher.is_match(v),
+            cell => {
+                text.clear();
+                let _ = write!(text, "{}", cell);
+                matcher.is_match(&text)
+            }
+        };
+        if matched {
+            counts[row] += 1;
+        }
+    })?;
+    if cancel.load(Ordering::Relaxed) {
+        return Ok(None);
+    }
+
+    let display: Box<dyn Iterator<Item = usize>> = match order {
+        Some(order) => Box::new(order.iter().copied()),
+        None => Box::new(0..counts.len()),
+    };
+    let mut found = Found::default();
+    let mut kept = Vec::new();
+    for (position, row) in display.enumerate() {
+        let count = counts[row] as usize;
+        if count == 0 {
+            continue;
+        }
+        found.rows.push(if filter { kept.len() } else { position });
+        found.cells_before.push(found.cells);
+        found.cells += count;
+        if filter {
+            kept.push(row);
+        }
+    }
+    found.order = filter.then_some(kept);
+    Ok(Some(found))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result() -> QueryResult {
+        let rows = [
+            ["1", "Alice", "alice@example.com"],
+            ["2", "bob", "NULL"],
+            ["3", "Carol", "carol@Example.org"],
+        ];
+        QueryResult::from_rows(
+            ["id", "name", "email"].map(str::to_string).to_vec(),
+            rows.iter()
+                .map(|row| row.map(str::to_string).to_vec())
+                .collect(),
+        )
+    }
+
+    fn matcher(text: &str, case_sensitive: bool, regex: bool) -> Matcher {
+        Matcher::new(&SearchOptions {
+            text: text.to_string(),
+            case_sensitive,
+            regex,
+        })
+        .unwrap()
+        .unwrap()
+    }
+
+    fn found(
+        result: &QueryResult,
+        order: Option<&[usize]>,
+        columns: &[usize],
+        m: &Matcher,
+    ) -> Found {
+        search(result, order, columns, m, false, &AtomicBool::new(false))
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_search() {
+        let result = result();
+        let all = [0, 1, 2];
+        let example = matcher("example", false, false);
+        let matches = found(&result, Some(&[2, 1, 0]), &all, &example);
+        assert_eq!(matches.rows, vec![0, 2]);
+        assert_eq!(matches.cells_before, vec![0, 1]);
+        assert_eq!(matches.cells, 2);
+        assert_eq!(matches.order, None);
+        let example = matcher("Example", true, false);
+        assert_eq!(found(&result, None, &all, &example).cells, 1);
+
+        // Literal text isn't a pattern; NULL matches as shown.
+        assert_eq!(
+            found(&result, None, &all, &matcher(".", false, false)).cells,
+            2
+        );
+        assert_eq!(
+            found(&result, None, &[2], &matcher("null", false, false)).rows,
+            vec![1]
+        );
+        // Every cell of a row counts.
+        let a = found(&result, None, &all, &matcher("a", false, false));
+        assert_eq!(
+            (a.rows, a.cells_before, a.cells),
+            (vec![0, 2], vec![0, 2], 4)
+        );
+
+        let cancelled = AtomicBool::new(true);
+        assert_eq!(
+            search(&result, None, &all, &example, false, &cancelled).unwrap(),
+            None
+        );
+        assert!(Matcher::new(&SearchOptions::default()).unwrap().is_none());
+        assert!(
+            Matcher::new(&SearchOptions {
+                text: "(".to_string(),
+                regex: true,
+                ..Default::default()
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_search_filter() {
+        let result = result();
+        let starts_upper = matcher("^[A-Z]", true, true);
+        let filter = |order: Option<&[usize]>, columns: &[usize]| {
+            search(
+                &result,
+                order,
+                columns,
+                &starts_upper,
+                true,
+                &AtomicBool::new(false),
+            )
+            .unwrap()
+            .unwrap()
+        };
+        let kept = filter(None, &[1]);
+        assert_eq!(kept.order, Some(vec![0, 2]));
+        // Positions are within the rows left.
+        assert_eq!(kept.rows, vec![0, 1]);
+        assert_eq!(filter(Some(&[2, 1, 0]), &[1]).order, Some(vec![2, 0]));
+        // The id column never starts with a letter.
+        assert_eq!(filter(None, &[0]).order, Some(vec![]));
+    }
+}
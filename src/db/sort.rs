@@ -0,0 +1,322 @@
+// Sorting for query results.
+//
+// Complete result sets are sorted in memory so the query is never re-run;
+// the rows stay where they are and the table shows them in the sorted order.
+// Paged results must be sorted by the database; their query is wrapped as a
+// subquery rather than edited, so ORDER BY clauses inside subqueries, window
+// functions or string literals are left untouched.
+use super::quote::quote_identifier;
+use super::{CellRef, QueryResult};
+use std::cmp::Ordering;
+
+/// Leading bytes of each text cell kept for sorting; longer cells that tie
+/// on them are read again to settle the order.
+const TEXT_PREFIX: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    pub column: String,
+    pub ascending: bool,
+}
+
+/// Apply a header click to the current sort keys.
+///
+/// `direction` is `Some(ascending)` to sort by `column` or `None` to stop
+/// sorting by it. A plain click replaces the keys; an `additive`
+/// (shift) click adds or updates `column` while keeping the others.
+pub fn update_sort_keys(
+    keys: &mut Vec<SortKey>,
+    column: &str,
+    direction: Option<bool>,
+    additive: bool,
+) {
+    if !additive {
+        keys.clear();
+    }
+
+    match (keys.iter().position(|k| k.column == column), direction) {
+        (Some(ix), Some(ascending)) => keys[ix].ascending = ascending,
+        (Some(ix), None) => {
+            keys.remove(ix);
+        }
+        (None, Some(ascending)) => keys.push(SortKey {
+            column: column.to_string(),
+            ascending,
+        }),
+        (None, None) => {}
+    }
+}
+
+/// The order to show complete results in, as row indexes, or `None` when
+/// there is nothing to sort by.
+///
+/// Numeric columns, and text columns whose non-NULL cells all parse as
+/// numbers, compare numerically; others compare as text. NULLs sort last
+/// ascending and first descending, matching PostgreSQL's defaults. Rows that
+/// can't be read back from disk leave the results unsorted.
+pub fn sort_order(result: &QueryResult, keys: &[SortKey]) -> Option<Vec<usize>> {
+    let keys: Vec<(SortColumn, bool)> = keys
+        .iter()
+        .filter_map(|key| {
+            let ix = result.columns().iter().position(|c| *c == key.column)?;
+            Some((ix, key.ascending))
+        })
+        .map(|(ix, ascending)| Some((SortColumn::read(result, ix)?, ascending)))
+        .collect::<Option<_>>()?;
+    if keys.is_empty() {
+        return None;
+    }
+
+    let mut order: Vec<usize> = (0..result.row_count()).collect();
+    order.sort_by(|&a, &b| {
+        keys.iter()
+            .map(|(column, ascending)| {
+                let ordering = column.compare(result, a, b);
+                if *ascending {
+                    ordering
+                } else {
+                    ordering.reverse()
+                }
+            })
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+    Some(order)
+}
+
+/// Wrap `base` so the database sorts its rows without the query text itself
+/// being rewritten.
+pub fn order_by_subquery(base: &str, keys: &[SortKey]) -> String {
+    let base = base.trim().trim_end_matches(';').trim_end();
+    let order = keys
+        .iter()
+        .map(|k| {
+            format!(
+                "{} {}",
+                quote_identifier(&k.column),
+                if k.ascending { "ASC" } else { "DESC" }
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    // The newline keeps a trailing `--` comment in `base` from swallowing the wrapper.
+    format!("SELECT * FROM (\n{}\n) q ORDER BY {}", base, order)
+}
+
+/// A sort key column read out of the result once, so comparisons don't
+/// decode cells (or read spilled chunks) again. Text keeps only a prefix of
+/// each cell, so sorting doesn't copy the column past the memory budget.
+enum SortColumn {
+    Integer(Vec<Option<i64>>),
+    Numeric(Vec<Option<f64>>),
+    Text {
+        column: usize,
+        keys: Vec<Option<TextKey>>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct TextKey {
+    /// The first bytes of the text, padded with zeros.
+    prefix: [u8; TEXT_PREFIX],
+    len: usize,
+}
+
+impl TextKey {
+    fn new(text: &str) -> Self {
+        let mut prefix = [0; TEXT_PREFIX];
+        let head = &text.as_bytes()[..text.len().min(TEXT_PREFIX)];
+        prefix[..head.len()].copy_from_slice(head);
+        Self {
+            prefix,
+            len: text.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Kind {
+    Integer,
+    Numeric,
+    Text,
+}
+
+/// The narrowest comparison `cell` allows; booleans compare as text.
+fn kind_of(cell: CellRef<'_>) -> Kind {
+    match cell {
+        CellRef::Null | CellRef::Int(_) => Kind::Integer,
+        CellRef::Float(_) => Kind::Numeric,
+        CellRef::Text(v) if v.parse::<i64>().is_ok() => Kind::Integer,
+        CellRef::Text(v) if v.parse::<f64>().is_ok() => Kind::Numeric,
+        CellRef::Text(_) | CellRef::Bool(_) => Kind::Text,
+    }
+}
+
+impl SortColumn {
+    /// `None` when the column can't be read.
+    fn read(result: &QueryResult, ix: usize) -> Option<Self> {
+        let scan = |f: &mut dyn FnMut(CellRef<'_>)| {
+            result
+                .scan(&[ix], |_, _, cell| f(cell))
+                .inspect_err(|e| tracing::error!(error = %e, "Failed to read rows to sort"))
+                .ok()
+        };
+        let mut kind = Kind::Integer;
+        scan(&mut |cell| kind = kind.max(kind_of(cell)))?;
+
+        let rows = result.row_count();
+        Some(match kind {
+            Kind::Integer => {
+                let mut values = Vec::with_capacity(rows);
+                scan(&mut |cell| {
+                    values.push(match cell {
+                        CellRef::Int(v) => Some(v),
+                        CellRef::Text(v) => v.parse().ok(),
+                        _ => None,
+                    })
+                })?;
+                SortColumn::Integer(values)
+            }
+            Kind::Numeric => {
+                let mut values = Vec::with_capacity(rows);
+                scan(&mut |cell| {
+                    values.push(match cell {
+                        CellRef::Int(v) => Some(v as f64),
+                        CellRef::Float(v) => Some(v),
+                        CellRef::Text(v) => v.parse().ok(),
+                        _ => None,
+                    })
+                })?;
+                SortColumn::Numeric(values)
+            }
+            Kind::Text => {
+                let mut keys = Vec::with_capacity(rows);
+                scan(&mut |cell| {
+                    keys.push(match cell {
+                        CellRef::Null => None,
+                        CellRef::Text(v) => Some(TextKey::new(v)),
+                        cell => Some(TextKey::new(&cell.to_value().to_string())),
+                    })
+                })?;
+                SortColumn::Text { column: ix, keys }
+            }
+        })
+    }
+
+    fn compare(&self, result: &QueryResult, a: usize, b: usize) -> Ordering {
+        match self {
+            SortColumn::Integer(values) => compare_cells(values[a], values[b], |a, b| a.cmp(b)),
+            SortColumn::Numeric(values) => compare_cells(values[a], values[b], f64::total_cmp),
+            SortColumn::Text { column, keys } => compare_cells(keys[a], keys[b], |x, y| {
+                if x.prefix != y.prefix || x.len.min(y.len) <= TEXT_PREFIX {
+                    return x.cmp(y);
+                }
+                // Both run past the prefix and agree on it.
+                let text = |row| result.value(row, *column).to_string();
+                text(a).cmp(&text(b))
+            }),
+        }
+    }
+}
+
+fn compare_cells<T>(a: Option<T>, b: Option<T>, cmp: impl Fn(&T, &T) -> Ordering) -> Ordering {
+    match (a, b) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => cmp(&a, &b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(rows: &[[&str; 2]]) -> QueryResult {
+        QueryResult::from_rows(
+            vec!["n".to_string(), "name".to_string()],
+            rows.iter()
+                .map(|r| r.iter().map(|c| c.to_string()).collect())
+                .collect(),
+        )
+    }
+
+    fn sorted(res: &QueryResult, keys: &[SortKey]) -> Vec<String> {
+        let order = sort_order(res, keys).unwrap();
+        order.iter().map(|&row| res.cell(row, 1)).collect()
+    }
+
+    fn key(column: &str, ascending: bool) -> SortKey {
+        SortKey {
+            column: column.to_string(),
+            ascending,
+        }
+    }
+
+    #[test]
+    fn test_sort_order_numeric_and_nulls() {
+        let res = result(&[["10", "a"], ["NULL", "b"], ["9", "c"], ["-1.5", "d"]]);
+        assert_eq!(sorted(&res, &[key("n", true)]), ["d", "c", "a", "b"]);
+        assert_eq!(sorted(&res, &[key("n", false)]), ["b", "a", "c", "d"]);
+        assert_eq!(sort_order(&res, &[key("missing", true)]), None);
+    }
+
+    #[test]
+    fn test_sort_order_multiple_keys() {
+        let res = result(&[["1", "b"], ["2", "a"], ["1", "a"]]);
+        let order = sort_order(&res, &[key("n", true), key("name", false)]);
+        assert_eq!(order, Some(vec![0, 2, 1]));
+    }
+
+    #[test]
+    fn test_sort_order_integers_and_long_text() {
+        // Too close together to tell apart as floats.
+        let res = result(&[
+            ["9007199254740993", "a"],
+            ["9007199254740992", "b"],
+            ["NULL", "c"],
+        ]);
+        assert_eq!(sorted(&res, &[key("n", true)]), ["b", "a", "c"]);
+
+        // Cells that share the kept prefix are compared in full.
+        let res = result(&[
+            ["1", "https://example.com/b"],
+            ["2", "https://example.com/a"],
+            ["3", "https://example.com"],
+            ["4", "https://"],
+        ]);
+        assert_eq!(
+            sorted(&res, &[key("name", true)]),
+            [
+                "https://",
+                "https://example.com",
+                "https://example.com/a",
+                "https://example.com/b"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_update_sort_keys() {
+        let mut keys = vec![];
+        update_sort_keys(&mut keys, "a", Some(true), false);
+        update_sort_keys(&mut keys, "b", Some(false), true);
+        assert_eq!(keys, vec![key("a", true), key("b", false)]);
+
+        update_sort_keys(&mut keys, "a", None, true);
+        assert_eq!(keys, vec![key("b", false)]);
+
+        update_sort_keys(&mut keys, "c", Some(true), false);
+        assert_eq!(keys, vec![key("c", true)]);
+    }
+
+    #[test]
+    fn test_order_by_subquery_keeps_query_intact() {
+        let base = "SELECT * FROM t WHERE note = 'ORDER BY x' -- trailing\n;";
+        assert_eq!(
+            order_by_subquery(base, &[key("id", false)]),
+            "SELECT * FROM (\nSELECT * FROM t WHERE note = 'ORDER BY x' -- trailing\n) q ORDER BY \"id\" DESC"
+        );
+    }
+}
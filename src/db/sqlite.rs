@@ -1,8 +1,31 @@
 use super::TOKIO_RUNTIME;
-use crate::db::{Column, DatabaseClient, QueryResult, Table};
+use crate::db::{
+    CellValue, Column, DatabaseClient, ErrorLocation, QueryResult, ResultBuilder, RowCount, Table,
+    quote_identifier, quote_table,
+};
 use anyhow::Result;
+use futures::TryStreamExt;
 use sqlx::Column as SqlxColumnTrait;
-use sqlx::{Pool, Row, Sqlite, sqlite::SqlitePoolOptions};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Pool, Row, Sqlite, ValueRef, sqlite::SqlitePoolOptions};
+
+/// Decode a cell as the first type that fits. Text that isn't valid UTF-8
+/// and other types shown as NULL.
+fn decode_cell(row: &SqliteRow, i: usize) -> CellValue {
+    if row.try_get_raw(i).map_or(true, |v| v.is_null()) {
+        CellValue::Null
+    } else if let Ok(v) = row.try_get::<i64, _>(i) {
+        CellValue::Int(v)
+    } else if let Ok(v) = row.try_get::<f64, _>(i) {
+        CellValue::Float(v)
+    } else if let Ok(v) = row.try_get::<String, _>(i) {
+        CellValue::Text(v)
+    } else if let Ok(v) = row.try_get::<bool, _>(i) {
+        CellValue::Bool(v)
+    } else {
+        CellValue::Null
+    }
+}
 
 pub struct SqliteClient {
     pool: Pool<Sqlite>,
@@ -41,12 +64,18 @@ impl DatabaseClient for SqliteClient {
             .await?
     }
 
-    async fn get_columns(&self, table_name: &str) -> Result<Vec<Column>> {
+    async fn get_columns(&self, table: &Table) -> Result<Vec<Column>> {
         let pool = self.pool.clone();
-        let table_name = table_name.to_string();
+        let query = match &table.schema {
+            Some(schema) => format!(
+                "PRAGMA {}.table_info({})",
+                quote_identifier(schema),
+                quote_identifier(&table.name)
+            ),
+            None => format!("PRAGMA table_info({})", quote_identifier(&table.name)),
+        };
         TOKIO_RUNTIME
             .spawn(async move {
-                let query = format!("PRAGMA table_info({})", table_name);
                 let rows = sqlx::query(&query).fetch_all(&pool).await?;
 
                 let columns = rows
@@ -55,7 +84,7 @@ impl DatabaseClient for SqliteClient {
                         name: row.get("name"),
                         data_type: row.get("type"),
                         is_nullable: row.get::<i32, _>("notnull") == 0,
-                        is_primary_key: row.get::<i32, _>("pk") == 1,
+                        is_primary_key: row.get::<i32, _>("pk") > 0,
                     })
                     .collect();
 
@@ -69,49 +98,78 @@ impl DatabaseClient for SqliteClient {
         let query = query.to_string();
         TOKIO_RUNTIME
             .spawn(async move {
-                let rows = sqlx::query(&query).fetch_all(&pool).await?;
-
-                if rows.is_empty() {
-                    return Ok(QueryResult {
-                        columns: vec![],
-                        rows: vec![],
+                // Rows are decoded as they stream in, so only the typed
+                // columns are ever held in memory.
+                let mut rows = sqlx::query(&query).fetch(&pool);
+                let mut builder: Option<ResultBuilder> = None;
+                while let Some(row) = rows.try_next().await? {
+                    let builder = builder.get_or_insert_with(|| {
+                        ResultBuilder::new(
+                            row.columns().iter().map(|c| c.name().to_string()).collect(),
+                        )
                     });
-                }
-
-                let columns: Vec<String> = rows[0]
-                    .columns()
-                    .iter()
-                    .map(|c| c.name().to_string())
-                    .collect();
-                let mut result_rows = Vec::new();
-
-                for row in rows {
-                    let mut current_row = Vec::new();
-                    for (i, _) in columns.iter().enumerate() {
-                        // Try to get the value as different types and convert to string
-                        let val: String = if let Ok(v) = row.try_get::<i64, _>(i) {
-                            v.to_string()
-                        } else if let Ok(v) = row.try_get::<f64, _>(i) {
-                            v.to_string()
-                        } else if let Ok(v) = row.try_get::<String, _>(i) {
-                            v
-                        } else if let Ok(v) = row.try_get::<bool, _>(i) {
-                            v.to_string()
-                        } else {
-                            "NULL".to_string()
-                        };
-                        current_row.push(val);
+                    if !builder.push_row((0..row.len()).map(|i| decode_cell(&row, i))) {
+                        break;
                     }
-                    result_rows.push(current_row);
                 }
+                Ok(builder.map_or_else(QueryResult::empty, ResultBuilder::finish))
+            })
+            .await?
+    }
 
-                Ok(QueryResult {
-                    columns,
-                    rows: result_rows,
+    async fn count_rows(&self, table: &Table) -> Result<RowCount> {
+        let pool = self.pool.clone();
+        let query = format!("SELECT COUNT(*) FROM {}", quote_table(table));
+        TOKIO_RUNTIME
+            .spawn(async move {
+                let row = sqlx::query(&query).fetch_one(&pool).await?;
+                Ok(RowCount {
+                    rows: row.get::<i64, _>(0) as u64,
+                    exact: true,
                 })
             })
             .await?
     }
+
+    async fn get_functions(&self) -> Result<Vec<String>> {
+        let pool = self.pool.clone();
+        TOKIO_RUNTIME
+            .spawn(async move {
+                let functions = sqlx::query_scalar(
+                    "SELECT DISTINCT name FROM pragma_function_list ORDER BY name",
+                )
+                .fetch_all(&pool)
+                .await?;
+                Ok(functions)
+            })
+            .await?
+    }
+
+    fn error_location(&self, error: &anyhow::Error) -> Option<ErrorLocation> {
+        let error = error.downcast_ref::<sqlx::Error>()?.as_database_error()?;
+        error_location_from_message(error.message())
+    }
+
+    async fn close(&self) {
+        let pool = self.pool.clone();
+        let _ = TOKIO_RUNTIME.spawn(async move { pool.close().await }).await;
+    }
+}
+
+/// SQLite only names the offending token in its messages, e.g.
+/// `near "SELEC": syntax error` or `no such column: t.nme`.
+fn error_location_from_message(message: &str) -> Option<ErrorLocation> {
+    if let Some(rest) = message.strip_prefix("near \"") {
+        let (token, _) = rest.split_once("\":")?;
+        return Some(ErrorLocation::Near(token.to_string()));
+    }
+
+    let name = ["no such table: ", "no such column: ", "no such function: "]
+        .iter()
+        .find_map(|prefix| message.strip_prefix(prefix))?;
+    // Qualified names are reported whole; the last part is the unknown one.
+    let name = name.rsplit('.').next().unwrap_or(name);
+    Some(ErrorLocation::Near(name.to_string()))
 }
 
 #[cfg(test)]
@@ -124,6 +182,18 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_close() {
+        let client =
+            TOKIO_RUNTIME.block_on(async { SqliteClient::new("sqlite::memory:").await.unwrap() });
+
+        TOKIO_RUNTIME.block_on(async {
+            client.execute_query("SELECT 1").await.unwrap();
+            client.close().await;
+            assert!(client.execute_query("SELECT 1").await.is_err());
+        });
+    }
+
     #[test]
     fn test_get_tables() {
         let client =
@@ -154,8 +224,12 @@ mod tests {
                 .unwrap();
         });
 
-        let columns =
-            TOKIO_RUNTIME.block_on(async { client.get_columns("test_table").await.unwrap() });
+        let columns = TOKIO_RUNTIME.block_on(async {
+            client
+                .get_columns(&Table::unqualified("test_table"))
+                .await
+                .unwrap()
+        });
 
         assert_eq!(columns.len(), 2);
         assert!(columns.iter().any(|c| c.name == "id" && c.is_primary_key));
@@ -193,8 +267,72 @@ mod tests {
                 .unwrap()
         });
 
-        assert_eq!(result.rows.len(), 2);
-        assert_eq!(result.rows[0][1], "Alice");
-        assert_eq!(result.rows[1][1], "Bob");
+        assert_eq!(result.row_count(), 2);
+        assert_eq!(result.cell(0, 1), "Alice");
+        assert_eq!(result.cell(1, 1), "Bob");
+    }
+
+    #[test]
+    fn test_count_rows() {
+        let client =
+            TOKIO_RUNTIME.block_on(async { SqliteClient::new("sqlite::memory:").await.unwrap() });
+
+        TOKIO_RUNTIME.block_on(async {
+            client
+                .execute_query("CREATE TABLE test_table (id INTEGER PRIMARY KEY, name TEXT)")
+                .await
+                .unwrap();
+            client
+                .execute_query("INSERT INTO test_table (name) VALUES ('Alice'), ('Bob')")
+                .await
+                .unwrap();
+        });
+
+        let count = TOKIO_RUNTIME.block_on(async {
+            client
+                .count_rows(&Table::unqualified("test_table"))
+                .await
+                .unwrap()
+        });
+
+        assert_eq!(
+            count,
+            RowCount {
+                rows: 2,
+                exact: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_get_functions() {
+        let client =
+            TOKIO_RUNTIME.block_on(async { SqliteClient::new("sqlite::memory:").await.unwrap() });
+
+        let functions = TOKIO_RUNTIME.block_on(async { client.get_functions().await.unwrap() });
+
+        assert!(functions.iter().any(|f| f == "substr"));
+    }
+
+    #[test]
+    fn test_error_location() {
+        let client =
+            TOKIO_RUNTIME.block_on(async { SqliteClient::new("sqlite::memory:").await.unwrap() });
+
+        let location = |query: &str| {
+            let error = TOKIO_RUNTIME
+                .block_on(async { client.execute_query(query).await })
+                .unwrap_err();
+            client.error_location(&error)
+        };
+
+        assert_eq!(
+            location("SELEC 1"),
+            Some(ErrorLocation::Near("SELEC".to_string()))
+        );
+        assert_eq!(
+            location("SELECT * FROM missing_table"),
+            Some(ErrorLocation::Near("missing_table".to_string()))
+        );
     }
 }
@@ -1,3 +1,5 @@
+use super::QueryResult;
+use crate::error::AppError;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
@@ -7,6 +9,16 @@ pub struct Table {
     pub schema: Option<String>,
 }
 
+impl Table {
+    /// A table named without a schema, found on the search path.
+    pub fn unqualified(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            schema: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct Column {
@@ -16,16 +28,41 @@ pub struct Column {
     pub is_primary_key: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct QueryResult {
-    pub columns: Vec<String>,
-    pub rows: Vec<Vec<String>>,
+/// Number of rows in a table. Large PostgreSQL tables report the planner
+/// estimate instead of running a full `COUNT(*)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowCount {
+    pub rows: u64,
+    pub exact: bool,
+}
+
+/// Where in the query text the database placed an error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorLocation {
+    /// Zero-based character offset into the query.
+    Offset(usize),
+    /// The token the error was reported near.
+    Near(String),
 }
 
 #[async_trait::async_trait]
 #[allow(dead_code)]
 pub trait DatabaseClient: Send + Sync {
     async fn get_tables(&self) -> Result<Vec<Table>>;
-    async fn get_columns(&self, table_name: &str) -> Result<Vec<Column>>;
+    async fn get_columns(&self, table: &Table) -> Result<Vec<Column>>;
     async fn execute_query(&self, query: &str) -> Result<QueryResult>;
+    async fn count_rows(&self, table: &Table) -> Result<RowCount>;
+    /// Names of callable functions, used for editor completions.
+    async fn get_functions(&self) -> Result<Vec<String>>;
+    /// Where a failed `execute_query` error points in the query, if the
+    /// database reported it.
+    fn error_location(&self, error: &anyhow::Error) -> Option<ErrorLocation>;
+    /// A failed call's error, classified, with its location in the query.
+    fn describe_error(&self, error: &anyhow::Error) -> AppError {
+        let mut app_error = AppError::from_error(error);
+        app_error.details.position = self.error_location(error);
+        app_error
+    }
+    /// Close the connection pool; later queries fail.
+    async fn close(&self);
 }
@@ -1,2 +1,259 @@
-// Error handling and custom error types for the application.
-// Currently unused as we use anyhow::Result directly.
+// Typed errors for failures shown to the user.
+//
+// Database errors are classified by their SQLSTATE on PostgreSQL and by the
+// primary result code on SQLite, and keep the structured fields the server
+// sent (detail, hint, the constraint, table and column involved) so the
+// error panel can show them apart from the message. Anything that isn't a
+// database error is classified from the driver error it wraps.
+use crate::db::ErrorLocation;
+use sqlx::postgres::PgDatabaseError;
+use sqlx::sqlite::SqliteError;
+use std::fmt;
+
+/// What went wrong, broadly enough to pick how to present it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The server couldn't be reached, or the connection dropped.
+    Connection,
+    /// The login was rejected.
+    Auth,
+    /// The query is malformed or names something that doesn't exist.
+    Syntax,
+    /// A unique, foreign key, not-null or check constraint was violated.
+    Constraint,
+    /// The user lacks a privilege, or the database is read-only.
+    Permission,
+    /// A statement, lock or pool timeout expired.
+    Timeout,
+    /// The query was cancelled before it finished.
+    Cancelled,
+    /// Anything else the driver or database reported.
+    Driver,
+}
+
+impl ErrorKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            ErrorKind::Connection => "Connection error",
+            ErrorKind::Auth => "Authentication failed",
+            ErrorKind::Syntax => "Query error",
+            ErrorKind::Constraint => "Constraint violation",
+            ErrorKind::Permission => "Permission denied",
+            ErrorKind::Timeout => "Timed out",
+            ErrorKind::Cancelled => "Cancelled",
+            ErrorKind::Driver => "Database error",
+        }
+    }
+}
+
+/// Structured fields of a database error, where the database reported them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorDetails {
+    /// Five-character SQLSTATE code; PostgreSQL only.
+    pub sqlstate: Option<String>,
+    pub detail: Option<String>,
+    pub hint: Option<String>,
+    pub schema: Option<String>,
+    pub table: Option<String>,
+    pub column: Option<String>,
+    pub constraint: Option<String>,
+    /// Where in the query the error was reported.
+    pub position: Option<ErrorLocation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppError {
+    pub kind: ErrorKind,
+    /// The database's own message, without the driver's prefix.
+    pub message: String,
+    pub details: ErrorDetails,
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind.label(), self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl AppError {
+    /// Classify an error returned by a database client. The position is
+    /// left for the client to fill in, see `DatabaseClient::describe_error`.
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        if let Some(error) = error.downcast_ref::<AppError>() {
+            return error.clone();
+        }
+        if let Some(error) = error.downcast_ref::<sqlx::Error>() {
+            return Self::from_sqlx(error);
+        }
+        let kind = if error
+            .downcast_ref::<tokio::time::error::Elapsed>()
+            .is_some()
+        {
+            ErrorKind::Timeout
+        } else if error
+            .downcast_ref::<tokio::task::JoinError>()
+            .is_some_and(|e| e.is_cancelled())
+        {
+            ErrorKind::Cancelled
+        } else {
+            ErrorKind::Driver
+        };
+        Self::new(kind, error.to_string())
+    }
+
+    fn new(kind: ErrorKind, message: String) -> Self {
+        Self {
+            kind,
+            message,
+            details: ErrorDetails::default(),
+        }
+    }
+
+    fn from_sqlx(error: &sqlx::Error) -> Self {
+        let kind = match error {
+            sqlx::Error::Database(_) => return Self::from_database(error),
+            sqlx::Error::Io(e) if e.kind() == std::io::ErrorKind::TimedOut => ErrorKind::Timeout,
+            sqlx::Error::PoolTimedOut => ErrorKind::Timeout,
+            sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::Configuration(_)
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed => ErrorKind::Connection,
+            _ => ErrorKind::Driver,
+        };
+        Self::new(kind, error.to_string())
+    }
+
+    fn from_database(error: &sqlx::Error) -> Self {
+        let Some(db_error) = error.as_database_error() else {
+            return Self::new(ErrorKind::Driver, error.to_string());
+        };
+        let message = db_error.message().to_string();
+
+        if let Some(pg) = db_error.try_downcast_ref::<PgDatabaseError>() {
+            let owned = |s: Option<&str>| s.map(str::to_string);
+            return Self {
+                kind: classify_sqlstate(pg.code(), &message),
+                message,
+                details: ErrorDetails {
+                    sqlstate: Some(pg.code().to_string()),
+                    detail: owned(pg.detail()),
+                    hint: owned(pg.hint()),
+                    schema: owned(pg.schema()),
+                    table: owned(pg.table()),
+                    column: owned(pg.column()),
+                    constraint: owned(pg.constraint()),
+                    position: None,
+                },
+            };
+        }
+
+        let kind = match db_error.try_downcast_ref::<SqliteError>() {
+            Some(_) => db_error
+                .code()
+                .and_then(|code| code.parse::<i32>().ok())
+                .map_or(ErrorKind::Driver, classify_sqlite_code),
+            None => ErrorKind::Driver,
+        };
+        Self {
+            kind,
+            message,
+            details: ErrorDetails {
+                table: db_error.table().map(str::to_string),
+                constraint: db_error.constraint().map(str::to_string),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Map a PostgreSQL SQLSTATE to a kind, by its class where that suffices.
+fn classify_sqlstate(code: &str, message: &str) -> ErrorKind {
+    match code {
+        // query_canceled covers both a cancel request and statement_timeout.
+        "57014" if message.contains("timeout") => ErrorKind::Timeout,
+        "57014" => ErrorKind::Cancelled,
+        "55P03" | "25P03" => ErrorKind::Timeout,
+        // insufficient_privilege, read_only_sql_transaction
+        "42501" | "25006" => ErrorKind::Permission,
+        "3D000" | "57P01" | "57P02" | "57P03" => ErrorKind::Connection,
+        _ => match &code[..code.len().min(2)] {
+            "08" => ErrorKind::Connection,
+            "28" => ErrorKind::Auth,
+            "23" => ErrorKind::Constraint,
+            "42" | "22" => ErrorKind::Syntax,
+            _ => ErrorKind::Driver,
+        },
+    }
+}
+
+/// Map a SQLite extended result code to a kind by its primary code.
+fn classify_sqlite_code(code: i32) -> ErrorKind {
+    match code & 0xff {
+        // SQLITE_ERROR: syntax errors and unknown tables, columns or functions.
+        1 => ErrorKind::Syntax,
+        // SQLITE_PERM, SQLITE_READONLY, SQLITE_AUTH
+        3 | 8 | 23 => ErrorKind::Permission,
+        // SQLITE_BUSY, SQLITE_LOCKED
+        5 | 6 => ErrorKind::Timeout,
+        // SQLITE_INTERRUPT
+        9 => ErrorKind::Cancelled,
+        // SQLITE_CANTOPEN, SQLITE_NOTADB
+        14 | 26 => ErrorKind::Connection,
+        // SQLITE_CONSTRAINT
+        19 => ErrorKind::Constraint,
+        _ => ErrorKind::Driver,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{DatabaseClient, SqliteClient, TOKIO_RUNTIME};
+
+    #[test]
+    fn test_classify_sqlstate() {
+        assert_eq!(classify_sqlstate("23505", ""), ErrorKind::Constraint);
+        assert_eq!(classify_sqlstate("42601", ""), ErrorKind::Syntax);
+        assert_eq!(classify_sqlstate("42501", ""), ErrorKind::Permission);
+        assert_eq!(classify_sqlstate("28P01", ""), ErrorKind::Auth);
+        assert_eq!(classify_sqlstate("08006", ""), ErrorKind::Connection);
+        assert_eq!(
+            classify_sqlstate("57014", "canceling statement due to statement timeout"),
+            ErrorKind::Timeout
+        );
+        assert_eq!(
+            classify_sqlstate("57014", "canceling statement due to user request"),
+            ErrorKind::Cancelled
+        );
+        assert_eq!(classify_sqlstate("XX000", ""), ErrorKind::Driver);
+    }
+
+    #[test]
+    fn test_sqlite_errors() {
+        let client =
+            TOKIO_RUNTIME.block_on(async { SqliteClient::new("sqlite::memory:").await.unwrap() });
+        let execute = |query: &str| TOKIO_RUNTIME.block_on(client.execute_query(query));
+        let describe = |query: &str| client.describe_error(&execute(query).unwrap_err());
+
+        execute("CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT NOT NULL)").unwrap();
+        let error = describe("SELEC 1");
+        assert_eq!(error.kind, ErrorKind::Syntax);
+        assert_eq!(error.message, "near \"SELEC\": syntax error");
+        assert_eq!(
+            error.details.position,
+            Some(ErrorLocation::Near("SELEC".to_string()))
+        );
+
+        execute("INSERT INTO t VALUES (1, 'a')").unwrap();
+        let error = describe("INSERT INTO t VALUES (1, 'b')");
+        assert_eq!(error.kind, ErrorKind::Constraint);
+        assert!(
+            error
+                .to_string()
+                .starts_with("Constraint violation: UNIQUE")
+        );
+    }
+}
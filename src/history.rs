@@ -0,0 +1,244 @@
+// Query history, persisted as JSON lines next to the connection list.
+//
+// Entries are appended as queries run, so a crash loses at most the query in
+// flight. The file is compacted on load once it grows past `MAX_ENTRIES`.
+use crate::vault::write_private;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+const HISTORY_FILE_NAME: &str = "history.jsonl";
+
+/// Oldest entries beyond this are dropped.
+const MAX_ENTRIES: usize = 5_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub query: String,
+    pub connection: String,
+    pub executed_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    /// Rows returned; `None` when the query failed.
+    pub rows: Option<usize>,
+    pub error: Option<String>,
+}
+
+impl HistoryEntry {
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatusFilter {
+    #[default]
+    All,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HistoryFilter {
+    /// Whitespace-separated terms that must all appear in the query or its
+    /// error, ignoring case.
+    pub text: String,
+    pub connection: Option<String>,
+    pub status: StatusFilter,
+}
+
+impl HistoryFilter {
+    fn matches(&self, entry: &HistoryEntry) -> bool {
+        let status = match self.status {
+            StatusFilter::All => true,
+            StatusFilter::Succeeded => entry.succeeded(),
+            StatusFilter::Failed => !entry.succeeded(),
+        };
+        let connection = self
+            .connection
+            .as_ref()
+            .is_none_or(|c| *c == entry.connection);
+
+        let haystack = format!(
+            "{}\n{}",
+            entry.query,
+            entry.error.as_deref().unwrap_or_default()
+        )
+        .to_lowercase();
+        let text = self
+            .text
+            .split_whitespace()
+            .all(|term| haystack.contains(&term.to_lowercase()));
+
+        status && connection && text
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct QueryHistory {
+    /// Oldest first, as stored on disk.
+    entries: Vec<HistoryEntry>,
+    path: Option<PathBuf>,
+}
+
+impl QueryHistory {
+    /// Load the history from the app's config directory.
+    pub fn load() -> Result<Self> {
+        let path = crate::persistence::get_config_dir()?.join(HISTORY_FILE_NAME);
+        Self::open(path)
+    }
+
+    fn open(path: PathBuf) -> Result<Self> {
+        let mut entries = Vec::new();
+        if path.exists() {
+            // A line cut short by a crash should not cost the whole history.
+            entries = fs::read_to_string(&path)?
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect();
+        }
+
+        let mut history = Self {
+            entries,
+            path: Some(path),
+        };
+        if history.entries.len() > MAX_ENTRIES {
+            history.entries.drain(..history.entries.len() - MAX_ENTRIES);
+            history.rewrite()?;
+        }
+        Ok(history)
+    }
+
+    /// Remember `entry` and append it to the history file.
+    pub fn record(&mut self, entry: HistoryEntry) -> Result<()> {
+        let line = serde_json::to_string(&entry)?;
+        self.entries.push(entry);
+        if let Some(path) = &self.path {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}", line)?;
+        }
+        Ok(())
+    }
+
+    /// Entries matching `filter`, newest first.
+    pub fn search(&self, filter: &HistoryFilter) -> Vec<&HistoryEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .filter(|e| filter.matches(e))
+            .collect()
+    }
+
+    /// Connection names that appear in the history, sorted.
+    pub fn connections(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.entries.iter().map(|e| e.connection.as_str()).collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    fn rewrite(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut contents = String::new();
+        for entry in &self.entries {
+            contents.push_str(&serde_json::to_string(entry)?);
+            contents.push('\n');
+        }
+        write_private(path, &contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(query: &str, connection: &str, error: Option<&str>) -> HistoryEntry {
+        HistoryEntry {
+            query: query.to_string(),
+            connection: connection.to_string(),
+            executed_at: Utc::now(),
+            duration_ms: 3,
+            rows: error.is_none().then_some(1),
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_record_and_reload() {
+        let path =
+            std::env::temp_dir().join(format!("slick-dataui-history-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut history = QueryHistory::open(path.clone()).unwrap();
+        history.record(entry("SELECT 1", "local", None)).unwrap();
+        history
+            .record(entry("SELEC 2", "prod", Some("syntax error")))
+            .unwrap();
+        // A torn write at the end of the file is skipped on load.
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"query\": \"SEL")
+            .unwrap();
+
+        let reloaded = QueryHistory::open(path.clone()).unwrap();
+        assert_eq!(reloaded.entries, history.entries);
+        assert_eq!(reloaded.connections(), vec!["local", "prod"]);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_search() {
+        let mut history = QueryHistory::default();
+        history
+            .record(entry("SELECT * FROM users", "local", None))
+            .unwrap();
+        history
+            .record(entry(
+                "SELECT * FROM orders",
+                "prod",
+                Some("permission denied"),
+            ))
+            .unwrap();
+        history
+            .record(entry("select name from Users", "prod", None))
+            .unwrap();
+
+        let queries = |filter: HistoryFilter| -> Vec<String> {
+            history
+                .search(&filter)
+                .into_iter()
+                .map(|e| e.query.clone())
+                .collect()
+        };
+
+        assert_eq!(
+            queries(HistoryFilter {
+                text: "users select".to_string(),
+                ..Default::default()
+            }),
+            vec!["select name from Users", "SELECT * FROM users"]
+        );
+        assert_eq!(
+            queries(HistoryFilter {
+                connection: Some("prod".to_string()),
+                status: StatusFilter::Failed,
+                ..Default::default()
+            }),
+            vec!["SELECT * FROM orders"]
+        );
+        assert_eq!(
+            queries(HistoryFilter {
+                text: "denied".to_string(),
+                ..Default::default()
+            }),
+            vec!["SELECT * FROM orders"]
+        );
+    }
+}
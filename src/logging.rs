@@ -0,0 +1,306 @@
+// Logging and crash reporting, with secrets scrubbed before anything leaves
+// the process.
+//
+// Every log field passes through the redactor: passwords in connection URLs
+// and `password=` style options are masked everywhere, and fields that carry
+// SQL (`sql`, `query`) also lose their string and number literals. Database
+// errors (`error`) quote SQL and row values, so they lose those too. Sentry
+// events go through the same scrubbing in `before_send`, and are dropped
+// entirely while crash reporting is switched off.
+use crate::sql::lexer::{TokenKind, tokenize};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::field::MakeExt;
+use tracing_subscriber::fmt::format::{Writer, debug_fn};
+
+/// Overrides the configured log level, e.g. `SLICK_DATAUI_LOG=debug`.
+const LOG_ENV_VAR: &str = "SLICK_DATAUI_LOG";
+/// Log fields holding SQL text, whose literals are redacted.
+const SQL_FIELDS: [&str; 2] = ["sql", "query"];
+/// Log fields holding errors, which may quote SQL or row values.
+const ERROR_FIELDS: [&str; 1] = ["error"];
+/// Phrases in PostgreSQL error details followed by a parenthesized list of
+/// row values, e.g. `Key (email)=(a@b.com) already exists.`
+const VALUE_LIST_MARKERS: [&str; 2] = [")=(", "Failing row contains ("];
+/// Option names whose values are always masked.
+const SECRET_KEYS: [&str; 6] = ["password", "passwd", "pwd", "token", "secret", "api_key"];
+const MASK: &str = "****";
+
+static CRASH_REPORTS: AtomicBool = AtomicBool::new(true);
+
+/// The `telemetry` section of `settings.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetrySettings {
+    /// A `tracing` filter such as `info` or `slick_dataui=debug`.
+    pub log_level: String,
+    /// Send crash reports and errors to Sentry.
+    pub crash_reports: bool,
+}
+
+impl Default for TelemetrySettings {
+    fn default() -> Self {
+        Self {
+            log_level: "info".to_string(),
+            crash_reports: true,
+        }
+    }
+}
+
+/// Install the global `tracing` subscriber, writing redacted logs to stderr.
+pub fn init_logging(settings: &TelemetrySettings) {
+    let filter = EnvFilter::try_from_env(LOG_ENV_VAR)
+        .or_else(|_| EnvFilter::try_new(&settings.log_level))
+        .unwrap_or_else(|_| EnvFilter::new("info"));
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .fmt_fields(debug_fn(format_field).delimited(" "))
+        .try_init();
+}
+
+fn format_field(
+    writer: &mut Writer<'_>,
+    field: &tracing::field::Field,
+    value: &dyn fmt::Debug,
+) -> fmt::Result {
+    let text = format!("{:?}", value);
+    let text = if SQL_FIELDS.contains(&field.name()) {
+        redact_sql(&redact_secrets(&text))
+    } else if ERROR_FIELDS.contains(&field.name()) {
+        redact_text(&text)
+    } else {
+        redact_secrets(&text)
+    };
+    if field.name() == "message" {
+        write!(writer, "{}", text)
+    } else {
+        write!(writer, "{}={}", field.name(), text)
+    }
+}
+
+/// Start Sentry unless crash reporting is off. The returned guard flushes
+/// pending events when dropped.
+pub fn init_crash_reporting(settings: &TelemetrySettings) -> Option<sentry::ClientInitGuard> {
+    set_crash_reports(settings.crash_reports);
+    if !settings.crash_reports {
+        return None;
+    }
+    Some(sentry::init((
+        option_env!("SENTRY_DSN"),
+        sentry::ClientOptions {
+            release: sentry::release_name!(),
+            send_default_pii: false,
+            before_send: Some(Arc::new(scrub_event)),
+            before_breadcrumb: Some(Arc::new(|mut breadcrumb| {
+                breadcrumb.message = breadcrumb.message.map(|m| redact_text(&m));
+                breadcrumb.data.clear();
+                Some(breadcrumb)
+            })),
+            ..Default::default()
+        },
+    )))
+}
+
+/// Switch crash reporting on or off for the rest of the session.
+pub fn set_crash_reports(enabled: bool) {
+    CRASH_REPORTS.store(enabled, Ordering::Relaxed);
+}
+
+fn scrub_event(
+    mut event: sentry::protocol::Event<'static>,
+) -> Option<sentry::protocol::Event<'static>> {
+    if !CRASH_REPORTS.load(Ordering::Relaxed) {
+        return None;
+    }
+    event.message = event.message.map(|m| redact_text(&m));
+    if let Some(entry) = &mut event.logentry {
+        entry.message = redact_text(&entry.message);
+        entry.params.clear();
+    }
+    for exception in &mut event.exception.values {
+        exception.value = exception.value.as_deref().map(redact_text);
+    }
+    for breadcrumb in &mut event.breadcrumbs.values {
+        breadcrumb.message = breadcrumb.message.as_deref().map(redact_text);
+        breadcrumb.data.clear();
+    }
+    event.extra.clear();
+    event.request = None;
+    event.user = None;
+    event.server_name = None;
+    Some(event)
+}
+
+/// Free text that may quote SQL, such as a database error.
+fn redact_text(text: &str) -> String {
+    redact_sql(&redact_value_lists(&redact_secrets(text)))
+}
+
+/// Replace the row values PostgreSQL quotes in error details with `?`.
+fn redact_value_lists(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some((at, marker)) = VALUE_LIST_MARKERS
+        .iter()
+        .filter_map(|m| rest.find(m).map(|at| (at, m)))
+        .min()
+    {
+        let (before, after) = rest.split_at(at + marker.len());
+        out.push_str(before);
+        // Values may contain parentheses of their own.
+        let mut depth = 1;
+        let end = after
+            .char_indices()
+            .find(|&(_, c)| {
+                match c {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => {}
+                }
+                depth == 0
+            })
+            .map_or(after.len(), |(i, _)| i);
+        out.push('?');
+        rest = &after[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Mask passwords in connection URLs and `password=` style options.
+pub fn redact_secrets(text: &str) -> String {
+    redact_options(&redact_url_passwords(text))
+}
+
+fn redact_url_passwords(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(ix) = rest.find("://") {
+        let (before, after) = rest.split_at(ix + 3);
+        out.push_str(before);
+        // User info ends at the last `@` before the host.
+        let authority_end = after
+            .find(|c: char| {
+                c == '/' || c == '?' || c == '#' || c.is_whitespace() || c == '\'' || c == '"'
+            })
+            .unwrap_or(after.len());
+        let authority = &after[..authority_end];
+        match authority.rfind('@').and_then(|at| {
+            let colon = authority[..at].find(':')?;
+            Some((colon, at))
+        }) {
+            Some((colon, at)) => {
+                out.push_str(&authority[..=colon]);
+                out.push_str(MASK);
+                out.push_str(&authority[at..]);
+            }
+            None => out.push_str(authority),
+        }
+        rest = &after[authority_end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn redact_options(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(eq) = rest.find('=') {
+        let key_start = rest[..eq]
+            .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .map_or(0, |ix| ix + 1);
+        let key = &rest[key_start..eq];
+        out.push_str(&rest[..=eq]);
+        rest = &rest[eq + 1..];
+        if SECRET_KEYS.iter().any(|k| key.eq_ignore_ascii_case(k)) {
+            let value_end = rest
+                .find(|c: char| {
+                    c == '&' || c == ';' || c == ',' || c == '\'' || c == '"' || c.is_whitespace()
+                })
+                .unwrap_or(rest.len());
+            if value_end > 0 {
+                out.push_str(MASK);
+            }
+            rest = &rest[value_end..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Replace string and number literals in SQL with `?`, keeping its shape.
+pub fn redact_sql(sql: &str) -> String {
+    tokenize(sql)
+        .into_iter()
+        .map(|token| match token.kind {
+            TokenKind::String | TokenKind::Number => "?",
+            _ => token.text,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_secrets() {
+        assert_eq!(
+            redact_secrets("Connecting to postgres://admin:s3cr%40t@db:5432/app failed"),
+            "Connecting to postgres://admin:****@db:5432/app failed"
+        );
+        assert_eq!(
+            redact_secrets("postgres://admin@db/app and sqlite:///tmp/a.db"),
+            "postgres://admin@db/app and sqlite:///tmp/a.db"
+        );
+        assert_eq!(
+            redact_secrets("host=db password=hunter2 user=me; api_key=abc&x=1"),
+            "host=db password=**** user=me; api_key=****&x=1"
+        );
+    }
+
+    #[test]
+    fn test_redact_sql() {
+        assert_eq!(
+            redact_sql("SELECT * FROM users WHERE email = 'a@b.com' AND id > 42 -- note"),
+            "SELECT * FROM users WHERE email = ? AND id > ? -- note"
+        );
+        assert_eq!(redact_sql("SELECT t1.c2 FROM t1"), "SELECT t1.c2 FROM t1");
+    }
+
+    #[test]
+    fn test_redact_database_errors() {
+        let error = "duplicate key value violates unique constraint \"users_email_key\"\n\
+                     DETAIL: Key (email)=(jane@example.com) already exists.";
+        assert_eq!(
+            redact_text(error),
+            "duplicate key value violates unique constraint \"users_email_key\"\n\
+             DETAIL: Key (email)=(?) already exists."
+        );
+        assert_eq!(
+            redact_text("DETAIL: Failing row contains (7, null, f(x)), Key (a, b)=(1, 2)"),
+            "DETAIL: Failing row contains (?), Key (a, b)=(?)"
+        );
+    }
+
+    #[test]
+    fn test_scrub_event() {
+        let event = sentry::protocol::Event {
+            message: Some("query failed: INSERT INTO t VALUES ('secret')".to_string()),
+            ..Default::default()
+        };
+        let scrubbed = scrub_event(event.clone()).unwrap();
+        assert_eq!(
+            scrubbed.message.as_deref(),
+            Some("query failed: INSERT INTO t VALUES (?)")
+        );
+
+        set_crash_reports(false);
+        assert!(scrub_event(event).is_none());
+        set_crash_reports(true);
+    }
+}
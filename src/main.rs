@@ -1,23 +1,44 @@
+mod cli;
+mod column_layout;
 mod db;
 mod error;
+mod history;
+mod import;
+mod launch;
+mod logging;
+mod notifications;
 mod persistence;
+mod saved_queries;
+mod share;
+mod sql;
 mod state;
 mod table_delegate;
+mod tabs;
 mod ui;
+mod vault;
 
+use clap::Parser;
 use gpui::{AppContext, Application, WindowOptions};
 use gpui_component::Root;
 use state::GlobalAppState;
 use ui::MainLayout;
 
 fn main() {
-    let _guard = sentry::init((
-        option_env!("SENTRY_DSN"),
-        sentry::ClientOptions {
-            release: sentry::release_name!(),
-            ..Default::default()
-        },
-    ));
+    let cli = cli::Cli::parse();
+    let settings = persistence::load_settings()
+        .map(|(settings, _)| settings)
+        .unwrap_or_default();
+    logging::init_logging(&settings.telemetry);
+    db::set_result_limits(&settings.results);
+    let guard = logging::init_crash_reporting(&settings.telemetry);
+
+    if let Some(command) = cli.command {
+        let code = cli::run(command);
+        drop(guard);
+        std::process::exit(code);
+    }
+
+    let (targets, target_errors) = launch::parse_targets(&cli.open);
 
     let app = Application::new().with_assets(gpui_component_assets::Assets);
 
@@ -29,7 +50,11 @@ fn main() {
             cx.open_window(WindowOptions::default(), |window, cx| {
                 let app_state_entity = cx.new(|_| state::AppState::new());
                 let app_state = GlobalAppState::new(app_state_entity.clone());
-                let view = cx.new(|cx| MainLayout::new(app_state, window, cx));
+                let view = cx.new(|cx| {
+                    let mut layout = MainLayout::new(app_state, window, cx);
+                    layout.open_launch_targets(targets, target_errors, window, cx);
+                    layout
+                });
                 // This first level on the window, should be a Root.
                 cx.new(|cx| Root::new(view, window, cx))
             })?;
@@ -0,0 +1,171 @@
+// Notifications: short messages shown as toasts and kept in a history.
+//
+// Anything that used to set a one-off error string pushes a notification
+// instead. It shows as a toast until it expires or is dismissed, and stays
+// in the history drawer (newest last, capped) for the rest of the session so
+// details can still be copied after the toast is gone.
+use chrono::{DateTime, Utc};
+use std::time::{Duration, Instant};
+
+/// Oldest notifications are dropped past this many.
+const HISTORY_LIMIT: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Level {
+    /// How long the toast stays up. Problems stay longer so they can be read.
+    pub fn timeout(self) -> Duration {
+        match self {
+            Level::Info | Level::Success => Duration::from_secs(4),
+            Level::Warning => Duration::from_secs(8),
+            Level::Error => Duration::from_secs(12),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub id: usize,
+    pub level: Level,
+    pub title: String,
+    /// The underlying error or other text worth copying.
+    pub details: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Notification {
+    /// Title and details, for the clipboard.
+    pub fn copy_text(&self) -> String {
+        match &self.details {
+            Some(details) => format!("{}\n\n{}", self.title, details),
+            None => self.title.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Notifications {
+    history: Vec<Notification>,
+    /// Ids shown as toasts, with when each one goes away.
+    toasts: Vec<(usize, Instant)>,
+    next_id: usize,
+}
+
+impl Notifications {
+    pub fn push(
+        &mut self,
+        level: Level,
+        title: impl Into<String>,
+        details: Option<String>,
+    ) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.history.push(Notification {
+            id,
+            level,
+            title: title.into(),
+            details,
+            created_at: Utc::now(),
+        });
+        if self.history.len() > HISTORY_LIMIT {
+            let dropped = self.history.remove(0);
+            self.toasts.retain(|(id, _)| *id != dropped.id);
+        }
+        self.toasts.push((id, Instant::now() + level.timeout()));
+        id
+    }
+
+    pub fn info(&mut self, title: impl Into<String>) {
+        self.push(Level::Info, title, None);
+    }
+
+    pub fn success(&mut self, title: impl Into<String>) {
+        self.push(Level::Success, title, None);
+    }
+
+    pub fn warning(&mut self, title: impl Into<String>, details: impl ToString) {
+        self.push(Level::Warning, title, Some(details.to_string()));
+    }
+
+    /// An error, with the error itself as the details.
+    pub fn error(&mut self, title: impl Into<String>, error: impl ToString) {
+        self.push(Level::Error, title, Some(error.to_string()));
+    }
+
+    /// Notifications currently shown as toasts, oldest first.
+    pub fn toasts(&self) -> impl Iterator<Item = &Notification> {
+        self.toasts
+            .iter()
+            .filter_map(|(id, _)| self.history.iter().find(|n| n.id == *id))
+    }
+
+    /// Every notification this session, oldest first.
+    pub fn history(&self) -> &[Notification] {
+        &self.history
+    }
+
+    /// Hide a toast; it stays in the history.
+    pub fn dismiss(&mut self, id: usize) {
+        self.toasts.retain(|(toast, _)| *toast != id);
+    }
+
+    /// Hide toasts whose time is up.
+    pub fn dismiss_expired(&mut self, now: Instant) {
+        self.toasts.retain(|(_, expires)| *expires > now);
+    }
+
+    /// When the next toast expires, if any are shown.
+    pub fn next_expiry(&self) -> Option<Instant> {
+        self.toasts.iter().map(|(_, expires)| *expires).min()
+    }
+
+    pub fn clear(&mut self) {
+        self.history.clear();
+        self.toasts.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toasts_expire_into_history() {
+        let mut notifications = Notifications::default();
+        notifications.success("Saved");
+        notifications.error("Failed to connect", "connection refused");
+        assert_eq!(notifications.toasts().count(), 2);
+
+        let start = Instant::now();
+        assert_eq!(notifications.next_expiry().map(|t| t > start), Some(true));
+        notifications.dismiss_expired(start + Level::Success.timeout() + Duration::from_millis(1));
+        let toasts: Vec<&str> = notifications.toasts().map(|n| n.title.as_str()).collect();
+        assert_eq!(toasts, vec!["Failed to connect"]);
+
+        let id = notifications.toasts().next().unwrap().id;
+        notifications.dismiss(id);
+        assert!(notifications.next_expiry().is_none());
+        assert_eq!(notifications.history().len(), 2);
+        assert_eq!(
+            notifications.history()[1].copy_text(),
+            "Failed to connect\n\nconnection refused"
+        );
+    }
+
+    #[test]
+    fn test_history_is_capped() {
+        let mut notifications = Notifications::default();
+        for i in 0..HISTORY_LIMIT + 5 {
+            notifications.push(Level::Info, format!("n{}", i), None);
+        }
+        assert_eq!(notifications.history().len(), HISTORY_LIMIT);
+        assert_eq!(notifications.history()[0].title, "n5");
+        assert_eq!(notifications.toasts().count(), HISTORY_LIMIT);
+    }
+}
@@ -1,24 +1,93 @@
+use crate::db::ResultSettings;
+use crate::logging::TelemetrySettings;
+use crate::sql::format::FormatOptions;
 use crate::state::{ConnectionConfig, DatabaseType};
-use anyhow::Result;
+use crate::vault::{Vault, VaultLocked, write_private};
+use anyhow::{Result, anyhow, bail};
 use keyring::Entry;
+use migrate::{CURRENT_VERSION, ConfigFile};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+mod migrate;
 
 const SERVICE_NAME: &str = "com.slick-dataui.app";
 const CONFIG_DIR_NAME: &str = "slick-dataui";
 const CONNECTIONS_FILE_NAME: &str = "connections.json";
+const SETTINGS_FILE_NAME: &str = "settings.json";
+
+/// User preferences, read from `settings.json` in the config directory.
+/// Missing keys take their defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub format: FormatOptions,
+    pub telemetry: TelemetrySettings,
+    pub results: ResultSettings,
+}
+
+/// Where a connection's secret is kept. Never `connections.json` itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SecretStore {
+    Keyring,
+    /// The encrypted vault file, used when the keyring is unavailable.
+    Vault,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 struct ConnectionMetadata {
     name: String,
     db_type: DatabaseType,
-    // Fallback for when system keyring fails (e.g. dev environment)
-    // This is NOT secure, but ensures the app works.
+    /// Connection URL or file path without its password. Missing in files
+    /// from versions that kept the whole URL as the secret.
+    #[serde(default)]
+    connection_string: Option<String>,
+    #[serde(default)]
+    ask_password: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shared_from: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    needs_credentials: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    secret: Option<SecretStore>,
+    /// Plaintext copy of the connection string written by older versions.
+    /// Read once so it can be moved somewhere safe, never written again.
+    #[serde(default, skip_serializing)]
     unsafe_password: Option<String>,
 }
 
-fn get_config_dir() -> Result<PathBuf> {
+/// `settings.json` as written, with its schema version.
+#[derive(Serialize)]
+struct SettingsDocument<'a> {
+    version: u32,
+    #[serde(flatten)]
+    settings: &'a Settings,
+}
+
+/// `connections.json` as written, with its schema version.
+#[derive(Serialize)]
+struct ConnectionsDocument {
+    version: u32,
+    connections: Vec<ConnectionMetadata>,
+}
+
+/// Saved connections, and the entries of `connections.json` that couldn't
+/// be read.
+#[derive(Debug, Default)]
+pub struct LoadedConnections {
+    pub connections: Vec<ConnectionConfig>,
+    /// Secrets are still stored the way older versions did (in plaintext,
+    /// or as whole URLs), which the next successful [`save_connections`]
+    /// fixes.
+    pub migration_pending: bool,
+    /// One message per entry that was skipped.
+    pub skipped: Vec<String>,
+}
+
+pub fn get_config_dir() -> Result<PathBuf> {
     let config_dir = dirs::config_dir()
         .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?
         .join(CONFIG_DIR_NAME);
@@ -34,101 +103,370 @@ fn get_connections_file_path() -> Result<PathBuf> {
     Ok(get_config_dir()?.join(CONNECTIONS_FILE_NAME))
 }
 
-pub fn save_connections(connections: &[ConnectionConfig]) -> Result<()> {
-    println!("Saving {} connections...", connections.len());
-    // 1. Save metadata to JSON
-    let metadata: Vec<ConnectionMetadata> = connections
-        .iter()
-        .map(|c| ConnectionMetadata {
-            name: c.name.clone(),
-            db_type: c.db_type.clone(),
-            // We store the password here as a fallback.
-            // In a production app, we would encrypt this or strictly enforce keyring.
-            unsafe_password: Some(c.connection_string.clone()),
-        })
-        .collect();
+/// Load settings, migrating an older `settings.json` first. Sections that
+/// can't be read keep their defaults and are reported in the returned list.
+pub fn load_settings() -> Result<(Settings, Vec<String>)> {
+    let file_path = get_config_dir()?.join(SETTINGS_FILE_NAME);
+    match read_document(&file_path, ConfigFile::Settings)? {
+        Some(doc) => parse_settings(doc),
+        None => Ok((Settings::default(), Vec::new())),
+    }
+}
 
-    let file_path = get_connections_file_path()?;
-    let json = serde_json::to_string_pretty(&metadata)?;
-    fs::write(file_path, json)?;
+/// Drop each top-level key that fails to deserialize on its own, so one bad
+/// section doesn't reset the others.
+fn parse_settings(doc: Value) -> Result<(Settings, Vec<String>)> {
+    let Value::Object(mut sections) = doc else {
+        bail!("Expected settings to be an object");
+    };
+    let mut skipped = Vec::new();
+    sections.retain(|key, value| {
+        let section = Value::Object([(key.clone(), value.clone())].into_iter().collect());
+        match serde_json::from_value::<Settings>(section) {
+            Ok(_) => true,
+            Err(e) => {
+                skipped.push(format!("settings '{}': {}", key, e));
+                false
+            }
+        }
+    });
+    Ok((serde_json::from_value(Value::Object(sections))?, skipped))
+}
 
-    // 2. Save secrets to Keyring (Best effort)
-    for conn in connections {
-        println!("Saving password for '{}'", conn.name);
-        let entry = Entry::new(SERVICE_NAME, &conn.name);
+pub fn save_settings(settings: &Settings) -> Result<()> {
+    let file_path = get_config_dir()?.join(SETTINGS_FILE_NAME);
+    let doc = SettingsDocument {
+        version: CURRENT_VERSION,
+        settings,
+    };
+    write_private(&file_path, &serde_json::to_string_pretty(&doc)?)
+}
 
-        if let Ok(entry) = entry
-            && let Err(e) = entry.set_password(&conn.connection_string)
-        {
-            eprintln!(
-                "Failed to save password to keyring for '{}': {}",
-                conn.name, e
+/// Read a config file as JSON and bring it up to the current version. An
+/// older file is copied to `<name>.v<version>.bak` before the migrated
+/// document replaces it, and a newer one before the next save writes it back
+/// in this version's format; one that isn't JSON at all is copied to
+/// `<name>.broken.bak` so a later save can't lose it. `None` if the file
+/// doesn't exist.
+fn read_document(path: &Path, file: ConfigFile) -> Result<Option<Value>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = fs::read_to_string(path)?;
+    let doc: Value = match serde_json::from_str(&text) {
+        Ok(doc) => doc,
+        Err(e) => {
+            let backup = backup(path, "broken")?;
+            bail!(
+                "{} is not valid JSON ({}); a copy was kept as {}",
+                path.display(),
+                e,
+                backup.display()
             );
         }
+    };
+
+    let version = migrate::version_of(&doc);
+    if version > CURRENT_VERSION {
+        let backup = backup(path, &format!("v{}", version))?;
+        tracing::warn!(
+            file = %path.display(),
+            version,
+            backup = %backup.display(),
+            "Config file is from a newer version; reading what this version understands"
+        );
+        return Ok(Some(doc));
+    }
+    if version == CURRENT_VERSION {
+        return Ok(Some(doc));
     }
 
-    println!("Connections saved successfully.");
-    Ok(())
+    let backup = backup(path, &format!("v{}", version))?;
+    let doc = migrate::migrate(file, doc)?;
+    write_private(path, &serde_json::to_string_pretty(&doc)?)?;
+    tracing::info!(
+        file = %path.display(),
+        from = version,
+        to = CURRENT_VERSION,
+        backup = %backup.display(),
+        "Migrated config file"
+    );
+    Ok(Some(doc))
 }
 
-pub fn load_connections() -> Result<Vec<ConnectionConfig>> {
-    println!("Loading connections...");
-    let file_path = get_connections_file_path()?;
-    if !file_path.exists() {
-        println!("No connections file found.");
-        return Ok(vec![]);
-    }
+/// Copy `path` to `<path>.<suffix>.bak` without any plaintext passwords,
+/// returning the copy's path.
+fn backup(path: &Path, suffix: &str) -> Result<PathBuf> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.bak", suffix));
+    let backup = path.with_file_name(name);
+    write_private(&backup, &strip_passwords(&fs::read_to_string(path)?))?;
+    Ok(backup)
+}
 
-    let json = fs::read_to_string(file_path)?;
-    let metadata: Vec<ConnectionMetadata> = serde_json::from_str(&json)?;
-    println!("Found metadata for {} connections.", metadata.len());
+/// Empty every `"unsafe_password": "..."` value in `text`. Works on the raw
+/// text so files that aren't valid JSON are scrubbed too, and the rest of the
+/// file is kept byte for byte.
+fn strip_passwords(text: &str) -> String {
+    const KEY: &str = "\"unsafe_password\"";
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find(KEY) {
+        let (head, tail) = rest.split_at(at + KEY.len());
+        out.push_str(head);
+        rest = tail;
+        let value = tail.trim_start();
+        let Some(value) = value.strip_prefix(':').map(str::trim_start) else {
+            continue;
+        };
+        let Some(string) = value.strip_prefix('"') else {
+            continue;
+        };
+        // Up to the closing quote, or the end of a truncated file.
+        let mut escaped = false;
+        let end = string
+            .char_indices()
+            .find(|&(_, c)| {
+                let close = c == '"' && !escaped;
+                escaped = c == '\\' && !escaped;
+                close
+            })
+            .map_or(string.len(), |(i, _)| i);
+        out.push_str(&tail[..tail.len() - string.len()]);
+        rest = &string[end..];
+    }
+    out.push_str(rest);
+    out
+}
 
-    let mut connections = Vec::new();
+/// Save connection metadata, with each password in the keyring or, when the
+/// keyring fails, the vault. Fails with [`VaultLocked`] if a password has
+/// nowhere safe to go.
+pub fn save_connections(connections: &[ConnectionConfig], vault: &mut Vault) -> Result<()> {
+    tracing::debug!(count = connections.len(), "Saving connections");
+    let metadata = connections
+        .iter()
+        .filter(|c| !c.ephemeral)
+        .map(|c| {
+            // Passwords asked for on connect are never stored.
+            let password = if c.ask_password { "" } else { &c.password };
+            Ok(ConnectionMetadata {
+                name: c.name.clone(),
+                db_type: c.db_type.clone(),
+                connection_string: Some(c.connection_string.clone()),
+                ask_password: c.ask_password,
+                shared_from: c.shared_from.clone(),
+                needs_credentials: c.needs_credentials,
+                secret: store_secret(&c.name, password, c.password_unreadable, vault)?,
+                unsafe_password: None,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
 
-    for meta in metadata {
-        let mut password = String::new();
-        let mut found_in_keyring = false;
+    let file_path = get_connections_file_path()?;
+    let doc = ConnectionsDocument {
+        version: CURRENT_VERSION,
+        connections: metadata,
+    };
+    write_private(&file_path, &serde_json::to_string_pretty(&doc)?)
+}
 
-        // Try Keyring first
-        let entry = Entry::new(SERVICE_NAME, &meta.name);
-        if let Ok(entry) = entry
-            && let Ok(p) = entry.get_password()
-        {
-            password = p;
-            found_in_keyring = true;
-            println!("Loaded password for '{}' from Keyring", meta.name);
+fn store_secret(
+    name: &str,
+    secret: &str,
+    unreadable: bool,
+    vault: &mut Vault,
+) -> Result<Option<SecretStore>> {
+    if secret.is_empty() {
+        // Not loaded because the vault is still locked; leave it there.
+        if vault.contains(name) && !vault.is_unlocked() {
+            return Ok(Some(SecretStore::Vault));
         }
-
-        // Fallback to JSON if not found in keyring
-        if !found_in_keyring {
-            if let Some(unsafe_pass) = meta.unsafe_password {
-                password = unsafe_pass;
-                println!("Loaded password for '{}' from JSON fallback", meta.name);
+        // Failed to load, which doesn't mean it's gone; keep it.
+        if unreadable {
+            return Ok(Some(if vault.contains(name) {
+                SecretStore::Vault
             } else {
-                eprintln!(
-                    "Failed to get password for connection {}: Not in Keyring or JSON",
-                    meta.name
-                );
+                SecretStore::Keyring
+            }));
+        }
+        // Best effort: without a keyring there is nothing to delete.
+        vault.remove(name)?;
+        if let Ok(entry) = Entry::new(SERVICE_NAME, name) {
+            let _ = entry.delete_credential();
+        }
+        return Ok(None);
+    }
+    let saved = Entry::new(SERVICE_NAME, name)
+        .and_then(|entry| entry.set_password(secret))
+        .map_err(|e| e.to_string())
+        .and_then(|()| {
+            // Read it back through a fresh entry; a store that only keeps
+            // secrets in memory would otherwise lose them on restart.
+            match Entry::new(SERVICE_NAME, name).and_then(|entry| entry.get_password()) {
+                Ok(stored) if stored == secret => Ok(()),
+                _ => Err("the keyring did not keep the password".to_string()),
             }
+        });
+    match saved {
+        Ok(()) => {
+            vault.remove(name)?;
+            Ok(Some(SecretStore::Keyring))
         }
+        Err(e) => {
+            tracing::warn!(connection = name, error = %e, "Failed to save password to keyring");
+            if !vault.is_unlocked() {
+                return Err(VaultLocked.into());
+            }
+            vault.set(name, secret)?;
+            Ok(Some(SecretStore::Vault))
+        }
+    }
+}
 
-        connections.push(ConnectionConfig {
-            name: meta.name,
-            db_type: meta.db_type,
-            connection_string: password,
-        });
+/// Remove a connection's secret from the keyring and the vault. A missing
+/// entry is not an error.
+pub fn delete_secret(name: &str, vault: &mut Vault) -> Result<()> {
+    vault.remove(name)?;
+    match Entry::new(SERVICE_NAME, name)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Set a connection's password from its stored secret. Connections saved
+/// before passwords were split out have an empty connection string and keep
+/// the whole URL as their secret.
+pub fn fill_secret(conn: &mut ConnectionConfig, secret: String) {
+    if conn.connection_string.is_empty() {
+        (conn.connection_string, conn.password) = crate::db::split_password(&secret);
+    } else {
+        conn.password = secret;
+    }
+}
+
+/// Load saved connections, migrating an older `connections.json` first.
+/// Passwords in the vault are read only if it is unlocked, and are left
+/// empty otherwise. Entries that can't be read are skipped and reported,
+/// after the file is copied to `connections.json.broken.bak`.
+pub fn load_connections(vault: &Vault) -> Result<LoadedConnections> {
+    let file_path = get_connections_file_path()?;
+    let Some(doc) = read_document(&file_path, ConfigFile::Connections)? else {
+        return Ok(LoadedConnections::default());
+    };
+    let (metadata, mut skipped) = parse_connection_entries(&doc)?;
+    if !skipped.is_empty() {
+        let backup = backup(&file_path, "broken")?;
+        skipped.push(format!(
+            "The original file was kept as {}",
+            backup.display()
+        ));
+    }
+    let migration_pending = metadata
+        .iter()
+        .any(|m| m.unsafe_password.is_some() || m.connection_string.is_none());
+
+    let connections = metadata
+        .into_iter()
+        .map(|meta| {
+            let (secret, password_unreadable) = match load_secret(&meta, vault) {
+                Ok(secret) => (secret, false),
+                Err(e) => {
+                    tracing::warn!(connection = %meta.name, error = %e, "Failed to get password");
+                    (String::new(), true)
+                }
+            };
+            let mut conn = ConnectionConfig {
+                name: meta.name,
+                db_type: meta.db_type,
+                connection_string: meta.connection_string.unwrap_or_default(),
+                password: String::new(),
+                ask_password: meta.ask_password,
+                shared_from: meta.shared_from,
+                needs_credentials: meta.needs_credentials,
+                ephemeral: false,
+                password_unreadable,
+            };
+            fill_secret(&mut conn, secret);
+            conn
+        })
+        .collect::<Vec<_>>();
+
+    tracing::debug!(count = connections.len(), "Loaded connections");
+    Ok(LoadedConnections {
+        connections,
+        migration_pending,
+        skipped,
+    })
+}
+
+/// Deserialize each connection on its own, collecting a message for each
+/// one that fails instead of failing the whole file.
+fn parse_connection_entries(doc: &Value) -> Result<(Vec<ConnectionMetadata>, Vec<String>)> {
+    let entries = doc
+        .get("connections")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("connections.json has no connections list"))?;
+    let mut metadata = Vec::new();
+    let mut skipped = Vec::new();
+    for (ix, entry) in entries.iter().enumerate() {
+        match ConnectionMetadata::deserialize(entry) {
+            Ok(meta) => metadata.push(meta),
+            Err(e) => {
+                let name = entry
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unnamed");
+                skipped.push(format!("connection {} ('{}'): {}", ix + 1, name, e));
+            }
+        }
     }
+    Ok((metadata, skipped))
+}
 
-    println!("Successfully loaded {} connections.", connections.len());
-    Ok(connections)
+fn load_secret(meta: &ConnectionMetadata, vault: &Vault) -> Result<String> {
+    match meta.secret {
+        Some(SecretStore::Vault) if vault.is_unlocked() => {
+            Ok(vault.get(&meta.name)?.unwrap_or_default())
+        }
+        Some(SecretStore::Vault) => Ok(String::new()),
+        Some(SecretStore::Keyring) => Ok(Entry::new(SERVICE_NAME, &meta.name)?.get_password()?),
+        // Older files did not say where the secret was.
+        None if meta.connection_string.is_none() => {
+            match Entry::new(SERVICE_NAME, &meta.name).and_then(|entry| entry.get_password()) {
+                Ok(secret) => Ok(secret),
+                Err(e) => meta.unsafe_password.clone().ok_or_else(|| e.into()),
+            }
+        }
+        None => Ok(String::new()),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Whether a system keyring that keeps secrets is running; CI machines
+    /// often have none, and the tests that need one are skipped there.
+    fn keyring_available() -> bool {
+        let (service, user) = ("com.slick-dataui.app.test", "probe");
+        let available = Entry::new(service, user)
+            .and_then(|entry| entry.set_password("probe"))
+            .and_then(|()| Entry::new(service, user)?.get_password())
+            .is_ok();
+        if available {
+            let _ = Entry::new(service, user).and_then(|entry| entry.delete_credential());
+        } else {
+            eprintln!("No system keyring; skipping");
+        }
+        available
+    }
+
     #[test]
     fn test_keyring_direct() {
+        if !keyring_available() {
+            return;
+        }
         let service = "com.slick-dataui.app.test";
         let user = "test_user";
         let password = "test_password";
@@ -148,4 +486,190 @@ mod tests {
         // Delete
         entry.delete_credential().unwrap();
     }
+
+    #[test]
+    fn test_delete_secret() {
+        if !keyring_available() {
+            return;
+        }
+        let name = "slick-dataui-test-delete-secret";
+        Entry::new(SERVICE_NAME, name)
+            .unwrap()
+            .set_password("secret")
+            .unwrap();
+
+        let mut vault = Vault::default();
+        delete_secret(name, &mut vault).unwrap();
+        assert!(
+            Entry::new(SERVICE_NAME, name)
+                .unwrap()
+                .get_password()
+                .is_err()
+        );
+        // Deleting again is a no-op.
+        delete_secret(name, &mut vault).unwrap();
+    }
+
+    #[test]
+    fn test_stored_secret_survives_a_fresh_load() {
+        let path = std::env::temp_dir().join(format!(
+            "slick-dataui-store-secret-{}.json",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        let mut vault = Vault::open(path.clone()).unwrap();
+        vault.create("pass").unwrap();
+
+        let name = "slick-dataui-test-store-secret";
+        let secret = store_secret(name, "s3cret", false, &mut vault).unwrap();
+        // Whichever store took it, a later load must find it again.
+        let meta = ConnectionMetadata {
+            name: name.to_string(),
+            db_type: DatabaseType::Postgres,
+            connection_string: Some("postgres://u@h/db".to_string()),
+            ask_password: false,
+            shared_from: None,
+            needs_credentials: false,
+            secret,
+            unsafe_password: None,
+        };
+        assert_eq!(load_secret(&meta, &vault).unwrap(), "s3cret");
+
+        let _ = delete_secret(name, &mut vault);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_unreadable_secret_is_kept() {
+        let name = "slick-dataui-test-unreadable-secret";
+        let mut vault = Vault::default();
+        // Still listed where it was rather than deleted.
+        let store = store_secret(name, "", true, &mut vault).unwrap();
+        assert_eq!(store, Some(SecretStore::Keyring));
+        // A password cleared on purpose goes.
+        assert_eq!(store_secret(name, "", false, &mut vault).unwrap(), None);
+    }
+
+    #[test]
+    fn test_old_metadata_is_rewritten_without_secrets() {
+        let old = r#"[{"name": "prod", "db_type": "Postgres", "unsafe_password": "postgres://u:p@h/db"}]"#;
+        let meta: ConnectionMetadata = serde_json::from_str::<Vec<_>>(old).unwrap().pop().unwrap();
+        assert!(meta.connection_string.is_none());
+
+        let mut conn = ConnectionConfig {
+            name: meta.name.clone(),
+            db_type: meta.db_type.clone(),
+            ..Default::default()
+        };
+        fill_secret(&mut conn, meta.unsafe_password.clone().unwrap());
+        assert_eq!(conn.connection_string, "postgres://u@h/db");
+        assert_eq!(conn.password, "p");
+
+        let rewritten = serde_json::to_string(&ConnectionMetadata {
+            connection_string: Some(conn.connection_string),
+            secret: Some(SecretStore::Vault),
+            ..meta
+        })
+        .unwrap();
+        assert_eq!(
+            rewritten,
+            r#"{"name":"prod","db_type":"Postgres","connection_string":"postgres://u@h/db","ask_password":false,"secret":"vault"}"#
+        );
+    }
+
+    #[test]
+    fn test_broken_entries_are_skipped() {
+        let doc = serde_json::json!({
+            "version": 1,
+            "connections": [
+                {"name": "good", "db_type": "Sqlite", "connection_string": "sqlite::memory:"},
+                {"name": "bad", "db_type": "Oracle"},
+                {"db_type": "Postgres"}
+            ]
+        });
+        let (metadata, skipped) = parse_connection_entries(&doc).unwrap();
+        assert_eq!(metadata.len(), 1);
+        assert_eq!(metadata[0].name, "good");
+        assert_eq!(skipped.len(), 2);
+        assert!(skipped[0].starts_with("connection 2 ('bad')"));
+        assert!(skipped[1].starts_with("connection 3 ('unnamed')"));
+    }
+
+    #[test]
+    fn test_broken_settings_section_keeps_the_rest() {
+        let doc = serde_json::json!({
+            "version": 1,
+            "format": {"indent_width": "wide"},
+            "telemetry": {"crash_reports": false}
+        });
+        let (settings, skipped) = parse_settings(doc).unwrap();
+        assert_eq!(settings.format, FormatOptions::default());
+        assert!(!settings.telemetry.crash_reports);
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].starts_with("settings 'format'"));
+    }
+
+    #[test]
+    fn test_unversioned_file_is_backed_up_and_migrated() {
+        let dir = std::env::temp_dir().join(format!("slick-dataui-migrate-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(CONNECTIONS_FILE_NAME);
+        let old =
+            r#"[{"name": "local", "db_type": "Sqlite", "unsafe_password": "sqlite::memory:"}]"#;
+        fs::write(&path, old).unwrap();
+
+        let doc = read_document(&path, ConfigFile::Connections)
+            .unwrap()
+            .unwrap();
+        assert_eq!(migrate::version_of(&doc), CURRENT_VERSION);
+        assert_eq!(doc["connections"][0]["name"], "local");
+        assert_eq!(
+            fs::read_to_string(dir.join("connections.json.v0.bak")).unwrap(),
+            r#"[{"name": "local", "db_type": "Sqlite", "unsafe_password": ""}]"#
+        );
+        let rewritten: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(rewritten, doc);
+
+        fs::write(&path, r#"[{"unsafe_password": "postgres://u:pw@h"#).unwrap();
+        assert!(read_document(&path, ConfigFile::Connections).is_err());
+        let broken = fs::read_to_string(dir.join("connections.json.broken.bak")).unwrap();
+        assert_eq!(broken, r#"[{"unsafe_password": ""#);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.join("connections.json.broken.bak"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_newer_file_is_backed_up_before_it_is_read() {
+        let dir = std::env::temp_dir().join(format!("slick-dataui-newer-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(SETTINGS_FILE_NAME);
+        let newer = format!(r#"{{"version": {}, "future": true}}"#, CURRENT_VERSION + 1);
+        fs::write(&path, &newer).unwrap();
+
+        let doc = read_document(&path, ConfigFile::Settings).unwrap().unwrap();
+        assert_eq!(doc["future"], true);
+        let backup = dir.join(format!("settings.json.v{}.bak", CURRENT_VERSION + 1));
+        assert_eq!(fs::read_to_string(backup).unwrap(), newer);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_strip_passwords() {
+        assert_eq!(
+            strip_passwords(r#"{"unsafe_password" : "a\"b", "name": "x"}"#),
+            r#"{"unsafe_password" : "", "name": "x"}"#
+        );
+        assert_eq!(
+            strip_passwords(r#"{"unsafe_password": null}"#),
+            r#"{"unsafe_password": null}"#
+        );
+    }
 }
@@ -0,0 +1,73 @@
+// Schema versions of the config files and the migrations between them.
+//
+// Version 0 is the unversioned layout of older releases: `connections.json`
+// a bare array of connections and `settings.json` a plain object. From
+// version 1 each file is an object with a `version` key, and connections sit
+// under `connections`. A migration takes a document one version forward;
+// files are read as JSON values and migrated before being deserialized, so
+// a step only needs to know the shape of the version it starts from.
+use anyhow::{Result, anyhow};
+use serde_json::{Value, json};
+
+/// Version written by this build.
+pub const CURRENT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigFile {
+    Connections,
+    Settings,
+}
+
+/// The version a document was written as; unversioned files are version 0.
+pub fn version_of(doc: &Value) -> u32 {
+    doc.get("version")
+        .and_then(Value::as_u64)
+        .map_or(0, |v| v as u32)
+}
+
+/// Bring `doc` up to [`CURRENT_VERSION`], one version at a time. Documents
+/// from newer versions are returned unchanged.
+pub fn migrate(file: ConfigFile, mut doc: Value) -> Result<Value> {
+    let mut version = version_of(&doc);
+    while version < CURRENT_VERSION {
+        doc = match (file, version) {
+            (ConfigFile::Connections, 0) => json!({ "version": 1, "connections": doc }),
+            (ConfigFile::Settings, 0) => {
+                let mut settings = doc;
+                settings
+                    .as_object_mut()
+                    .ok_or_else(|| anyhow!("Expected settings to be an object"))?
+                    .insert("version".to_string(), json!(1));
+                settings
+            }
+            (_, version) => unreachable!("no migration from version {}", version),
+        };
+        version += 1;
+    }
+    Ok(doc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_unversioned() {
+        let connections = json!([{ "name": "local", "db_type": "Sqlite" }]);
+        let migrated = migrate(ConfigFile::Connections, connections.clone()).unwrap();
+        assert_eq!(version_of(&migrated), CURRENT_VERSION);
+        assert_eq!(migrated["connections"], connections);
+
+        let settings = json!({ "format": { "indent_width": 4 } });
+        let migrated = migrate(ConfigFile::Settings, settings).unwrap();
+        assert_eq!(migrated["version"], json!(1));
+        assert_eq!(migrated["format"]["indent_width"], json!(4));
+
+        // Already current: nothing to do.
+        assert_eq!(
+            migrate(ConfigFile::Settings, migrated.clone()).unwrap(),
+            migrated
+        );
+        assert!(migrate(ConfigFile::Settings, json!([])).is_err());
+    }
+}
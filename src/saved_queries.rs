@@ -0,0 +1,419 @@
+// Saved queries and snippets, stored as plain `.sql` files.
+//
+// Each query lives at `queries/<folder>/<name>.sql` under the config
+// directory so the library can be kept in version control. Metadata sits in
+// a front-matter block of SQL comments, which keeps every file runnable as-is:
+//
+//     -- ---
+//     -- name: Active users
+//     -- tags: reporting, users
+//     -- connection: prod
+//     -- description: Users seen in the last 30 days
+//     -- ---
+//     SELECT * FROM users WHERE last_seen > now() - interval '${1:30} days'
+//
+// Snippet tab stops use the `${1:default}` form; see `expand_snippet`.
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+const QUERIES_DIR_NAME: &str = "queries";
+const FRONT_MATTER_FENCE: &str = "-- ---";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SavedQuery {
+    pub name: String,
+    /// `/`-separated folder path; empty for the top level.
+    pub folder: String,
+    pub tags: Vec<String>,
+    /// Connection the query was written for, if any.
+    pub connection: Option<String>,
+    pub description: Option<String>,
+    /// The SQL, which may contain snippet tab stops.
+    pub body: String,
+}
+
+impl SavedQuery {
+    /// Parse a `.sql` file, taking the name from `file_stem` when the
+    /// front-matter has none.
+    pub fn parse(contents: &str, file_stem: &str, folder: &str) -> Self {
+        let mut query = SavedQuery {
+            name: file_stem.to_string(),
+            folder: folder.to_string(),
+            ..Default::default()
+        };
+
+        let mut lines = contents.split_inclusive('\n');
+        let Some(first) = lines.next().filter(|l| l.trim_end() == FRONT_MATTER_FENCE) else {
+            query.body = contents.to_string();
+            return query;
+        };
+        let mut consumed = first.len();
+        for line in lines {
+            consumed += line.len();
+            if line.trim_end() == FRONT_MATTER_FENCE {
+                break;
+            }
+            let Some((key, value)) = line
+                .trim_start_matches("--")
+                .trim()
+                .split_once(':')
+                .map(|(k, v)| (k.trim(), v.trim().to_string()))
+            else {
+                continue;
+            };
+            match key {
+                "name" if !value.is_empty() => query.name = value,
+                "tags" => {
+                    query.tags = value
+                        .split(',')
+                        .map(|t| t.trim().to_string())
+                        .filter(|t| !t.is_empty())
+                        .collect()
+                }
+                "connection" => query.connection = Some(value).filter(|v| !v.is_empty()),
+                "description" => query.description = Some(value).filter(|v| !v.is_empty()),
+                _ => {}
+            }
+        }
+        query.body = contents[consumed..].to_string();
+        query
+    }
+
+    pub fn to_file_contents(&self) -> String {
+        let mut contents = format!("{}\n-- name: {}\n", FRONT_MATTER_FENCE, self.name);
+        if !self.tags.is_empty() {
+            contents.push_str(&format!("-- tags: {}\n", self.tags.join(", ")));
+        }
+        if let Some(connection) = &self.connection {
+            contents.push_str(&format!("-- connection: {}\n", connection));
+        }
+        if let Some(description) = &self.description {
+            // Front-matter values are single lines.
+            let description = description.lines().collect::<Vec<_>>().join(" ");
+            contents.push_str(&format!("-- description: {}\n", description));
+        }
+        contents.push_str(FRONT_MATTER_FENCE);
+        contents.push('\n');
+        contents.push_str(&self.body);
+        if !self.body.ends_with('\n') {
+            contents.push('\n');
+        }
+        contents
+    }
+}
+
+/// All saved queries, kept in sync with the files on disk.
+#[derive(Debug, Default)]
+pub struct QueryLibrary {
+    root: PathBuf,
+    /// Sorted by folder, then name.
+    pub queries: Vec<SavedQuery>,
+    /// File of each query by (folder, name). Files edited by hand may not
+    /// be named after the query they hold.
+    files: HashMap<(String, String), PathBuf>,
+}
+
+impl QueryLibrary {
+    /// Load the library from the app's config directory.
+    pub fn load() -> Result<Self> {
+        Self::open(crate::persistence::get_config_dir()?.join(QUERIES_DIR_NAME))
+    }
+
+    fn open(root: PathBuf) -> Result<Self> {
+        let mut library = Self {
+            root,
+            ..Default::default()
+        };
+        if library.root.exists() {
+            let root = library.root.clone();
+            library.scan(&root, "")?;
+        }
+        library.sort();
+        Ok(library)
+    }
+
+    fn scan(&mut self, dir: &Path, folder: &str) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if path.is_dir() {
+                let child = if folder.is_empty() {
+                    file_name.to_string()
+                } else {
+                    format!("{}/{}", folder, file_name)
+                };
+                self.scan(&path, &child)?;
+            } else if let Some(stem) = file_name.strip_suffix(".sql") {
+                let contents = fs::read_to_string(&path)?;
+                let query = SavedQuery::parse(&contents, stem, folder);
+                self.files
+                    .insert((query.folder.clone(), query.name.clone()), path.clone());
+                self.queries.push(query);
+            }
+        }
+        Ok(())
+    }
+
+    fn sort(&mut self) {
+        self.queries.sort_by(|a, b| {
+            (a.folder.to_lowercase(), a.name.to_lowercase())
+                .cmp(&(b.folder.to_lowercase(), b.name.to_lowercase()))
+        });
+    }
+
+    pub fn find(&self, folder: &str, name: &str) -> Option<&SavedQuery> {
+        self.queries
+            .iter()
+            .find(|q| q.folder == folder && q.name == name)
+    }
+
+    /// Write `query`, replacing any query with the same folder and name.
+    pub fn save(&mut self, query: SavedQuery) -> Result<()> {
+        let path = self.path_of(&query.folder, &query.name)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, query.to_file_contents())?;
+
+        self.queries
+            .retain(|q| !(q.folder == query.folder && q.name == query.name));
+        self.files
+            .insert((query.folder.clone(), query.name.clone()), path);
+        self.queries.push(query);
+        self.sort();
+        Ok(())
+    }
+
+    /// Rename and/or move the query at `folder`/`name`, replacing its metadata
+    /// with `updated`.
+    pub fn update(&mut self, folder: &str, name: &str, updated: SavedQuery) -> Result<()> {
+        let old_path = self.path_of(folder, name)?;
+        let moved = updated.folder != folder || updated.name != name;
+        if moved && self.find(&updated.folder, &updated.name).is_some() {
+            bail!(
+                "A saved query named '{}' already exists in that folder",
+                updated.name
+            );
+        }
+
+        if moved {
+            // Move the file before writing it: both names may be the same
+            // file, as with a case-only rename on a case-insensitive file
+            // system or names that differ only in characters `file_name_for`
+            // replaces.
+            let new_path = self.path_of(&updated.folder, &updated.name)?;
+            if old_path.exists() {
+                if let Some(parent) = new_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::rename(&old_path, &new_path)?;
+            }
+            self.forget(folder, name);
+        }
+        self.save(updated)
+    }
+
+    pub fn delete(&mut self, folder: &str, name: &str) -> Result<()> {
+        let path = self.path_of(folder, name)?;
+        self.remove(folder, name, &path)
+    }
+
+    fn remove(&mut self, folder: &str, name: &str, path: &Path) -> Result<()> {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        self.forget(folder, name);
+        Ok(())
+    }
+
+    fn forget(&mut self, folder: &str, name: &str) {
+        self.queries
+            .retain(|q| !(q.folder == folder && q.name == name));
+        self.files.remove(&(folder.to_string(), name.to_string()));
+    }
+
+    /// Folders holding at least one query, sorted.
+    pub fn folders(&self) -> Vec<&str> {
+        let mut folders: Vec<&str> = self.queries.iter().map(|q| q.folder.as_str()).collect();
+        folders.dedup();
+        folders
+    }
+
+    fn path_of(&self, folder: &str, name: &str) -> Result<PathBuf> {
+        if let Some(path) = self.files.get(&(folder.to_string(), name.to_string())) {
+            return Ok(path.clone());
+        }
+        let mut path = self.root.clone();
+        for part in folder.split('/').map(str::trim).filter(|p| !p.is_empty()) {
+            if part == "." || part == ".." {
+                bail!("Invalid folder name '{}'", folder);
+            }
+            path.push(file_name_for(part));
+        }
+        if name.trim().is_empty() {
+            bail!("A saved query needs a name");
+        }
+        path.push(format!("{}.sql", file_name_for(name.trim())));
+        Ok(path)
+    }
+}
+
+/// Replace characters that are not portable in file names.
+fn file_name_for(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// A snippet with its tab stops resolved: `${1:default}` becomes `default`,
+/// `${1}` becomes empty, and `${0}` marks where the cursor ends up. Bare `$1`
+/// is left alone since it is a PostgreSQL parameter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpandedSnippet {
+    pub text: String,
+    /// Byte ranges into `text`, in tab order with `${0}` last.
+    pub stops: Vec<Range<usize>>,
+}
+
+pub fn expand_snippet(body: &str) -> ExpandedSnippet {
+    let mut text = String::with_capacity(body.len());
+    let mut stops: Vec<(usize, Range<usize>)> = Vec::new();
+    let mut rest = body;
+
+    while let Some(ix) = rest.find('$') {
+        text.push_str(&rest[..ix]);
+        rest = &rest[ix..];
+
+        let (number, default, len) = match parse_tab_stop(rest) {
+            Some(stop) => stop,
+            None => {
+                // Not a tab stop, e.g. `$$` dollar quoting or a `$1` parameter
+                // with text directly after it; keep the `$` literally.
+                text.push('$');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let start = text.len();
+        text.push_str(default);
+        stops.push((number, start..text.len()));
+        rest = &rest[len..];
+    }
+    text.push_str(rest);
+
+    // `${0}` is the final stop; the others go in numeric order.
+    stops.sort_by_key(|(number, _)| if *number == 0 { usize::MAX } else { *number });
+    ExpandedSnippet {
+        text,
+        stops: stops.into_iter().map(|(_, range)| range).collect(),
+    }
+}
+
+/// Parse `${n:default}` or `${n}` at the start of `s`, returning the stop
+/// number, its default text and the length consumed.
+fn parse_tab_stop(s: &str) -> Option<(usize, &str, usize)> {
+    let inner = s.strip_prefix("${")?;
+    let end = inner.find('}')?;
+    let (number, default) = match inner[..end].split_once(':') {
+        Some((number, default)) => (number, default),
+        None => (&inner[..end], ""),
+    };
+    Some((number.parse().ok()?, default, end + 3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_front_matter_round_trip() {
+        let query = SavedQuery {
+            name: "Active users".to_string(),
+            folder: "reports/daily".to_string(),
+            tags: vec!["reporting".to_string(), "users".to_string()],
+            connection: Some("prod".to_string()),
+            description: Some("Seen recently".to_string()),
+            body: "SELECT *\nFROM users;\n".to_string(),
+        };
+        let contents = query.to_file_contents();
+        assert!(contents.starts_with("-- ---\n-- name: Active users\n"));
+        assert_eq!(
+            SavedQuery::parse(&contents, "ignored", "reports/daily"),
+            query
+        );
+
+        let plain = SavedQuery::parse("SELECT 1;\n", "one", "");
+        assert_eq!(plain.name, "one");
+        assert_eq!(plain.body, "SELECT 1;\n");
+    }
+
+    #[test]
+    fn test_library_save_rename_move_delete() {
+        let root =
+            std::env::temp_dir().join(format!("slick-dataui-queries-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        let mut library = QueryLibrary::open(root.clone()).unwrap();
+        library
+            .save(SavedQuery {
+                name: "users".to_string(),
+                body: "SELECT * FROM users\n".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(root.join("users.sql").exists());
+
+        let mut moved = library.find("", "users").unwrap().clone();
+        moved.name = "all users".to_string();
+        moved.folder = "admin".to_string();
+        library.update("", "users", moved).unwrap();
+        assert!(!root.join("users.sql").exists());
+        assert!(root.join("admin").join("all users.sql").exists());
+
+        let reloaded = QueryLibrary::open(root.clone()).unwrap();
+        assert_eq!(reloaded.queries, library.queries);
+        assert_eq!(reloaded.folders(), vec!["admin"]);
+
+        // "a:b" is stored as "a_b.sql", so renaming it to "a_b" keeps the file.
+        let mut odd = library.find("admin", "all users").unwrap().clone();
+        odd.name = "a:b".to_string();
+        library.update("admin", "all users", odd.clone()).unwrap();
+        odd.name = "a_b".to_string();
+        library.update("admin", "a:b", odd).unwrap();
+        assert!(root.join("admin").join("a_b.sql").exists());
+        assert_eq!(
+            QueryLibrary::open(root.clone()).unwrap().queries,
+            library.queries
+        );
+        assert!(!root.join("admin").join("all users.sql").exists());
+
+        library.delete("admin", "a_b").unwrap();
+        assert!(library.queries.is_empty());
+        assert!(!root.join("admin").join("a_b.sql").exists());
+        assert!(library.path_of("..", "x").is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_expand_snippet() {
+        let snippet = expand_snippet("SELECT ${2:*} FROM ${1:table} WHERE id = $1${0}");
+        assert_eq!(snippet.text, "SELECT * FROM table WHERE id = $1");
+        let stops: Vec<&str> = snippet
+            .stops
+            .iter()
+            .map(|r| &snippet.text[r.clone()])
+            .collect();
+        assert_eq!(stops, vec!["table", "*", ""]);
+        assert_eq!(snippet.stops[2], snippet.text.len()..snippet.text.len());
+    }
+}
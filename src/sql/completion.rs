@@ -0,0 +1,691 @@
+// Context-aware completion candidates for the SQL editor.
+//
+// The statement under the cursor is tokenized to find what is being typed
+// (a table after FROM/JOIN, a column after `alias.`, ...) and parsed with
+// tree-sitter to learn which tables and aliases are in scope.
+use super::lexer::{Token, TokenKind, statement_at, tokenize, unquote};
+use super::schema::SchemaCache;
+use crate::state::DatabaseType;
+use std::ops::Range;
+use tree_sitter::Node;
+
+/// Upper bound on candidates returned for one request.
+const MAX_CANDIDATES: usize = 100;
+
+const KEYWORDS: &[&str] = &[
+    "SELECT",
+    "FROM",
+    "WHERE",
+    "AND",
+    "OR",
+    "NOT",
+    "IN",
+    "IS",
+    "NULL",
+    "LIKE",
+    "BETWEEN",
+    "EXISTS",
+    "JOIN",
+    "INNER",
+    "LEFT",
+    "RIGHT",
+    "FULL",
+    "OUTER",
+    "CROSS",
+    "ON",
+    "USING",
+    "AS",
+    "DISTINCT",
+    "GROUP BY",
+    "HAVING",
+    "ORDER BY",
+    "ASC",
+    "DESC",
+    "LIMIT",
+    "OFFSET",
+    "UNION",
+    "UNION ALL",
+    "INTERSECT",
+    "EXCEPT",
+    "CASE",
+    "WHEN",
+    "THEN",
+    "ELSE",
+    "END",
+    "CAST",
+    "WITH",
+    "INSERT INTO",
+    "VALUES",
+    "UPDATE",
+    "SET",
+    "DELETE FROM",
+    "CREATE TABLE",
+    "CREATE INDEX",
+    "CREATE VIEW",
+    "ALTER TABLE",
+    "DROP TABLE",
+    "PRIMARY KEY",
+    "FOREIGN KEY",
+    "REFERENCES",
+    "DEFAULT",
+    "UNIQUE",
+    "CHECK",
+    "BEGIN",
+    "COMMIT",
+    "ROLLBACK",
+    "TRUE",
+    "FALSE",
+];
+
+const POSTGRES_KEYWORDS: &[&str] = &[
+    "RETURNING",
+    "ILIKE",
+    "LATERAL",
+    "ON CONFLICT",
+    "DO NOTHING",
+    "DO UPDATE",
+    "FILTER",
+    "OVER",
+    "PARTITION BY",
+    "WINDOW",
+    "FETCH FIRST",
+    "FOR UPDATE",
+    "SIMILAR TO",
+    "ANALYZE",
+    "EXPLAIN",
+    "VACUUM",
+    "SCHEMA",
+    "SEQUENCE",
+    "MATERIALIZED VIEW",
+    "TRUNCATE",
+];
+
+const SQLITE_KEYWORDS: &[&str] = &[
+    "RETURNING",
+    "PRAGMA",
+    "AUTOINCREMENT",
+    "GLOB",
+    "REPLACE",
+    "INSERT OR REPLACE",
+    "INSERT OR IGNORE",
+    "WITHOUT ROWID",
+    "ATTACH",
+    "DETACH",
+    "VACUUM",
+    "EXPLAIN QUERY PLAN",
+    "OVER",
+    "PARTITION BY",
+    "WINDOW",
+    "FILTER",
+];
+
+const FUNCTIONS: &[&str] = &[
+    "count", "sum", "avg", "min", "max", "coalesce", "nullif", "lower", "upper", "length", "abs",
+    "round", "trim", "replace", "substr",
+];
+
+const POSTGRES_FUNCTIONS: &[&str] = &[
+    "now",
+    "current_date",
+    "current_timestamp",
+    "date_trunc",
+    "date_part",
+    "extract",
+    "age",
+    "to_char",
+    "to_date",
+    "to_timestamp",
+    "string_agg",
+    "array_agg",
+    "json_agg",
+    "jsonb_agg",
+    "json_build_object",
+    "jsonb_build_object",
+    "jsonb_set",
+    "jsonb_array_elements",
+    "generate_series",
+    "unnest",
+    "array_length",
+    "concat",
+    "concat_ws",
+    "format",
+    "left",
+    "right",
+    "split_part",
+    "regexp_replace",
+    "regexp_matches",
+    "row_number",
+    "rank",
+    "dense_rank",
+    "lag",
+    "lead",
+    "greatest",
+    "least",
+    "gen_random_uuid",
+    "pg_size_pretty",
+];
+
+const SQLITE_FUNCTIONS: &[&str] = &[
+    "date",
+    "time",
+    "datetime",
+    "julianday",
+    "strftime",
+    "unixepoch",
+    "ifnull",
+    "iif",
+    "instr",
+    "printf",
+    "group_concat",
+    "total",
+    "typeof",
+    "json_extract",
+    "json_object",
+    "json_array",
+    "json_group_array",
+    "row_number",
+    "rank",
+    "dense_rank",
+    "lag",
+    "lead",
+    "random",
+    "last_insert_rowid",
+    "changes",
+];
+
+/// Keywords after which a table name is expected.
+const TABLE_KEYWORDS: &[&str] = &["FROM", "JOIN", "INTO", "UPDATE", "TABLE"];
+
+/// Keywords that start a clause, used to tell `FROM a, |` from `SELECT a, |`.
+const CLAUSE_KEYWORDS: &[&str] = &[
+    "SELECT",
+    "FROM",
+    "WHERE",
+    "GROUP",
+    "HAVING",
+    "ORDER",
+    "LIMIT",
+    "SET",
+    "VALUES",
+    "ON",
+    "RETURNING",
+    "JOIN",
+    "USING",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CandidateKind {
+    Keyword,
+    Schema,
+    Table,
+    Column,
+    Function,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub label: String,
+    pub kind: CandidateKind,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completions {
+    /// The byte range of the partial word the chosen candidate replaces.
+    pub replace: Range<usize>,
+    pub candidates: Vec<Candidate>,
+}
+
+/// A table referenced by the statement, with its alias if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableRef {
+    pub schema: Option<String>,
+    pub name: String,
+    pub alias: Option<String>,
+}
+
+impl TableRef {
+    fn answers_to(&self, qualifier: &str) -> bool {
+        match &self.alias {
+            Some(alias) => alias.eq_ignore_ascii_case(qualifier),
+            None => self.name.eq_ignore_ascii_case(qualifier),
+        }
+    }
+}
+
+enum Context {
+    /// After `qualifier.`
+    Qualified(String),
+    /// Where a table name is expected.
+    Table,
+    Anywhere,
+}
+
+/// Compute completions for the cursor at byte `offset` in `sql`.
+///
+/// `dialect` is `None` when no connection is active; only standard keywords
+/// and functions are offered then.
+pub fn complete(
+    sql: &str,
+    offset: usize,
+    schema: &SchemaCache,
+    dialect: Option<&DatabaseType>,
+) -> Completions {
+    let statement = statement_at(sql, offset);
+    let before = &sql[statement.start..offset];
+    let tokens: Vec<Token> = tokenize(before);
+
+    let mut replace = offset..offset;
+    let mut significant: Vec<&Token> = tokens.iter().filter(|t| !t.is_trivia()).collect();
+
+    match tokens.last() {
+        // Never complete inside strings or comments.
+        Some(t)
+            if t.is_comment()
+                || matches!(t.kind, TokenKind::String | TokenKind::QuotedIdentifier) =>
+        {
+            return Completions {
+                replace,
+                candidates: vec![],
+            };
+        }
+        Some(t) if t.kind == TokenKind::Word => {
+            replace.start = statement.start + t.start;
+            significant.pop();
+        }
+        _ => {}
+    }
+    let prefix = &sql[replace.clone()];
+
+    let context = match significant.as_slice() {
+        [.., qualifier, dot] if dot.text == "." => Context::Qualified(unquote(qualifier.text)),
+        [.., keyword] if TABLE_KEYWORDS.iter().any(|k| keyword.is_keyword(k)) => Context::Table,
+        [.., comma] if comma.text == "," && current_clause(&significant) == Some("FROM") => {
+            Context::Table
+        }
+        _ => Context::Anywhere,
+    };
+
+    let scope = tables_in_scope(&sql[statement.clone()]);
+    let mut candidates = Vec::new();
+
+    match context {
+        Context::Qualified(qualifier) => {
+            if let Some(table) = scope.iter().find(|t| t.answers_to(&qualifier)) {
+                push_columns(
+                    &mut candidates,
+                    schema,
+                    table.schema.as_deref(),
+                    &table.name,
+                );
+            } else if schema.has_table(&qualifier) {
+                push_columns(&mut candidates, schema, None, &qualifier);
+            }
+            // `FROM sales.` parses as a reference to a table named `sales`,
+            // so a qualifier without columns may still be a schema.
+            if candidates.is_empty() {
+                for table in &schema.tables {
+                    if table
+                        .schema
+                        .as_deref()
+                        .is_some_and(|s| s.eq_ignore_ascii_case(&qualifier))
+                    {
+                        candidates.push(table_candidate(&table.name, table.schema.as_deref()));
+                    }
+                }
+            }
+        }
+        Context::Table => {
+            for table in &schema.tables {
+                candidates.push(table_candidate(&table.name, table.schema.as_deref()));
+            }
+            for name in schema.schemas() {
+                candidates.push(Candidate {
+                    label: name.to_string(),
+                    kind: CandidateKind::Schema,
+                    detail: Some("schema".to_string()),
+                });
+            }
+        }
+        Context::Anywhere => {
+            for table in &scope {
+                push_columns(
+                    &mut candidates,
+                    schema,
+                    table.schema.as_deref(),
+                    &table.name,
+                );
+            }
+            push_functions(&mut candidates, schema, dialect);
+            push_keywords(&mut candidates, dialect, prefix);
+            for table in &schema.tables {
+                candidates.push(table_candidate(&table.name, table.schema.as_deref()));
+            }
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    candidates.retain(|c| {
+        c.label.len() >= prefix.len()
+            && c.label.is_char_boundary(prefix.len())
+            && c.label[..prefix.len()].eq_ignore_ascii_case(prefix)
+            && seen.insert((c.label.clone(), c.kind))
+    });
+    candidates.truncate(MAX_CANDIDATES);
+
+    Completions {
+        replace,
+        candidates,
+    }
+}
+
+/// Tables and aliases referenced anywhere in `statement`.
+///
+/// Uses the tree-sitter parse, which recovers from the half-typed input an
+/// editor usually holds, and falls back to scanning tokens after
+/// FROM/JOIN/UPDATE/INTO when the parse finds nothing.
+pub fn tables_in_scope(statement: &str) -> Vec<TableRef> {
+    let mut tables = Vec::new();
+    if let Some(tree) = super::parse(statement) {
+        collect_table_refs(tree.root_node(), statement, &mut tables);
+    }
+    if tables.is_empty() {
+        tables = scan_table_refs(statement);
+    }
+    tables
+}
+
+fn collect_table_refs(node: Node, source: &str, tables: &mut Vec<TableRef>) {
+    if node.kind() == "object_reference"
+        && let Some(parent) = node.parent()
+        // Qualifiers of columns (`u.id`, `u.*`) and function names are not tables.
+        && !matches!(parent.kind(), "field" | "all_fields" | "invocation")
+        && let Some(name) = node.child_by_field_name("name")
+    {
+        let text = |n: Node| unquote(&source[n.byte_range()]);
+        let alias = if parent.kind() == "relation" {
+            parent.child_by_field_name("alias").map(text)
+        } else {
+            None
+        };
+        tables.push(TableRef {
+            schema: node.child_by_field_name("schema").map(text),
+            name: text(name),
+            alias,
+        });
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_table_refs(child, source, tables);
+    }
+}
+
+fn scan_table_refs(statement: &str) -> Vec<TableRef> {
+    let tokens: Vec<Token> = tokenize(statement)
+        .into_iter()
+        .filter(|t| !t.is_trivia())
+        .collect();
+    let is_name = |t: &Token| matches!(t.kind, TokenKind::Word | TokenKind::QuotedIdentifier);
+    let mut tables = Vec::new();
+
+    for (i, token) in tokens.iter().enumerate() {
+        if !["FROM", "JOIN", "UPDATE", "INTO"]
+            .iter()
+            .any(|k| token.is_keyword(k))
+        {
+            continue;
+        }
+        let rest = &tokens[i + 1..];
+        let (schema, name, rest) = match rest {
+            [s, dot, n, rest @ ..] if dot.text == "." && is_name(s) && is_name(n) => {
+                (Some(unquote(s.text)), n, rest)
+            }
+            [n, rest @ ..] if is_name(n) => (None, n, rest),
+            _ => continue,
+        };
+        let alias = match rest {
+            [as_kw, a, ..] if as_kw.is_keyword("AS") && is_name(a) => Some(unquote(a.text)),
+            [a, ..] if is_name(a) && !is_reserved(a) => Some(unquote(a.text)),
+            _ => None,
+        };
+        tables.push(TableRef {
+            schema,
+            name: unquote(name.text),
+            alias,
+        });
+    }
+    tables
+}
+
+/// Whether `word` is a standard or `dialect` keyword, ignoring case.
+pub fn is_keyword(word: &str, dialect: Option<&DatabaseType>) -> bool {
+    let dialect_keywords = match dialect {
+        Some(DatabaseType::Postgres) => POSTGRES_KEYWORDS,
+        Some(DatabaseType::Sqlite) => SQLITE_KEYWORDS,
+        None => &[],
+    };
+    KEYWORDS
+        .iter()
+        .chain(CLAUSE_KEYWORDS)
+        .chain(dialect_keywords)
+        .flat_map(|k| k.split(' '))
+        .any(|k| k.eq_ignore_ascii_case(word))
+}
+
+fn is_reserved(token: &Token) -> bool {
+    token.kind == TokenKind::Word
+        && KEYWORDS
+            .iter()
+            .chain(CLAUSE_KEYWORDS)
+            .flat_map(|k| k.split(' '))
+            .any(|k| token.is_keyword(k))
+}
+
+/// The clause keyword governing the end of `tokens`.
+fn current_clause(tokens: &[&Token]) -> Option<&'static str> {
+    let mut depth = 0i32;
+    for token in tokens.iter().rev() {
+        match token.text {
+            ")" => depth += 1,
+            "(" => depth -= 1,
+            _ if depth == 0 => {
+                if let Some(k) = CLAUSE_KEYWORDS.iter().find(|k| token.is_keyword(k)) {
+                    return Some(k);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn push_columns(
+    candidates: &mut Vec<Candidate>,
+    schema: &SchemaCache,
+    table_schema: Option<&str>,
+    table: &str,
+) {
+    for column in schema.columns_of(table_schema, table) {
+        candidates.push(Candidate {
+            label: column.name.clone(),
+            kind: CandidateKind::Column,
+            detail: Some(format!("{}.{} {}", table, column.name, column.data_type)),
+        });
+    }
+}
+
+fn push_functions(
+    candidates: &mut Vec<Candidate>,
+    schema: &SchemaCache,
+    dialect: Option<&DatabaseType>,
+) {
+    let dialect_functions = match dialect {
+        Some(DatabaseType::Postgres) => POSTGRES_FUNCTIONS,
+        Some(DatabaseType::Sqlite) => SQLITE_FUNCTIONS,
+        None => &[],
+    };
+    let names = FUNCTIONS
+        .iter()
+        .chain(dialect_functions)
+        .map(|f| f.to_string())
+        .chain(schema.functions.iter().cloned());
+    for name in names {
+        candidates.push(Candidate {
+            label: name,
+            kind: CandidateKind::Function,
+            detail: Some("function".to_string()),
+        });
+    }
+}
+
+fn push_keywords(candidates: &mut Vec<Candidate>, dialect: Option<&DatabaseType>, prefix: &str) {
+    let dialect_keywords = match dialect {
+        Some(DatabaseType::Postgres) => POSTGRES_KEYWORDS,
+        Some(DatabaseType::Sqlite) => SQLITE_KEYWORDS,
+        None => &[],
+    };
+    // Follow the case the user is typing in.
+    let lowercase = !prefix.is_empty() && !prefix.chars().any(|c| c.is_uppercase());
+    for keyword in KEYWORDS.iter().chain(dialect_keywords) {
+        candidates.push(Candidate {
+            label: if lowercase {
+                keyword.to_lowercase()
+            } else {
+                keyword.to_string()
+            },
+            kind: CandidateKind::Keyword,
+            detail: None,
+        });
+    }
+}
+
+fn table_candidate(name: &str, schema: Option<&str>) -> Candidate {
+    Candidate {
+        label: name.to_string(),
+        kind: CandidateKind::Table,
+        detail: Some(match schema {
+            Some(schema) => format!("{}.{}", schema, name),
+            None => "table".to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Column, Table};
+    use crate::sql::schema::key;
+
+    fn schema() -> SchemaCache {
+        let column = |name: &str| Column {
+            name: name.to_string(),
+            data_type: "text".to_string(),
+            is_nullable: true,
+            is_primary_key: false,
+        };
+        let mut cache = SchemaCache {
+            tables: vec![
+                Table {
+                    name: "users".to_string(),
+                    schema: Some("public".to_string()),
+                },
+                Table {
+                    name: "orders".to_string(),
+                    schema: Some("sales".to_string()),
+                },
+                Table {
+                    name: "users".to_string(),
+                    schema: Some("audit".to_string()),
+                },
+            ],
+            ..Default::default()
+        };
+        for (table, columns) in [
+            (&cache.tables[0], vec![column("id"), column("email")]),
+            (&cache.tables[1], vec![column("id"), column("user_id")]),
+            (&cache.tables[2], vec![column("changed_at")]),
+        ] {
+            cache.columns.insert(key(table), columns);
+        }
+        cache
+    }
+
+    fn labels(sql: &str) -> Vec<String> {
+        let offset = sql.find('|').unwrap();
+        let sql = sql.replace('|', "");
+        complete(&sql, offset, &schema(), Some(&DatabaseType::Postgres))
+            .candidates
+            .into_iter()
+            .map(|c| c.label)
+            .collect()
+    }
+
+    #[test]
+    fn test_tables_after_from_and_join() {
+        assert_eq!(
+            labels("SELECT * FROM |"),
+            ["users", "orders", "audit", "public", "sales"]
+        );
+        assert_eq!(labels("SELECT * FROM users u JOIN o|"), ["orders"]);
+        assert_eq!(labels("SELECT * FROM users, o|"), ["orders"]);
+    }
+
+    #[test]
+    fn test_columns_of_alias() {
+        assert_eq!(
+            labels("SELECT o.| FROM users u JOIN sales.orders o ON o.user_id = u.id"),
+            ["id", "user_id"]
+        );
+        assert_eq!(labels("SELECT u.e| FROM users AS u"), ["email"]);
+    }
+
+    #[test]
+    fn test_columns_of_same_named_tables() {
+        assert_eq!(labels("SELECT u.| FROM users u"), ["id", "email"]);
+        assert_eq!(labels("SELECT u.| FROM audit.users u"), ["changed_at"]);
+        assert_eq!(labels("SELECT chan| FROM \"audit\".users"), ["changed_at"]);
+    }
+
+    #[test]
+    fn test_tables_of_schema() {
+        assert_eq!(labels("SELECT * FROM sales.|"), ["orders"]);
+    }
+
+    #[test]
+    fn test_anywhere_offers_scope_columns_keywords_and_functions() {
+        let found = labels("SELECT em| FROM users");
+        assert_eq!(found, ["email"]);
+
+        let found = labels("select * from users wh|");
+        assert_eq!(found, ["where", "when"]);
+
+        let found = labels("SELECT date_t|");
+        assert_eq!(found, ["date_trunc"]);
+    }
+
+    #[test]
+    fn test_no_completions_in_strings_or_comments() {
+        assert!(labels("SELECT 'FROM us|").is_empty());
+        assert!(labels("SELECT 1 -- FROM us|").is_empty());
+    }
+
+    #[test]
+    fn test_replace_range_covers_prefix() {
+        let sql = "SELECT * FROM us";
+        let completions = complete(sql, sql.len(), &schema(), None);
+        assert_eq!(completions.replace, 14..16);
+    }
+
+    #[test]
+    fn test_tables_in_scope() {
+        assert_eq!(
+            tables_in_scope("UPDATE \"Users\" SET a = 1"),
+            vec![TableRef {
+                schema: None,
+                name: "Users".to_string(),
+                alias: None,
+            }]
+        );
+    }
+}
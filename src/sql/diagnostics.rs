@@ -0,0 +1,135 @@
+// Problems to underline in the editor buffer.
+//
+// Syntax errors come from the tree-sitter parse while typing. After a failed
+// run, the position the database reported is snapped to the token it points
+// at so the same underline marks the failing token.
+use super::lexer::{Token, tokenize, unquote};
+use crate::db::ErrorLocation;
+use std::ops::Range;
+use tree_sitter::Node;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub range: Range<usize>,
+    pub message: String,
+}
+
+/// Parse errors in `sql`, each narrowed to the token the grammar stumbled on
+/// rather than the whole unparsable region.
+pub fn syntax_errors(sql: &str) -> Vec<SyntaxError> {
+    let Some(tree) = super::parse(sql) else {
+        return vec![];
+    };
+    let tokens = significant_tokens(sql);
+    let mut errors = Vec::new();
+    collect_errors(tree.root_node(), &tokens, &mut errors);
+    errors
+}
+
+/// The byte range of the token `location` points at in `sql`.
+pub fn locate(sql: &str, location: &ErrorLocation) -> Option<Range<usize>> {
+    let tokens = significant_tokens(sql);
+    match location {
+        ErrorLocation::Offset(chars) => {
+            let offset = sql.char_indices().nth(*chars).map_or(sql.len(), |(i, _)| i);
+            token_at(&tokens, offset).map(Token::range)
+        }
+        ErrorLocation::Near(text) => tokens
+            .iter()
+            .find(|t| t.text.eq_ignore_ascii_case(text) || unquote(t.text) == *text)
+            .map(Token::range),
+    }
+}
+
+fn significant_tokens(sql: &str) -> Vec<Token<'_>> {
+    tokenize(sql)
+        .into_iter()
+        .filter(|t| !t.is_trivia())
+        .collect()
+}
+
+fn collect_errors(node: Node, tokens: &[Token], errors: &mut Vec<SyntaxError>) {
+    if node.is_missing() {
+        // Missing nodes are zero-width; mark the token they should follow.
+        let offset = node.start_byte();
+        let before = tokens.iter().rev().find(|t| t.start < offset);
+        if let Some(token) = before.or_else(|| token_at(tokens, offset)) {
+            errors.push(SyntaxError {
+                range: token.range(),
+                message: format!("Missing {}", describe(node.kind())),
+            });
+        }
+        return;
+    }
+    if node.is_error() {
+        if let Some(token) = token_at(tokens, node.start_byte()) {
+            errors.push(SyntaxError {
+                range: token.range(),
+                message: format!("Syntax error near `{}`", token.text),
+            });
+        }
+        return;
+    }
+    if !node.has_error() {
+        return;
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_errors(child, tokens, errors);
+    }
+}
+
+/// The token containing `offset`, or the next one after it.
+fn token_at<'t, 'a>(tokens: &'t [Token<'a>], offset: usize) -> Option<&'t Token<'a>> {
+    tokens
+        .iter()
+        .find(|t| t.range().end > offset)
+        .or_else(|| tokens.last())
+}
+
+fn describe(kind: &str) -> String {
+    match kind.strip_prefix("keyword_") {
+        Some(keyword) => keyword.to_uppercase(),
+        None if kind.chars().all(|c| !c.is_alphanumeric()) => format!("`{}`", kind),
+        None => kind.replace('_', " "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syntax_errors() {
+        assert!(syntax_errors("SELECT id FROM users WHERE id = 1").is_empty());
+
+        let sql = "SELECT id FROM users WHERE WHERE id = 1";
+        let errors = syntax_errors(sql);
+        assert!(!errors.is_empty());
+        assert!(
+            errors
+                .iter()
+                .all(|e| !sql[e.range.clone()].trim().is_empty())
+        );
+    }
+
+    #[test]
+    fn test_locate_offset_and_near() {
+        let sql = "SELECT é, nme FROM t";
+        // PostgreSQL counts characters, not bytes.
+        assert_eq!(
+            locate(sql, &ErrorLocation::Offset(10)).map(|r| &sql[r]),
+            Some("nme")
+        );
+        assert_eq!(
+            locate(sql, &ErrorLocation::Offset(100)).map(|r| &sql[r]),
+            Some("t")
+        );
+        assert_eq!(
+            locate(sql, &ErrorLocation::Near("NME".to_string())).map(|r| &sql[r]),
+            Some("nme")
+        );
+        assert_eq!(locate(sql, &ErrorLocation::Near("x".to_string())), None);
+    }
+}
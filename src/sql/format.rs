@@ -0,0 +1,719 @@
+// SQL pretty-printer for the query editor.
+//
+// Layout works on the lossless token stream, so comments, string literals and
+// dollar-quoted bodies come through untouched. The tree-sitter parse decides
+// which words are keywords: a column called `type` or `name` keeps its case.
+// Inside regions the grammar could not parse, the dialect keyword list is
+// used instead.
+use super::completion::is_keyword;
+use super::lexer::{Token, TokenKind, tokenize};
+use crate::state::DatabaseType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tree_sitter::Node as SyntaxNode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeywordCase {
+    #[default]
+    Upper,
+    Lower,
+    /// Leave keywords as written.
+    Preserve,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommaStyle {
+    /// `a,` at the end of each line.
+    #[default]
+    Trailing,
+    /// `, b` at the start of each continuation line.
+    Leading,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormatOptions {
+    pub keyword_case: KeywordCase,
+    /// Spaces per indentation level.
+    pub indent_width: usize,
+    pub comma_style: CommaStyle,
+    /// Lists and conditions longer than this are broken over several lines.
+    pub max_width: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            keyword_case: KeywordCase::Upper,
+            indent_width: 2,
+            comma_style: CommaStyle::Trailing,
+            max_width: 80,
+        }
+    }
+}
+
+/// Keywords that start a clause on its own line, longest first so that
+/// `LEFT OUTER JOIN` wins over `LEFT JOIN`.
+const CLAUSES: &[&[&str]] = &[
+    &["LEFT", "OUTER", "JOIN"],
+    &["RIGHT", "OUTER", "JOIN"],
+    &["FULL", "OUTER", "JOIN"],
+    &["GROUP", "BY"],
+    &["ORDER", "BY"],
+    &["UNION", "ALL"],
+    &["INSERT", "INTO"],
+    &["DELETE", "FROM"],
+    &["ON", "CONFLICT"],
+    &["INNER", "JOIN"],
+    &["LEFT", "JOIN"],
+    &["RIGHT", "JOIN"],
+    &["FULL", "JOIN"],
+    &["CROSS", "JOIN"],
+    &["NATURAL", "JOIN"],
+    &["WITH"],
+    &["SELECT"],
+    &["FROM"],
+    &["JOIN"],
+    &["WHERE"],
+    &["HAVING"],
+    &["UNION"],
+    &["INTERSECT"],
+    &["EXCEPT"],
+    &["LIMIT"],
+    &["OFFSET"],
+    &["FETCH"],
+    &["UPDATE"],
+    &["SET"],
+    &["VALUES"],
+    &["RETURNING"],
+];
+
+const SQLITE_CLAUSES: &[&[&str]] = &[
+    &["INSERT", "OR", "REPLACE", "INTO"],
+    &["INSERT", "OR", "IGNORE", "INTO"],
+    &["REPLACE", "INTO"],
+];
+
+/// Clauses whose body is a comma-separated list, one item per line when broken.
+const LIST_CLAUSES: &[&str] = &[
+    "SELECT",
+    "GROUP",
+    "ORDER",
+    "RETURNING",
+    "SET",
+    "VALUES",
+    "WITH",
+];
+
+/// Words kept on the clause line ahead of a broken list, e.g. `SELECT DISTINCT`.
+const LIST_MODIFIERS: &[&str] = &["DISTINCT", "ALL", "RECURSIVE"];
+
+/// Pretty-print `sql`, which may hold several `;`-separated statements.
+pub fn format_sql(sql: &str, options: &FormatOptions, dialect: Option<&DatabaseType>) -> String {
+    let keywords = Keywords::classify(sql, dialect);
+    let atoms = tokenize(sql)
+        .into_iter()
+        .scan(None::<Token>, |previous, token| {
+            let atom = (token.kind != TokenKind::Whitespace).then(|| Atom {
+                token,
+                keyword: keywords.contains(&token),
+                newline_before: previous
+                    .is_some_and(|p| p.kind == TokenKind::Whitespace && p.text.contains('\n')),
+                adjacent: previous.is_some_and(|p| p.kind != TokenKind::Whitespace),
+            });
+            *previous = Some(token);
+            Some(atom)
+        })
+        .flatten();
+    let nodes = build_tree(atoms);
+
+    let mut f = Formatter::new(options, dialect);
+    let mut first = true;
+    for statement in nodes.split_inclusive(|n| n.is_text(";")) {
+        let (body, terminated) = match statement.split_last() {
+            Some((last, body)) if last.is_text(";") => (body, true),
+            _ => (statement, false),
+        };
+
+        // Comments on the same line as the previous `;` stay there.
+        let trailing = if first {
+            0
+        } else {
+            body.iter()
+                .take_while(|n| {
+                    n.as_atom()
+                        .is_some_and(|a| a.is_comment() && !a.newline_before)
+                })
+                .count()
+        };
+        for node in &body[..trailing] {
+            f.node(node, 0);
+        }
+        let body = &body[trailing..];
+        if body.is_empty() && !terminated {
+            continue;
+        }
+
+        if !first {
+            f.newline(0);
+            f.out.push('\n');
+        }
+        first = false;
+        f.block(body, 0);
+        if terminated {
+            f.punctuation(";");
+        }
+    }
+
+    let mut out = f.finish();
+    if sql.ends_with('\n') && !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+/// Byte offsets of the words the parser (or, inside syntax errors, the
+/// dialect keyword list) considers keywords.
+struct Keywords {
+    offsets: HashSet<usize>,
+    error_ranges: Vec<std::ops::Range<usize>>,
+    parsed: bool,
+    dialect: Option<DatabaseType>,
+}
+
+impl Keywords {
+    fn classify(sql: &str, dialect: Option<&DatabaseType>) -> Self {
+        let mut keywords = Self {
+            offsets: HashSet::new(),
+            error_ranges: Vec::new(),
+            parsed: false,
+            dialect: dialect.cloned(),
+        };
+        if let Some(tree) = super::parse(sql) {
+            keywords.parsed = true;
+            keywords.collect(tree.root_node());
+        }
+        keywords
+    }
+
+    fn collect(&mut self, node: SyntaxNode) {
+        if node.kind().starts_with("keyword_") {
+            self.offsets.insert(node.start_byte());
+            return;
+        }
+        if node.is_error() {
+            self.error_ranges.push(node.byte_range());
+        }
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            self.collect(child);
+        }
+    }
+
+    fn contains(&self, token: &Token) -> bool {
+        if token.kind != TokenKind::Word {
+            return false;
+        }
+        if self.offsets.contains(&token.start) {
+            return true;
+        }
+        let unparsed = !self.parsed || self.error_ranges.iter().any(|r| r.contains(&token.start));
+        unparsed && is_keyword(token.text, self.dialect.as_ref())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Atom<'a> {
+    token: Token<'a>,
+    keyword: bool,
+    /// A line break separated this token from the previous one.
+    newline_before: bool,
+    /// No whitespace separated this token from the previous one.
+    adjacent: bool,
+}
+
+impl Atom<'_> {
+    fn is_keyword(&self, keyword: &str) -> bool {
+        self.keyword && self.token.text.eq_ignore_ascii_case(keyword)
+    }
+
+    fn is_comment(&self) -> bool {
+        self.token.is_comment()
+    }
+}
+
+#[derive(Debug)]
+enum Node<'a> {
+    Atom(Atom<'a>),
+    /// A parenthesized run of nodes; `close` is missing when unbalanced.
+    Group {
+        open: Atom<'a>,
+        nodes: Vec<Node<'a>>,
+        close: Option<Atom<'a>>,
+    },
+}
+
+impl<'a> Node<'a> {
+    fn as_atom(&self) -> Option<&Atom<'a>> {
+        match self {
+            Node::Atom(atom) => Some(atom),
+            Node::Group { .. } => None,
+        }
+    }
+
+    fn is_text(&self, text: &str) -> bool {
+        self.as_atom().is_some_and(|a| a.token.text == text)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        self.as_atom().is_some_and(|a| a.is_keyword(keyword))
+    }
+
+    /// A parenthesized query such as `(SELECT ...)`, laid out as a block.
+    fn is_subquery(&self) -> bool {
+        match self {
+            Node::Group { nodes, .. } => nodes
+                .iter()
+                .find(|n| !n.as_atom().is_some_and(Atom::is_comment))
+                .is_some_and(|n| n.is_keyword("SELECT") || n.is_keyword("WITH")),
+            Node::Atom(_) => false,
+        }
+    }
+}
+
+fn build_tree<'a>(atoms: impl Iterator<Item = Atom<'a>>) -> Vec<Node<'a>> {
+    let mut stack: Vec<(Atom<'a>, Vec<Node<'a>>)> = Vec::new();
+    let mut nodes = Vec::new();
+    for atom in atoms {
+        match atom.token.text {
+            "(" if atom.token.kind == TokenKind::Punctuation => {
+                stack.push((atom, std::mem::take(&mut nodes)));
+            }
+            ")" if atom.token.kind == TokenKind::Punctuation && !stack.is_empty() => {
+                let (open, outer) = stack.pop().unwrap();
+                let inner = std::mem::replace(&mut nodes, outer);
+                nodes.push(Node::Group {
+                    open,
+                    nodes: inner,
+                    close: Some(atom),
+                });
+            }
+            _ => nodes.push(Node::Atom(atom)),
+        }
+    }
+    while let Some((open, outer)) = stack.pop() {
+        let inner = std::mem::replace(&mut nodes, outer);
+        nodes.push(Node::Group {
+            open,
+            nodes: inner,
+            close: None,
+        });
+    }
+    nodes
+}
+
+/// Split `nodes` at top-level commas, dropping the commas.
+fn split_commas<'n, 'a>(nodes: &'n [Node<'a>]) -> Vec<&'n [Node<'a>]> {
+    nodes.split(|n| n.is_text(",")).collect()
+}
+
+struct Formatter<'o> {
+    options: &'o FormatOptions,
+    dialect: Option<&'o DatabaseType>,
+    out: String,
+    column: usize,
+    /// Set after a line comment: the next token must start a new line at
+    /// this indentation level.
+    pending_break: Option<usize>,
+    /// Text of the last token written, for spacing decisions.
+    last: Option<(TokenKind, String, bool)>,
+    /// The last token was a unary sign, so no space follows it.
+    unary: bool,
+}
+
+impl<'o> Formatter<'o> {
+    fn new(options: &'o FormatOptions, dialect: Option<&'o DatabaseType>) -> Self {
+        Self {
+            options,
+            dialect,
+            out: String::new(),
+            column: 0,
+            pending_break: None,
+            last: None,
+            unary: false,
+        }
+    }
+
+    fn finish(self) -> String {
+        self.out
+            .lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string()
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.out
+            .rsplit('\n')
+            .next()
+            .is_none_or(|line| line.trim().is_empty())
+    }
+
+    /// Start a new line at `level`. Repeated calls do not add blank lines.
+    fn newline(&mut self, level: usize) {
+        let trimmed = self.out.trim_end_matches(' ').len();
+        self.out.truncate(trimmed);
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+        let indent = level * self.options.indent_width;
+        self.out.extend(std::iter::repeat_n(' ', indent));
+        self.column = indent;
+        self.pending_break = None;
+    }
+
+    fn text(&mut self, text: &str, space: bool) {
+        if let Some(level) = self.pending_break {
+            self.newline(level);
+        } else if space && !self.at_line_start() {
+            self.out.push(' ');
+            self.column += 1;
+        }
+        self.out.push_str(text);
+        match text.rfind('\n') {
+            Some(ix) => self.column = text[ix + 1..].chars().count(),
+            None => self.column += text.chars().count(),
+        }
+    }
+
+    fn atom(&mut self, atom: &Atom, level: usize) {
+        let token = &atom.token;
+        if atom.is_comment() && atom.newline_before {
+            self.newline(level);
+        }
+
+        let space = self.needs_space(atom);
+        let unary = matches!(token.text, "-" | "+")
+            && token.kind == TokenKind::Operator
+            && self.last.as_ref().is_none_or(|(kind, text, keyword)| {
+                *keyword || *kind == TokenKind::Operator || matches!(text.as_str(), "(" | ",")
+            });
+
+        let text = match self.options.keyword_case {
+            KeywordCase::Upper if atom.keyword => token.text.to_uppercase(),
+            KeywordCase::Lower if atom.keyword => token.text.to_lowercase(),
+            _ => token.text.to_string(),
+        };
+        self.text(&text, space);
+        self.last = Some((token.kind, token.text.to_string(), atom.keyword));
+        self.unary = unary;
+
+        if token.kind == TokenKind::LineComment {
+            self.pending_break = Some(level);
+        }
+    }
+
+    /// Write a separator that has no token of its own in the layout.
+    fn punctuation(&mut self, text: &str) {
+        self.text(text, false);
+        self.last = Some((TokenKind::Punctuation, text.to_string(), false));
+        self.unary = false;
+    }
+
+    fn needs_space(&self, next: &Atom) -> bool {
+        let Some((last_kind, last, _)) = &self.last else {
+            return false;
+        };
+        let next_text = next.token.text;
+        if self.unary || matches!(next_text, "," | ")" | ";" | ".") {
+            return false;
+        }
+        if matches!(last.as_str(), "(" | ".") {
+            return false;
+        }
+        // PostgreSQL casts hug both operands: `id::text`.
+        if (next_text == "::" || last == "::") && self.dialect != Some(&DatabaseType::Sqlite) {
+            return false;
+        }
+        // Function calls and array subscripts keep the spacing they were
+        // written with; `count(*)` and `IN (...)` both stay as they are.
+        if next_text == "(" || next_text.starts_with('[') {
+            return !next.adjacent || *last_kind == TokenKind::Operator;
+        }
+        true
+    }
+
+    fn node(&mut self, node: &Node, level: usize) {
+        match node {
+            Node::Atom(atom) => self.atom(atom, level),
+            Node::Group { open, nodes, close } => {
+                self.atom(open, level);
+                if node.is_subquery() {
+                    self.block(nodes, level + 1);
+                    self.newline(level);
+                } else if self.fits(nodes) {
+                    self.nodes(nodes, level);
+                } else {
+                    self.list_items(&split_commas(nodes), level + 1);
+                    self.newline(level);
+                }
+                if let Some(close) = close {
+                    self.atom(close, level);
+                }
+            }
+        }
+    }
+
+    fn nodes(&mut self, nodes: &[Node], level: usize) {
+        for node in nodes {
+            self.node(node, level);
+        }
+    }
+
+    /// Whether `nodes` can be written on the rest of the current line.
+    fn fits(&self, nodes: &[Node]) -> bool {
+        let unlimited = FormatOptions {
+            max_width: usize::MAX,
+            ..self.options.clone()
+        };
+        let mut probe = Formatter::new(&unlimited, self.dialect);
+        probe.last = self.last.clone();
+        probe.nodes(nodes, 0);
+        !probe.out.contains('\n')
+            && probe.pending_break.is_none()
+            && self.column + 1 + probe.column <= self.options.max_width
+    }
+
+    /// Lay out one statement or subquery, one clause per line.
+    fn block(&mut self, nodes: &[Node], level: usize) {
+        for (header, body) in self.clauses(nodes) {
+            self.newline(level);
+            self.nodes(header, level);
+            self.clause_body(header, body, level);
+        }
+    }
+
+    fn clauses<'n, 'a>(&self, nodes: &'n [Node<'a>]) -> Vec<(&'n [Node<'a>], &'n [Node<'a>])> {
+        let mut starts = vec![(0, 0)];
+        let mut ix = 0;
+        while ix < nodes.len() {
+            match self.clause_at(nodes, ix) {
+                Some(len) => {
+                    if ix > 0 {
+                        starts.push((ix, len));
+                    } else {
+                        starts[0].1 = len;
+                    }
+                    ix += len;
+                }
+                None => ix += 1,
+            }
+        }
+
+        let mut clauses = Vec::new();
+        for (i, &(start, header_len)) in starts.iter().enumerate() {
+            let end = starts.get(i + 1).map_or(nodes.len(), |&(next, _)| next);
+            if start == end {
+                continue;
+            }
+            clauses.push((
+                &nodes[start..start + header_len],
+                &nodes[start + header_len..end],
+            ));
+        }
+        clauses
+    }
+
+    /// Length of the clause header starting at `nodes[ix]`, if any.
+    fn clause_at(&self, nodes: &[Node], ix: usize) -> Option<usize> {
+        // `DO UPDATE` and `FOR UPDATE` continue the clause before them.
+        if nodes[ix].is_keyword("UPDATE")
+            && ix > 0
+            && nodes[ix - 1].as_atom().is_some_and(|a| a.keyword)
+        {
+            return None;
+        }
+        let dialect_clauses: &[&[&str]] = match self.dialect {
+            Some(DatabaseType::Sqlite) => SQLITE_CLAUSES,
+            _ => &[],
+        };
+        dialect_clauses
+            .iter()
+            .chain(CLAUSES)
+            .find(|words| {
+                nodes.len() >= ix + words.len()
+                    && words
+                        .iter()
+                        .zip(&nodes[ix..])
+                        .all(|(word, node)| node.is_keyword(word))
+            })
+            .map(|words| words.len())
+    }
+
+    fn clause_body(&mut self, header: &[Node], body: &[Node], level: usize) {
+        let is_list = header
+            .first()
+            .is_some_and(|h| LIST_CLAUSES.iter().any(|k| h.is_keyword(k)));
+        if !is_list {
+            self.conditions(body, level);
+            return;
+        }
+
+        let mut modifiers = body
+            .iter()
+            .take_while(|n| LIST_MODIFIERS.iter().any(|k| n.is_keyword(k)))
+            .count();
+        // `DISTINCT ON (a, b)`
+        if modifiers > 0
+            && body.get(modifiers).is_some_and(|n| n.is_keyword("ON"))
+            && matches!(body.get(modifiers + 1), Some(Node::Group { .. }))
+        {
+            modifiers += 2;
+        }
+        let (modifiers, body) = body.split_at(modifiers);
+        self.nodes(modifiers, level);
+
+        let items = split_commas(body);
+        if items.len() == 1 || self.fits(body) {
+            self.nodes(body, level);
+        } else {
+            self.list_items(&items, level + 1);
+        }
+    }
+
+    /// Write comma-separated `items`, each on its own line at `level`.
+    fn list_items(&mut self, items: &[&[Node]], level: usize) {
+        for (i, item) in items.iter().enumerate() {
+            // Comments written on the line of the previous item stay there.
+            let trailing = item
+                .iter()
+                .take_while(|n| {
+                    n.as_atom()
+                        .is_some_and(|a| a.is_comment() && !a.newline_before)
+                })
+                .count();
+            let (comments, item) = item.split_at(trailing);
+
+            if i > 0 && self.options.comma_style == CommaStyle::Trailing {
+                self.punctuation(",");
+            }
+            self.nodes(comments, level);
+            self.newline(level);
+            if i > 0 && self.options.comma_style == CommaStyle::Leading {
+                self.punctuation(",");
+            }
+            self.nodes(item, level);
+        }
+    }
+
+    /// Write a condition-like clause body, breaking before top-level
+    /// `AND`/`OR` when it does not fit.
+    fn conditions(&mut self, body: &[Node], level: usize) {
+        if self.fits(body) {
+            self.nodes(body, level);
+            return;
+        }
+
+        let mut segment_start = 0;
+        let mut in_between = false;
+        for (ix, node) in body.iter().enumerate() {
+            if node.is_keyword("BETWEEN") {
+                in_between = true;
+            } else if node.is_keyword("AND") && in_between {
+                in_between = false;
+            } else if node.is_keyword("AND") || node.is_keyword("OR") {
+                self.nodes(&body[segment_start..ix], level);
+                self.newline(level + 1);
+                segment_start = ix;
+            }
+        }
+        self.nodes(&body[segment_start..], level);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(sql: &str) -> String {
+        format_sql(
+            sql,
+            &FormatOptions::default(),
+            Some(&DatabaseType::Postgres),
+        )
+    }
+
+    #[test]
+    fn test_format_select() {
+        assert_eq!(
+            format(
+                "select id, type from users u left join orders o on o.user_id = u.id where u.active order by id desc limit 10"
+            ),
+            "SELECT id, type\nFROM users u\nLEFT JOIN orders o ON o.user_id = u.id\nWHERE u.active\nORDER BY id DESC\nLIMIT 10"
+        );
+    }
+
+    #[test]
+    fn test_format_breaks_long_lists_and_conditions() {
+        let sql = "select first_name, last_name, email_address, created_at, updated_at, deleted_at, id from people where first_name = 'a' and last_name = 'b' and created_at between '2020-01-01' and '2021-01-01'";
+        assert_eq!(
+            format(sql),
+            "SELECT\n  first_name,\n  last_name,\n  email_address,\n  created_at,\n  updated_at,\n  deleted_at,\n  id\nFROM people\nWHERE first_name = 'a'\n  AND last_name = 'b'\n  AND created_at BETWEEN '2020-01-01' AND '2021-01-01'"
+        );
+
+        let options = FormatOptions {
+            comma_style: CommaStyle::Leading,
+            keyword_case: KeywordCase::Lower,
+            indent_width: 4,
+            max_width: 20,
+        };
+        assert_eq!(
+            format_sql("SELECT alpha, beta, gamma FROM t", &options, None),
+            "select\n    alpha\n    , beta\n    , gamma\nfrom t"
+        );
+    }
+
+    #[test]
+    fn test_format_subqueries_and_calls() {
+        assert_eq!(
+            format("select count(*), max(x) from (select x from t where x in (1, 2)) q"),
+            "SELECT count(*), max(x)\nFROM (\n  SELECT x\n  FROM t\n  WHERE x IN (1, 2)\n) q"
+        );
+    }
+
+    #[test]
+    fn test_format_preserves_comments() {
+        assert_eq!(
+            format("-- users\nselect a, -- first\n  b /* second */ from t; -- done\n"),
+            "-- users\nSELECT\n  a, -- first\n  b /* second */\nFROM t; -- done\n"
+        );
+    }
+
+    #[test]
+    fn test_format_postgres_specifics() {
+        assert_eq!(
+            format("insert into t (a, b) values (1, 'x') returning id, a :: text"),
+            "INSERT INTO t (a, b)\nVALUES (1, 'x')\nRETURNING id, a::TEXT"
+        );
+        assert_eq!(
+            format("update t set a = -1 where id = $1; select 1"),
+            "UPDATE t\nSET a = -1\nWHERE id = $1;\n\nSELECT 1"
+        );
+    }
+
+    #[test]
+    fn test_format_sqlite_specifics() {
+        assert_eq!(
+            format_sql(
+                "insert or replace into t values (1) returning *",
+                &FormatOptions::default(),
+                Some(&DatabaseType::Sqlite)
+            ),
+            "INSERT OR REPLACE INTO t\nVALUES (1)\nRETURNING *"
+        );
+    }
+}
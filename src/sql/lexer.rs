@@ -0,0 +1,280 @@
+// A small SQL tokenizer shared by the editor tooling.
+//
+// It is lossless: concatenating every token's text reproduces the input, so
+// callers can map tokens back to byte offsets in the editor buffer. It knows
+// just enough of both dialects to never split a string, quoted identifier,
+// comment or PostgreSQL dollar-quoted body.
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Keywords and unquoted identifiers.
+    Word,
+    /// `"name"`, `` `name` `` or `[name]`.
+    QuotedIdentifier,
+    /// `'text'` or `$tag$text$tag$`.
+    String,
+    Number,
+    LineComment,
+    BlockComment,
+    Whitespace,
+    /// `$1`, `?`, `:name`, `@name`.
+    Parameter,
+    /// Operators such as `=`, `<>`, `::` or `||`.
+    Operator,
+    /// `(`, `)`, `,`, `;` and `.`.
+    Punctuation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub start: usize,
+}
+
+impl Token<'_> {
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.start + self.text.len()
+    }
+
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self.kind,
+            TokenKind::Whitespace | TokenKind::LineComment | TokenKind::BlockComment
+        )
+    }
+
+    pub fn is_comment(&self) -> bool {
+        matches!(self.kind, TokenKind::LineComment | TokenKind::BlockComment)
+    }
+
+    /// Case-insensitive keyword comparison; never matches quoted names.
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        self.kind == TokenKind::Word && self.text.eq_ignore_ascii_case(keyword)
+    }
+}
+
+const MULTI_CHAR_OPERATORS: [&str; 10] =
+    ["->>", "::", "<=", ">=", "<>", "!=", "||", "->", "<<", ">>"];
+
+pub fn tokenize(sql: &str) -> Vec<Token<'_>> {
+    let bytes = sql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < sql.len() {
+        let rest = &sql[pos..];
+        let c = rest.chars().next().unwrap();
+
+        let (kind, len) = if c.is_whitespace() {
+            (TokenKind::Whitespace, take_while(rest, char::is_whitespace))
+        } else if rest.starts_with("--") {
+            (
+                TokenKind::LineComment,
+                rest.find('\n').unwrap_or(rest.len()),
+            )
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            (
+                TokenKind::BlockComment,
+                body.find("*/").map_or(rest.len(), |end| end + 4),
+            )
+        } else if c == '\'' {
+            (TokenKind::String, quoted_len(rest, '\''))
+        } else if c == '"' {
+            (TokenKind::QuotedIdentifier, quoted_len(rest, '"'))
+        } else if c == '`' {
+            (TokenKind::QuotedIdentifier, quoted_len(rest, '`'))
+        } else if c == '[' {
+            (
+                TokenKind::QuotedIdentifier,
+                rest.find(']').map_or(rest.len(), |end| end + 1),
+            )
+        } else if c == '$' {
+            match dollar_quote_len(rest) {
+                Some(len) => (TokenKind::String, len),
+                None => (
+                    TokenKind::Parameter,
+                    1 + take_while(&rest[1..], is_word_char),
+                ),
+            }
+        } else if c.is_ascii_digit()
+            || (c == '.' && bytes.get(pos + 1).is_some_and(u8::is_ascii_digit))
+        {
+            (TokenKind::Number, number_len(rest))
+        } else if is_word_char(c) {
+            (TokenKind::Word, take_while(rest, is_word_char))
+        } else if c == '?' {
+            (
+                TokenKind::Parameter,
+                1 + take_while(&rest[1..], |c| c.is_ascii_digit()),
+            )
+        } else if (c == ':' || c == '@')
+            && !rest.starts_with("::")
+            && rest[1..].starts_with(|c: char| c.is_alphabetic() || c == '_')
+        {
+            (
+                TokenKind::Parameter,
+                1 + take_while(&rest[1..], is_word_char),
+            )
+        } else if matches!(c, '(' | ')' | ',' | ';' | '.') {
+            (TokenKind::Punctuation, 1)
+        } else if let Some(op) = MULTI_CHAR_OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+            (TokenKind::Operator, op.len())
+        } else {
+            (TokenKind::Operator, c.len_utf8())
+        };
+
+        tokens.push(Token {
+            kind,
+            text: &sql[pos..pos + len],
+            start: pos,
+        });
+        pos += len;
+    }
+
+    tokens
+}
+
+/// Byte ranges of the `;`-separated statements in `sql`, excluding the
+/// separators themselves. Semicolons inside strings and comments are ignored.
+pub fn statement_ranges(sql: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    for token in tokenize(sql) {
+        if token.kind == TokenKind::Punctuation && token.text == ";" {
+            ranges.push(start..token.start);
+            start = token.start + 1;
+        }
+    }
+    ranges.push(start..sql.len());
+    ranges
+}
+
+/// The statement containing `offset`, as a byte range into `sql`.
+pub fn statement_at(sql: &str, offset: usize) -> Range<usize> {
+    statement_ranges(sql)
+        .into_iter()
+        .find(|r| offset <= r.end)
+        .unwrap_or(0..sql.len())
+}
+
+pub fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Strip identifier quotes from `name`, undoing doubled-quote escapes.
+pub fn unquote(name: &str) -> String {
+    let unquoted = name
+        .strip_prefix('"')
+        .and_then(|n| n.strip_suffix('"'))
+        .or_else(|| name.strip_prefix('`').and_then(|n| n.strip_suffix('`')))
+        .or_else(|| name.strip_prefix('[').and_then(|n| n.strip_suffix(']')));
+    match unquoted {
+        Some(n) => n.replace("\"\"", "\""),
+        None => name.to_string(),
+    }
+}
+
+fn take_while(s: &str, f: impl Fn(char) -> bool) -> usize {
+    s.find(|c: char| !f(c)).unwrap_or(s.len())
+}
+
+/// Length of a quoted token starting at `s`, where a doubled quote escapes
+/// itself. Unterminated tokens run to the end of the input.
+fn quoted_len(s: &str, quote: char) -> usize {
+    let mut chars = s.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == quote {
+            if chars.peek().is_some_and(|&(_, next)| next == quote) {
+                chars.next();
+            } else {
+                return i + 1;
+            }
+        }
+    }
+    s.len()
+}
+
+fn dollar_quote_len(s: &str) -> Option<usize> {
+    let tag_len = s[1..].find('$')? + 2;
+    let tag = &s[..tag_len];
+    if !tag[1..tag_len - 1].chars().all(is_word_char)
+        || tag[1..].starts_with(|c: char| c.is_ascii_digit())
+    {
+        return None;
+    }
+    Some(
+        s[tag_len..]
+            .find(tag)
+            .map_or(s.len(), |end| tag_len + end + tag_len),
+    )
+}
+
+fn number_len(s: &str) -> usize {
+    let mut len = take_while(s, |c| c.is_ascii_digit());
+    if s[len..].starts_with('.') {
+        len += 1 + take_while(&s[len + 1..], |c| c.is_ascii_digit());
+    }
+    if s[len..].starts_with(['e', 'E']) {
+        let exp = &s[len + 1..];
+        let sign = usize::from(exp.starts_with(['+', '-']));
+        let digits = take_while(&exp[sign..], |c| c.is_ascii_digit());
+        if digits > 0 {
+            len += 1 + sign + digits;
+        }
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(sql: &str) -> Vec<(TokenKind, &str)> {
+        tokenize(sql)
+            .into_iter()
+            .filter(|t| t.kind != TokenKind::Whitespace)
+            .map(|t| (t.kind, t.text))
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize_is_lossless() {
+        let sql = "SELECT a::int, 'it''s' -- note\nFROM \"t\" /* c */ WHERE b <> $1;";
+        let joined: String = tokenize(sql).iter().map(|t| t.text).collect();
+        assert_eq!(joined, sql);
+    }
+
+    #[test]
+    fn test_tokenize_kinds() {
+        assert_eq!(
+            kinds("x::text || $body$ a;b $body$ >= 1.5e3"),
+            vec![
+                (TokenKind::Word, "x"),
+                (TokenKind::Operator, "::"),
+                (TokenKind::Word, "text"),
+                (TokenKind::Operator, "||"),
+                (TokenKind::String, "$body$ a;b $body$"),
+                (TokenKind::Operator, ">="),
+                (TokenKind::Number, "1.5e3"),
+            ]
+        );
+        assert_eq!(
+            kinds("[my col] = :name"),
+            vec![
+                (TokenKind::QuotedIdentifier, "[my col]"),
+                (TokenKind::Operator, "="),
+                (TokenKind::Parameter, ":name"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_statement_ranges_skip_quoted_semicolons() {
+        let sql = "SELECT ';' FROM a; -- ;\nSELECT 2";
+        let statements: Vec<&str> = statement_ranges(sql).into_iter().map(|r| &sql[r]).collect();
+        assert_eq!(statements, vec!["SELECT ';' FROM a", " -- ;\nSELECT 2"]);
+        assert_eq!(&sql[statement_at(sql, sql.len())], " -- ;\nSELECT 2");
+    }
+}
@@ -0,0 +1,24 @@
+//! SQL language tooling for the query editor: tokenizing, completion,
+//! diagnostics, formatting and the cached schema model completions draw from.
+//!
+//! Parsing uses the tree-sitter SQL grammar; the same tree-sitter runtime is
+//! shared with gpui-component's editor highlighting.
+pub mod completion;
+pub mod diagnostics;
+pub mod format;
+pub mod lexer;
+pub mod schema;
+
+pub use schema::SchemaCache;
+
+use tree_sitter::{Parser, Tree};
+
+/// Parse `sql` with the tree-sitter SQL grammar. The tree always exists for
+/// valid UTF-8 input; syntax errors show up as `ERROR` and missing nodes.
+pub fn parse(sql: &str) -> Option<Tree> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_sequel::LANGUAGE.into())
+        .ok()?;
+    parser.parse(sql, None)
+}
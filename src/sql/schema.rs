@@ -0,0 +1,90 @@
+// Cached schema model for editor tooling.
+//
+// Loaded once per connection so completions never wait on the database.
+use crate::db::{Column, DatabaseClient, Table};
+use anyhow::Result;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default)]
+pub struct SchemaCache {
+    pub tables: Vec<Table>,
+    /// Columns keyed by lowercase schema and table name.
+    pub columns: HashMap<(Option<String>, String), Vec<Column>>,
+    /// User-visible functions reported by the database.
+    pub functions: Vec<String>,
+}
+
+impl SchemaCache {
+    /// Introspect columns and functions for `tables`. Tables whose columns
+    /// cannot be read are kept without columns rather than failing the load.
+    pub async fn load(client: &dyn DatabaseClient, tables: Vec<Table>) -> Result<Self> {
+        let mut columns = HashMap::new();
+        for table in &tables {
+            if let Ok(cols) = client.get_columns(table).await {
+                columns.insert(key(table), cols);
+            }
+        }
+        let functions = client.get_functions().await.unwrap_or_default();
+
+        Ok(Self {
+            tables,
+            columns,
+            functions,
+        })
+    }
+
+    pub fn schemas(&self) -> Vec<&str> {
+        let mut schemas: Vec<&str> = self
+            .tables
+            .iter()
+            .filter_map(|t| t.schema.as_deref())
+            .collect();
+        schemas.sort_unstable();
+        schemas.dedup();
+        schemas
+    }
+
+    /// Columns of the table `schema.table_name` refers to; see [`Self::resolve`].
+    pub fn columns_of(&self, schema: Option<&str>, table_name: &str) -> &[Column] {
+        self.resolve(schema, table_name)
+            .and_then(|table| self.columns.get(&key(table)))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn has_table(&self, table_name: &str) -> bool {
+        self.resolve(None, table_name).is_some()
+    }
+
+    /// The cached table a possibly unqualified name refers to. Without a
+    /// schema, one in `public` wins, as on the default search path, and
+    /// otherwise the first one listed.
+    fn resolve(&self, schema: Option<&str>, table_name: &str) -> Option<&Table> {
+        let mut named = self
+            .tables
+            .iter()
+            .filter(|t| t.name.eq_ignore_ascii_case(table_name));
+        match schema {
+            Some(schema) => named.find(|t| {
+                t.schema
+                    .as_deref()
+                    .is_some_and(|s| s.eq_ignore_ascii_case(schema))
+            }),
+            None => {
+                let named: Vec<&Table> = named.collect();
+                named
+                    .iter()
+                    .find(|t| t.schema.as_deref() == Some("public"))
+                    .or(named.first())
+                    .copied()
+            }
+        }
+    }
+}
+
+/// Lookup key of a table's columns.
+pub fn key(table: &Table) -> (Option<String>, String) {
+    (
+        table.schema.as_deref().map(str::to_lowercase),
+        table.name.to_lowercase(),
+    )
+}
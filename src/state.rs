@@ -1,71 +1,608 @@
-use crate::db::{DatabaseClient, QueryResult, Table};
+use crate::column_layout::LayoutStore;
+use crate::db::{DatabaseClient, PostgresClient, SqliteClient, Table};
+use crate::history::QueryHistory;
+use crate::notifications::Notifications;
+use crate::persistence::{LoadedConnections, Settings};
+use crate::saved_queries::QueryLibrary;
+use crate::sql::SchemaCache;
+use crate::tabs::QueryTab;
+use crate::vault::Vault;
+use anyhow::{Result, anyhow, bail};
 use gpui::{Context, Entity, IntoElement, Render, Window, div};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
 use std::sync::Arc;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub enum DatabaseType {
+    #[default]
     Sqlite,
     Postgres,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct ConnectionConfig {
     pub name: String,
     pub db_type: DatabaseType,
+    /// URL or file path, without the password.
     pub connection_string: String,
+    /// Kept in the keyring or vault, never in `connections.json`.
+    #[serde(skip)]
+    pub password: String,
+    /// Prompt for the password on each connect instead of storing it.
+    #[serde(default)]
+    pub ask_password: bool,
+    /// The shared connections file this came from; importing that file
+    /// again updates the connection instead of adding another.
+    #[serde(default)]
+    pub shared_from: Option<String>,
+    /// Ask for the password on the next connect and remember it, for
+    /// connections imported without one.
+    #[serde(default)]
+    pub needs_credentials: bool,
+    /// Opened from the command line and not saved to `connections.json`
+    /// until the user chooses to keep it.
+    #[serde(skip)]
+    pub ephemeral: bool,
+    /// The stored password couldn't be read when loading, so `password` is
+    /// empty; saving leaves the stored one alone instead of deleting it.
+    #[serde(skip)]
+    pub password_unreadable: bool,
+}
+
+impl ConnectionConfig {
+    /// The URL to connect with, given the password to log in with.
+    pub fn connect_url(&self, password: &str) -> Result<String> {
+        match self.db_type {
+            DatabaseType::Sqlite => Ok(self.connection_string.clone()),
+            DatabaseType::Postgres => crate::db::with_password(&self.connection_string, password),
+        }
+    }
+
+    /// Open a client for this connection's database at `url`.
+    pub async fn open_client(&self, url: &str) -> Result<Arc<dyn DatabaseClient>> {
+        Ok(match self.db_type {
+            DatabaseType::Sqlite => Arc::new(SqliteClient::new(url).await?),
+            DatabaseType::Postgres => Arc::new(PostgresClient::new(url).await?),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionStatus {
+    Connecting,
+    Connected,
+    Failed(String),
+}
+
+/// An opened connection and what has been loaded from it.
+pub struct ConnectionSession {
+    pub name: String,
+    pub status: ConnectionStatus,
+    /// Set once connected.
+    pub client: Option<Arc<dyn DatabaseClient>>,
+    pub tables: Vec<Table>,
+    /// Tables, columns and functions, for completions.
+    pub schema: Option<Arc<SchemaCache>>,
+    /// Whether the object tree is expanded in the sidebar.
+    pub expanded: bool,
+}
+
+impl ConnectionSession {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            status: ConnectionStatus::Connecting,
+            client: None,
+            tables: Vec::new(),
+            schema: None,
+            expanded: true,
+        }
+    }
 }
 
 pub struct AppState {
     pub connections: Vec<ConnectionConfig>,
-    pub active_connection: Option<Arc<dyn DatabaseClient>>,
+    /// Encrypted store for secrets the keyring could not take.
+    pub vault: Vault,
+    /// `connections.json` or the keyring still hold secrets in an older
+    /// layout because there was nowhere safe to move them yet.
+    pub secrets_migration_pending: bool,
+    pub settings: Settings,
+    /// Open connections, in the order they were opened.
+    pub sessions: Vec<ConnectionSession>,
+    /// Connection last picked in the sidebar; tabs that have not run a query
+    /// yet use it.
     pub active_connection_name: Option<String>,
-    pub tables: Vec<Table>,
-    pub query_results: Option<QueryResult>,
-    pub result_id: usize,
-    pub current_query: Option<String>,
-    pub current_table: Option<String>,
-    pub sort_column: Option<String>,
-    pub sort_ascending: bool,
+    /// Open editor tabs, in display order.
+    pub tabs: Vec<QueryTab>,
+    /// Index of the selected tab.
+    pub active_tab: usize,
+    next_tab_id: usize,
+    /// Every query run from the editor, persisted across sessions.
+    pub history: QueryHistory,
+    /// Saved queries and snippets from the config directory.
+    pub saved_queries: QueryLibrary,
+    /// Column widths, order and visibility of results, by table or query.
+    pub column_layouts: LayoutStore,
     pub is_connecting: bool,
-    pub error_message: Option<String>,
+    /// Toasts and their history, for errors and other messages.
+    pub notifications: Notifications,
 }
 
 impl AppState {
     pub fn new() -> Self {
-        let connections = crate::persistence::load_connections().unwrap_or_else(|e| {
-            eprintln!("Failed to load connections: {}", e);
-            vec![]
+        let mut vault = Vault::load().unwrap_or_else(|e| {
+            tracing::error!(error = %e, "Failed to load credential vault");
+            Vault::default()
+        });
+        // Parts of the config files that couldn't be read, shown once loaded.
+        let mut config_problems = Vec::new();
+        let loaded = crate::persistence::load_connections(&vault).unwrap_or_else(|e| {
+            tracing::error!(error = %e, "Failed to load connections");
+            config_problems.push(e.to_string());
+            LoadedConnections::default()
+        });
+        let connections = loaded.connections;
+        let mut secrets_migration_pending = loaded.migration_pending;
+        config_problems.extend(loaded.skipped);
+        // Rewrite secrets stored the way older versions did.
+        if secrets_migration_pending {
+            match crate::persistence::save_connections(&connections, &mut vault) {
+                Ok(()) => secrets_migration_pending = false,
+                Err(e) => tracing::warn!(error = %e, "Failed to migrate connection secrets"),
+            }
+        }
+
+        let settings = match crate::persistence::load_settings() {
+            Ok((settings, skipped)) => {
+                config_problems.extend(skipped);
+                settings
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to load settings");
+                config_problems.push(e.to_string());
+                Settings::default()
+            }
+        };
+        let mut notifications = Notifications::default();
+        for problem in &config_problems {
+            tracing::warn!(problem = %problem, "Skipped part of the config");
+        }
+        if !config_problems.is_empty() {
+            notifications.warning(
+                "Some saved settings couldn't be read",
+                config_problems.join("\n"),
+            );
+        }
+
+        let history = QueryHistory::load().unwrap_or_else(|e| {
+            tracing::error!(error = %e, "Failed to load query history");
+            QueryHistory::default()
+        });
+
+        let saved_queries = QueryLibrary::load().unwrap_or_else(|e| {
+            tracing::error!(error = %e, "Failed to load saved queries");
+            QueryLibrary::default()
+        });
+
+        let column_layouts = LayoutStore::load().unwrap_or_else(|e| {
+            tracing::error!(error = %e, "Failed to load column layouts");
+            LayoutStore::default()
         });
 
         Self {
             connections,
-            active_connection: None,
+            vault,
+            secrets_migration_pending,
+            settings,
+            sessions: Vec::new(),
             active_connection_name: None,
-            tables: Vec::new(),
-            query_results: None,
-            result_id: 0,
-            current_query: None,
-            current_table: None,
-            sort_column: None,
-            sort_ascending: true,
+            tabs: Vec::new(),
+            active_tab: 0,
+            next_tab_id: 0,
+            history,
+            saved_queries,
+            column_layouts,
             is_connecting: false,
-            error_message: None,
+            notifications,
         }
     }
 
-    pub fn add_connection(&mut self, config: ConnectionConfig) {
+    pub fn add_connection(&mut self, config: ConnectionConfig) -> Result<()> {
+        check_connection_name(&self.connections, &config.name, None)?;
         self.connections.push(config);
-        if let Err(e) = crate::persistence::save_connections(&self.connections) {
-            eprintln!("Failed to save connections: {}", e);
+        crate::persistence::save_connections(&self.connections, &mut self.vault)
+    }
+
+    /// List a connection opened from the command line without saving it, and
+    /// return the name to select. A saved connection to the same database is
+    /// used instead; a clashing name gets a number appended.
+    pub fn add_ephemeral_connection(&mut self, mut config: ConnectionConfig) -> String {
+        if let Some(existing) = self.connections.iter().find(|c| {
+            c.db_type == config.db_type && c.connection_string == config.connection_string
+        }) {
+            return existing.name.clone();
+        }
+        config.name = (1..)
+            .map(|n| match n {
+                1 => config.name.clone(),
+                n => format!("{} ({})", config.name, n),
+            })
+            .find(|candidate| self.connections.iter().all(|c| &c.name != candidate))
+            .unwrap_or_default();
+        config.ephemeral = true;
+        let name = config.name.clone();
+        self.connections.push(config);
+        name
+    }
+
+    /// Save an ephemeral connection, and its password, like any other.
+    pub fn keep_connection(&mut self, name: &str) -> Result<()> {
+        let conn = self
+            .connections
+            .iter_mut()
+            .find(|c| c.name == name)
+            .ok_or_else(|| anyhow!("Unknown connection '{}'", name))?;
+        conn.ephemeral = false;
+        let result = crate::persistence::save_connections(&self.connections, &mut self.vault);
+        if result.is_err()
+            && let Some(conn) = self.connections.iter_mut().find(|c| c.name == name)
+        {
+            conn.ephemeral = true;
+        }
+        result
+    }
+
+    /// Replace the connection named `original` with `config`. A rename moves
+    /// its keyring entry, open session and tab bindings to the new name.
+    pub fn update_connection(&mut self, original: &str, config: ConnectionConfig) -> Result<()> {
+        check_connection_name(&self.connections, &config.name, Some(original))?;
+        let ix = self
+            .connections
+            .iter()
+            .position(|c| c.name == original)
+            .ok_or_else(|| anyhow!("Unknown connection '{}'", original))?;
+        let name = config.name.clone();
+        self.connections[ix] = config;
+        // Writes the secret under the new name before the old one goes.
+        crate::persistence::save_connections(&self.connections, &mut self.vault)?;
+        if name == original {
+            return Ok(());
+        }
+
+        if let Err(e) = crate::persistence::delete_secret(original, &mut self.vault) {
+            tracing::warn!(connection = original, error = %e, "Failed to remove keyring entry");
+        }
+        if let Some(session) = self.session_mut(original) {
+            session.name = name.clone();
+        }
+        for tab in &mut self.tabs {
+            if tab.connection.as_deref() == Some(original) {
+                tab.connection = Some(name.clone());
+            }
+        }
+        if self.active_connection_name.as_deref() == Some(original) {
+            self.active_connection_name = Some(name);
+        }
+        Ok(())
+    }
+
+    /// Insert a copy of the connection at `ix` right after it.
+    pub fn duplicate_connection(&mut self, ix: usize) -> Result<()> {
+        let mut copy = self
+            .connections
+            .get(ix)
+            .cloned()
+            .ok_or_else(|| anyhow!("Unknown connection"))?;
+        copy.name = copy_name(&self.connections, &copy.name);
+        copy.shared_from = None;
+        self.connections.insert(ix + 1, copy);
+        crate::persistence::save_connections(&self.connections, &mut self.vault)
+    }
+
+    /// Add connections found by an importer, saving their passwords to the
+    /// keyring. One from the same shared file as an existing connection
+    /// updates it instead, keeping its password. One whose name is taken by
+    /// another connection is skipped, and why is returned. Nothing changes
+    /// if the connections can't be saved.
+    pub fn import_connections(&mut self, configs: Vec<ConnectionConfig>) -> Result<Vec<String>> {
+        let previous = self.connections.clone();
+        let mut skipped = Vec::new();
+        for config in configs {
+            match self.connections.iter_mut().find(|c| c.name == config.name) {
+                Some(existing) if is_shared_update(existing, &config) => {
+                    existing.db_type = config.db_type;
+                    existing.connection_string = config.connection_string;
+                    existing.ask_password = config.ask_password;
+                    // Follows the file if it was moved.
+                    existing.shared_from = config.shared_from;
+                }
+                _ => match check_connection_name(&self.connections, &config.name, None) {
+                    Ok(()) => self.connections.push(config),
+                    Err(e) => skipped.push(e.to_string()),
+                },
+            }
+        }
+        if let Err(e) = crate::persistence::save_connections(&self.connections, &mut self.vault) {
+            self.connections = previous;
+            return Err(e);
+        }
+        Ok(skipped)
+    }
+
+    /// Save the password entered for a connection that was imported
+    /// without one, so it isn't asked for again.
+    pub fn remember_password(&mut self, name: &str, password: &str) -> Result<()> {
+        let conn = self
+            .connections
+            .iter_mut()
+            .find(|c| c.name == name)
+            .ok_or_else(|| anyhow!("Unknown connection '{}'", name))?;
+        let previous = conn.clone();
+        conn.password = password.to_string();
+        conn.needs_credentials = false;
+        let result = crate::persistence::save_connections(&self.connections, &mut self.vault);
+        if result.is_err()
+            && let Some(conn) = self.connections.iter_mut().find(|c| c.name == name)
+        {
+            *conn = previous;
+        }
+        result
+    }
+
+    /// Forget a connection and its keyring entry, returning the client of
+    /// its open session so the caller can close the pool.
+    pub fn remove_connection(&mut self, name: &str) -> Result<Option<Arc<dyn DatabaseClient>>> {
+        let remaining: Vec<ConnectionConfig> = self
+            .connections
+            .iter()
+            .filter(|c| c.name != name)
+            .cloned()
+            .collect();
+        crate::persistence::save_connections(&remaining, &mut self.vault)?;
+        self.connections = remaining;
+        // The connection is gone either way; a leftover secret is harmless.
+        if let Err(e) = crate::persistence::delete_secret(name, &mut self.vault) {
+            tracing::warn!(connection = name, error = %e, "Failed to remove keyring entry");
+        }
+        Ok(self.disconnect(name))
+    }
+
+    pub fn move_connection(&mut self, from: usize, to: usize) -> Result<()> {
+        if from >= self.connections.len() || to >= self.connections.len() {
+            return Ok(());
+        }
+        let conn = self.connections.remove(from);
+        self.connections.insert(to, conn);
+        crate::persistence::save_connections(&self.connections, &mut self.vault)
+    }
+
+    /// Turn crash reporting on or off and remember the choice. Turning it on
+    /// takes effect on the next start.
+    pub fn set_crash_reports(&mut self, enabled: bool) -> Result<()> {
+        self.settings.telemetry.crash_reports = enabled;
+        crate::logging::set_crash_reports(enabled);
+        crate::persistence::save_settings(&self.settings)
+    }
+
+    /// Unlock the vault, creating it with this passphrase if there is none,
+    /// then fill in the secrets it holds and finish any pending migration.
+    pub fn unlock_vault(&mut self, passphrase: &str) -> Result<()> {
+        if self.vault.exists() {
+            self.vault.unlock(passphrase)?;
+        } else {
+            self.vault.create(passphrase)?;
+        }
+        for conn in &mut self.connections {
+            if !conn.password.is_empty() {
+                continue;
+            }
+            // One damaged entry shouldn't keep the others locked. It is left
+            // in the vault untouched, and the next save won't remove it.
+            match self.vault.get(&conn.name) {
+                Ok(Some(secret)) => {
+                    conn.password_unreadable = false;
+                    crate::persistence::fill_secret(conn, secret);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::warn!(connection = %conn.name, error = %e, "Failed to read password from the vault");
+                    conn.password_unreadable = true;
+                }
+            }
+        }
+        if self.secrets_migration_pending {
+            crate::persistence::save_connections(&self.connections, &mut self.vault)?;
+            self.secrets_migration_pending = false;
+        }
+        Ok(())
+    }
+
+    /// Whether the vault must be unlocked before some connections can be
+    /// used or saved safely.
+    pub fn vault_needed(&self) -> bool {
+        if self.vault.is_unlocked() {
+            return false;
+        }
+        self.secrets_migration_pending
+            || self
+                .connections
+                .iter()
+                .any(|c| c.password.is_empty() && self.vault.contains(&c.name))
+    }
+
+    /// Database type of the selected tab's connection.
+    pub fn active_db_type(&self) -> Option<&DatabaseType> {
+        let name = self.active_tab_connection()?;
+        self.connections
+            .iter()
+            .find(|c| c.name == name)
+            .map(|c| &c.db_type)
+    }
+
+    /// Schema of the selected tab's connection, once loaded.
+    pub fn active_schema(&self) -> Option<Arc<SchemaCache>> {
+        let name = self.active_tab_connection()?;
+        self.session(name)?.schema.clone()
+    }
+
+    pub fn session(&self, name: &str) -> Option<&ConnectionSession> {
+        self.sessions.iter().find(|s| s.name == name)
+    }
+
+    pub fn session_mut(&mut self, name: &str) -> Option<&mut ConnectionSession> {
+        self.sessions.iter_mut().find(|s| s.name == name)
+    }
+
+    /// Forget the session for `name`, returning its client so the caller
+    /// can close the pool.
+    pub fn disconnect(&mut self, name: &str) -> Option<Arc<dyn DatabaseClient>> {
+        let ix = self.sessions.iter().position(|s| s.name == name)?;
+        let session = self.sessions.remove(ix);
+        if self.active_connection_name.as_deref() == Some(name) {
+            self.active_connection_name = self
+                .sessions
+                .iter()
+                .find(|s| s.status == ConnectionStatus::Connected)
+                .map(|s| s.name.clone());
+        }
+        session.client
+    }
+
+    /// Append a tab and return its id.
+    pub fn add_tab(&mut self, title: String) -> usize {
+        let id = self.next_tab_id;
+        self.next_tab_id += 1;
+        self.tabs.push(QueryTab::new(id, title));
+        id
+    }
+
+    pub fn tab(&self, id: usize) -> Option<&QueryTab> {
+        self.tabs.iter().find(|t| t.id == id)
+    }
+
+    pub fn tab_mut(&mut self, id: usize) -> Option<&mut QueryTab> {
+        self.tabs.iter_mut().find(|t| t.id == id)
+    }
+
+    pub fn active_tab(&self) -> Option<&QueryTab> {
+        self.tabs.get(self.active_tab)
+    }
+
+    pub fn active_tab_mut(&mut self) -> Option<&mut QueryTab> {
+        self.tabs.get_mut(self.active_tab)
+    }
+
+    /// Remove a tab, keeping the selection on the same tab where possible.
+    pub fn close_tab(&mut self, id: usize) {
+        let Some(ix) = self.tabs.iter().position(|t| t.id == id) else {
+            return;
+        };
+        self.tabs.remove(ix);
+        if ix < self.active_tab || self.active_tab >= self.tabs.len() {
+            self.active_tab = self.active_tab.saturating_sub(1);
+        }
+    }
+
+    /// Move the tab at `from` to `to`; the selected tab stays selected.
+    pub fn move_tab(&mut self, from: usize, to: usize) {
+        if from >= self.tabs.len() || to >= self.tabs.len() {
+            return;
+        }
+        let active_id = self.active_tab().map(|t| t.id);
+        let tab = self.tabs.remove(from);
+        self.tabs.insert(to, tab);
+        if let Some(id) = active_id {
+            self.active_tab = self.tabs.iter().position(|t| t.id == id).unwrap_or(0);
         }
     }
 
+    /// The connection a tab runs against: its own, or the one last picked
+    /// in the sidebar.
+    pub fn tab_connection(&self, id: usize) -> Option<&str> {
+        self.tab(id)
+            .and_then(|t| t.connection.as_deref())
+            .or(self.active_connection_name.as_deref())
+    }
+
+    pub fn active_tab_connection(&self) -> Option<&str> {
+        self.tab_connection(self.active_tab()?.id)
+    }
+
+    /// The client a tab's queries run on.
+    pub fn tab_client(&self, id: usize) -> Result<Arc<dyn DatabaseClient>> {
+        let name = self
+            .tab_connection(id)
+            .ok_or_else(|| anyhow!("Not connected to a database"))?;
+        self.session(name)
+            .and_then(|s| s.client.clone())
+            .ok_or_else(|| anyhow!("Not connected to '{}'; connect to it first", name))
+    }
+
     pub fn toggle_connecting(&mut self, _cx: &mut Context<Self>) {
         self.is_connecting = !self.is_connecting;
     }
 }
 
+/// Connection names key the keyring, so they must be unique and non-empty.
+fn check_connection_name(
+    connections: &[ConnectionConfig],
+    name: &str,
+    original: Option<&str>,
+) -> Result<()> {
+    if name.trim().is_empty() {
+        bail!("A connection needs a name");
+    }
+    if Some(name) != original && connections.iter().any(|c| c.name == name) {
+        bail!("A connection named '{}' already exists", name);
+    }
+    Ok(())
+}
+
+/// Whether importing `config` refreshes `existing` rather than clashing
+/// with it: both come from the same shared file, or `existing` came from a
+/// shared file that is gone, most likely moved to where `config` is from.
+pub fn is_shared_update(existing: &ConnectionConfig, config: &ConnectionConfig) -> bool {
+    match (&existing.shared_from, &config.shared_from) {
+        (Some(old), Some(new)) => old == new || !Path::new(old).exists(),
+        _ => false,
+    }
+}
+
+/// Connections linked to a shared file that `configs`, read from it again,
+/// no longer contain. They are left in place for the user to remove.
+pub fn removed_from_shared<'a>(
+    connections: &'a [ConnectionConfig],
+    configs: &[ConnectionConfig],
+) -> Vec<&'a ConnectionConfig> {
+    let sources: HashSet<&str> = configs
+        .iter()
+        .filter_map(|c| c.shared_from.as_deref())
+        .collect();
+    connections
+        .iter()
+        .filter(|c| {
+            c.shared_from
+                .as_deref()
+                .is_some_and(|source| sources.contains(source))
+                && !configs.iter().any(|config| config.name == c.name)
+        })
+        .collect()
+}
+
+/// "name (copy)", numbered when that is taken too.
+fn copy_name(connections: &[ConnectionConfig], name: &str) -> String {
+    (1..)
+        .map(|n| match n {
+            1 => format!("{} (copy)", name),
+            n => format!("{} (copy {})", name, n),
+        })
+        .find(|candidate| connections.iter().all(|c| &c.name != candidate))
+        .unwrap_or_default()
+}
+
 impl Render for AppState {
     fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
         div() // Invisible
@@ -89,15 +626,148 @@ mod tests {
     fn test_new() {
         let state = AppState::new();
         // Connections might be loaded from disk, so we don't check is_empty()
-        assert!(state.active_connection.is_none());
+        assert!(state.sessions.is_empty());
         assert!(state.active_connection_name.is_none());
-        assert!(state.tables.is_empty());
-        assert!(state.query_results.is_none());
+        assert!(state.tabs.is_empty());
         assert!(!state.is_connecting);
-        assert!(state.error_message.is_none());
-        assert_eq!(state.result_id, 0);
-        assert!(state.sort_column.is_none());
-        assert!(state.sort_ascending);
+    }
+
+    #[test]
+    fn test_tabs() {
+        let mut state = AppState::new();
+        let a = state.add_tab("a".to_string());
+        let b = state.add_tab("b".to_string());
+        let c = state.add_tab("c".to_string());
+        state.active_tab = 1;
+
+        state.move_tab(0, 2);
+        let ids: Vec<usize> = state.tabs.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![b, c, a]);
+        assert_eq!(state.active_tab().map(|t| t.id), Some(b));
+
+        state.close_tab(b);
+        assert_eq!(state.active_tab().map(|t| t.id), Some(c));
+        state.close_tab(a);
+        assert_eq!(state.active_tab().map(|t| t.id), Some(c));
+        assert!(state.tab_client(c).is_err());
+    }
+
+    #[test]
+    fn test_connection_names() {
+        let conn = |name: &str| ConnectionConfig {
+            name: name.to_string(),
+            db_type: DatabaseType::Sqlite,
+            connection_string: "sqlite::memory:".to_string(),
+            ..Default::default()
+        };
+        let connections = vec![conn("local"), conn("local (copy)"), conn("prod")];
+
+        assert!(check_connection_name(&connections, "staging", None).is_ok());
+        assert!(check_connection_name(&connections, " ", None).is_err());
+        assert!(check_connection_name(&connections, "prod", None).is_err());
+        // Keeping its own name is fine when editing.
+        assert!(check_connection_name(&connections, "prod", Some("prod")).is_ok());
+        assert!(check_connection_name(&connections, "prod", Some("local")).is_err());
+
+        assert_eq!(copy_name(&connections, "prod"), "prod (copy)");
+        assert_eq!(copy_name(&connections, "local"), "local (copy 2)");
+
+        // Re-importing a shared file updates its connections; anything else clashes.
+        let shared = ConnectionConfig {
+            shared_from: Some("/repo/connections.json".to_string()),
+            ..conn("staging")
+        };
+        assert!(is_shared_update(&shared, &shared.clone()));
+        assert!(!is_shared_update(&conn("prod"), &conn("prod")));
+    }
+
+    #[test]
+    fn test_shared_imports() {
+        let conn = |name: &str, source: &str| ConnectionConfig {
+            name: name.to_string(),
+            db_type: DatabaseType::Sqlite,
+            connection_string: "sqlite::memory:".to_string(),
+            shared_from: Some(source.to_string()),
+            ..Default::default()
+        };
+        let existing_file = std::env::temp_dir().display().to_string();
+        let moved = conn("staging", "/gone/connections.json");
+        let other = conn("staging", &existing_file);
+        // A file that is gone was most likely moved; one still there is another file.
+        assert!(is_shared_update(
+            &moved,
+            &conn("staging", "/new/connections.json")
+        ));
+        assert!(!is_shared_update(
+            &other,
+            &conn("staging", "/new/connections.json")
+        ));
+
+        let connections = vec![
+            conn("staging", "/repo/connections.json"),
+            conn("legacy", "/repo/connections.json"),
+            conn("legacy", "/other/connections.json"),
+        ];
+        let reread = vec![conn("staging", "/repo/connections.json")];
+        let removed = removed_from_shared(&connections, &reread);
+        assert_eq!(removed.len(), 1);
+        assert_eq!(
+            removed[0].shared_from.as_deref(),
+            Some("/repo/connections.json")
+        );
+    }
+
+    #[test]
+    fn test_ephemeral_connections() {
+        let mut state = AppState::new();
+        let conn = |path: &str| ConnectionConfig {
+            name: "data.db".to_string(),
+            db_type: DatabaseType::Sqlite,
+            connection_string: format!("sqlite://{}", path),
+            ..Default::default()
+        };
+        let first = state.add_ephemeral_connection(conn("/a/data.db"));
+        let second = state.add_ephemeral_connection(conn("/b/data.db"));
+        assert_eq!(second, format!("{} (2)", first));
+        // Opening the same database again selects the listed connection.
+        assert_eq!(state.add_ephemeral_connection(conn("/a/data.db")), first);
+        assert!(
+            state
+                .connections
+                .iter()
+                .filter(|c| c.name == first || c.name == second)
+                .all(|c| c.ephemeral)
+        );
+    }
+
+    #[test]
+    fn test_sessions() {
+        use crate::db::{SqliteClient, TOKIO_RUNTIME};
+
+        let mut state = AppState::new();
+        for name in ["one", "two"] {
+            let client = TOKIO_RUNTIME
+                .block_on(async { SqliteClient::new("sqlite::memory:").await.unwrap() });
+            let mut session = ConnectionSession::new(name.to_string());
+            session.status = ConnectionStatus::Connected;
+            session.client = Some(Arc::new(client));
+            state.sessions.push(session);
+        }
+        state.active_connection_name = Some("two".to_string());
+
+        let unbound = state.add_tab("a".to_string());
+        let bound = state.add_tab("b".to_string());
+        state.tab_mut(bound).unwrap().connection = Some("one".to_string());
+        assert_eq!(state.tab_connection(unbound), Some("two"));
+        assert_eq!(state.tab_connection(bound), Some("one"));
+
+        assert!(state.disconnect("one").is_some());
+        assert!(state.tab_client(bound).is_err());
+        assert!(state.tab_client(unbound).is_ok());
+
+        assert!(state.disconnect("two").is_some());
+        assert!(state.active_connection_name.is_none());
+        assert!(state.tab_client(unbound).is_err());
     }
 
     #[test]
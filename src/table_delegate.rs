@@ -1,59 +1,393 @@
 // QueryResultsDelegate - implements TableDelegate for displaying SQL query results with sorting and pagination
-use crate::db::QueryResult;
+use crate::column_layout::{self, ColumnLayout, LayoutColumn};
+use crate::db::{
+    Found, Matcher, PageNav, QueryResult, SortKey, search, sort_order, update_sort_keys,
+};
 use crate::state::GlobalAppState;
-use gpui::{App, AsyncApp, Context, IntoElement, Window};
+use gpui::{App, Context, IntoElement, Pixels, Task, WeakEntity, Window};
 use gpui_component::table::{Column, ColumnSort, TableDelegate, TableState};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Rows kept formatted for drawing; comfortably more than fit on screen.
+const ROW_CACHE: usize = 512;
+/// Pause in typing before the find text is searched for.
+const SEARCH_DELAY: Duration = Duration::from_millis(150);
 
 pub struct QueryResultsDelegate {
     pub results: QueryResult,
+    /// Cells of recently drawn rows as displayed, by result row, so a row is
+    /// read once rather than once per cell and frame.
+    shown_rows: RefCell<HashMap<usize, Rc<Vec<String>>>>,
+    /// Rows in sorted order, when sorted in memory.
+    sorted: Option<Vec<usize>>,
+    /// Rows in display order, when sorted or filtered.
+    pub order: Option<Vec<usize>>,
+    /// What the find bar is looking for, if anything.
+    search: Option<Matcher>,
+    /// Result column the search is limited to; all visible ones when `None`.
+    search_column: Option<usize>,
+    /// Hide rows without a match.
+    filter_rows: bool,
+    /// What the last search found.
+    pub found: Found,
+    /// The match last jumped to, as an index into `found.rows` and the
+    /// result column.
+    current: Option<(usize, usize)>,
+    /// A search is running or waiting for typing to pause.
+    pub searching: bool,
+    search_task: Option<Task<()>>,
+    /// Set to stop the running search once a newer one replaces it.
+    search_cancel: Arc<AtomicBool>,
+    /// Every result column in display order, hidden ones included.
+    pub layout: Vec<LayoutColumn>,
+    /// Where the layout is saved; `None` when there's nothing to key it on.
+    layout_key: Option<String>,
+    /// The visible columns, as the table shows them.
     pub columns: Vec<Column>,
-    pub current_sort_col: Option<usize>,
-    pub current_sort_order: ColumnSort,
+    /// The table showing these results, for the header menus.
+    pub table: Option<WeakEntity<TableState<Self>>>,
+    pub sort_keys: Vec<SortKey>,
     pub app_state: GlobalAppState,
+    /// Editor tab the results belong to.
+    pub tab_id: usize,
 }
 
 impl QueryResultsDelegate {
+    /// `sorted_by_server` is set for paged results, which the database has
+    /// already ordered; complete result sets are sorted here instead.
     pub fn new(
         results: QueryResult,
         app_state: GlobalAppState,
-        sort_column: Option<String>,
-        sort_ascending: bool,
+        tab_id: usize,
+        sort_keys: Vec<SortKey>,
+        sorted_by_server: bool,
+        layout_key: Option<String>,
+        saved_layout: &ColumnLayout,
     ) -> Self {
-        // Determine sort index and order
-        let sort_col_index = sort_column
-            .as_ref()
-            .and_then(|sc| results.columns.iter().position(|c| c == sc));
-        let sort_order = if sort_ascending {
-            ColumnSort::Ascending
+        let sorted = if sorted_by_server {
+            None
         } else {
-            ColumnSort::Descending
+            sort_order(&results, &sort_keys)
         };
 
-        // Create column definitions from query results
-        let columns = results
-            .columns
-            .iter()
-            .enumerate()
-            .map(|(i, col_name)| {
-                let mut col = Column::new(col_name, col_name).width(150.).sortable();
-                if Some(i) == sort_col_index {
-                    col = col.sort(sort_order);
+        let mut delegate = Self {
+            layout: saved_layout.resolve(&results),
+            layout_key,
+            results,
+            shown_rows: RefCell::default(),
+            order: sorted.clone(),
+            sorted,
+            search: None,
+            search_column: None,
+            filter_rows: false,
+            found: Found::default(),
+            current: None,
+            searching: false,
+            search_task: None,
+            search_cancel: Arc::default(),
+            columns: Vec::new(),
+            table: None,
+            sort_keys,
+            app_state,
+            tab_id,
+        };
+        delegate.update_columns();
+        delegate
+    }
+
+    /// Rebuild the table's columns from the layout.
+    fn update_columns(&mut self) {
+        self.columns = self
+            .visible()
+            .map(|c| {
+                let name = &c.state.name;
+                let col = Column::new(name, name).width(c.width()).sortable();
+                let col = if c.state.pinned {
+                    col.fixed_left()
+                } else {
+                    col
+                };
+                match self.sort_keys.iter().find(|k| k.column == *name) {
+                    Some(key) if key.ascending => col.sort(ColumnSort::Ascending),
+                    Some(_) => col.sort(ColumnSort::Descending),
+                    None => col,
                 }
-                col
             })
             .collect();
+    }
 
-        Self {
-            results,
-            columns,
-            current_sort_col: sort_col_index,
-            current_sort_order: if sort_col_index.is_some() {
-                sort_order
-            } else {
-                ColumnSort::Default
-            },
-            app_state,
+    fn visible(&self) -> impl Iterator<Item = &LayoutColumn> {
+        self.layout.iter().filter(|c| !c.state.hidden)
+    }
+
+    /// The result column shown at table column `col_ix`.
+    fn result_column(&self, col_ix: usize) -> Option<&LayoutColumn> {
+        self.visible().nth(col_ix)
+    }
+
+    fn save_layout(&self, cx: &mut App) {
+        let Some(key) = &self.layout_key else {
+            return;
+        };
+        let layout = ColumnLayout::from_columns(&self.layout);
+        self.app_state.0.update(cx, |state, _| {
+            if let Err(e) = state.column_layouts.set(key, layout) {
+                tracing::error!(error = %e, "Failed to save column layout");
+            }
+        });
+    }
+
+    /// Apply a change to one column's layout, keyed by its result position,
+    /// then save. The table must be refreshed afterwards.
+    pub fn update_column(
+        &mut self,
+        index: usize,
+        change: impl FnOnce(&mut LayoutColumn),
+        cx: &mut Context<TableState<Self>>,
+    ) {
+        if let Some(column) = self.layout.iter_mut().find(|c| c.index == index) {
+            change(column);
+        }
+        column_layout::pin_first(&mut self.layout);
+        self.update_columns();
+        self.apply_search(false, false, cx);
+        self.save_layout(cx);
+    }
+
+    /// Size every column to its content again.
+    pub fn auto_size(&mut self, cx: &mut App) {
+        for column in &mut self.layout {
+            column.state.width = None;
         }
+        self.update_columns();
+        self.save_layout(cx);
+    }
+
+    /// Forget the saved layout and show the columns as the query returns them.
+    pub fn reset_layout(&mut self, cx: &mut Context<TableState<Self>>) {
+        self.layout = ColumnLayout::default().resolve(&self.results);
+        self.update_columns();
+        self.apply_search(false, false, cx);
+        if let Some(key) = &self.layout_key {
+            self.app_state.0.update(cx, |state, _| {
+                if let Err(e) = state.column_layouts.remove(key) {
+                    tracing::error!(error = %e, "Failed to save column layout");
+                }
+            });
+        }
+    }
+
+    /// Keep the widths the user dragged the visible columns to.
+    pub fn set_widths(&mut self, widths: &[Pixels], cx: &mut App) {
+        let mut changed = false;
+        let visible = self.layout.iter_mut().filter(|c| !c.state.hidden);
+        for (column, &width) in visible.zip(widths) {
+            let width = f32::from(width);
+            if (width - column.width()).abs() >= 1. {
+                column.state.width = Some(width);
+                changed = true;
+            }
+        }
+        if changed {
+            self.update_columns();
+            self.save_layout(cx);
+        }
+    }
+
+    /// Result column `name`, for scoping a search to it.
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.layout
+            .iter()
+            .find(|c| c.state.name == name)
+            .map(|c| c.index)
+    }
+
+    /// Look for `search` in the visible columns, or only in `column`, and
+    /// with `filter` show just the rows that match. The search runs in the
+    /// background, after a pause in typing if `debounce` is set, and scrolls
+    /// to the first match if `scroll` is set.
+    pub fn set_search(
+        &mut self,
+        search: Option<Matcher>,
+        column: Option<usize>,
+        filter: bool,
+        scroll: bool,
+        debounce: bool,
+        cx: &mut Context<TableState<Self>>,
+    ) {
+        self.search = search;
+        self.search_column = column;
+        self.filter_rows = filter;
+        self.apply_search(scroll, debounce, cx);
+    }
+
+    fn apply_search(&mut self, scroll: bool, debounce: bool, cx: &mut Context<TableState<Self>>) {
+        self.search_cancel.store(true, Ordering::Relaxed);
+        self.search_cancel = Arc::default();
+        let Some(matcher) = self.search.clone() else {
+            self.search_task = None;
+            self.searching = false;
+            self.set_found(Found::default());
+            return;
+        };
+        let columns: Vec<usize> = match self.search_column {
+            Some(column) => vec![column],
+            None => self.visible().map(|c| c.index).collect(),
+        };
+        let results = self.results.clone();
+        let sorted = self.sorted.clone();
+        let filter = self.filter_rows;
+        let cancel = self.search_cancel.clone();
+        self.searching = true;
+        self.search_task = Some(cx.spawn(async move |table, cx| {
+            if debounce {
+                cx.background_executor().timer(SEARCH_DELAY).await;
+            }
+            let found = cx
+                .background_executor()
+                .spawn(async move {
+                    search(
+                        &results,
+                        sorted.as_deref(),
+                        &columns,
+                        &matcher,
+                        filter,
+                        &cancel,
+                    )
+                })
+                .await;
+            let found = match found {
+                Ok(Some(found)) => found,
+                // A newer search took over.
+                Ok(None) => return,
+                // The footer reports rows that can't be read back.
+                Err(e) => {
+                    tracing::error!(error = %e, "Failed to search results");
+                    Found::default()
+                }
+            };
+            let _ = table.update(cx, |table, cx| {
+                let delegate = table.delegate_mut();
+                delegate.searching = false;
+                delegate.set_found(found);
+                if let Some((row, col)) = delegate.current_cell().filter(|_| scroll) {
+                    crate::ui::find::scroll_to(table, row, col, cx);
+                }
+                cx.notify();
+            });
+        }));
+    }
+
+    fn set_found(&mut self, found: Found) {
+        self.order = match &found.order {
+            Some(order) => Some(order.clone()),
+            None => self.sorted.clone(),
+        };
+        self.found = found;
+        self.current = self.first_match(0);
+    }
+
+    /// Rows shown, after filtering.
+    pub fn row_count(&self) -> usize {
+        self.order
+            .as_ref()
+            .map_or(self.results.row_count(), |order| order.len())
+    }
+
+    /// The result row shown at `row_ix`.
+    fn row(&self, row_ix: usize) -> usize {
+        self.order.as_ref().map_or(row_ix, |order| order[row_ix])
+    }
+
+    /// The cells of result row `row` as displayed.
+    fn row_cells(&self, row: usize) -> Rc<Vec<String>> {
+        let mut shown = self.shown_rows.borrow_mut();
+        if let Some(cells) = shown.get(&row) {
+            return cells.clone();
+        }
+        if shown.len() >= ROW_CACHE {
+            shown.clear();
+        }
+        let cells: Rc<Vec<String>> = Rc::new(
+            self.results
+                .row(row)
+                .iter()
+                .map(|v| v.to_string())
+                .collect(),
+        );
+        shown.insert(row, cells.clone());
+        cells
+    }
+
+    /// Result columns searched whose cell matches in display row `row_ix`,
+    /// in the order find steps through them.
+    fn matching_columns(&self, row_ix: usize) -> Vec<usize> {
+        let Some(search) = &self.search else {
+            return Vec::new();
+        };
+        let cells = self.row_cells(self.row(row_ix));
+        let matches = |index: usize| cells.get(index).is_some_and(|c| search.is_match(c));
+        match self.search_column {
+            Some(column) => vec![column].into_iter().filter(|&c| matches(c)).collect(),
+            None => self
+                .visible()
+                .map(|c| c.index)
+                .filter(|&c| matches(c))
+                .collect(),
+        }
+    }
+
+    /// The first match in the `ix`th row with one.
+    fn first_match(&self, ix: usize) -> Option<(usize, usize)> {
+        let row_ix = *self.found.rows.get(ix)?;
+        Some((ix, *self.matching_columns(row_ix).first()?))
+    }
+
+    /// The last match in the `ix`th row with one.
+    fn last_match(&self, ix: usize) -> Option<(usize, usize)> {
+        let row_ix = *self.found.rows.get(ix)?;
+        Some((ix, *self.matching_columns(row_ix).last()?))
+    }
+
+    /// Position of the current match among all of them, from zero.
+    pub fn current_match(&self) -> Option<usize> {
+        let (ix, column) = self.current?;
+        let before = self.found.cells_before.get(ix)?;
+        let columns = self.matching_columns(self.found.rows[ix]);
+        Some(before + columns.iter().position(|&c| c == column).unwrap_or(0))
+    }
+
+    /// Table row and column of the current match; the column is `None` when
+    /// the match is in a hidden column.
+    pub fn current_cell(&self) -> Option<(usize, Option<usize>)> {
+        let (ix, column) = self.current?;
+        let col_ix = self.visible().position(|c| c.index == column);
+        Some((self.found.rows[ix], col_ix))
+    }
+
+    /// Move to the next or previous match, wrapping around at either end.
+    pub fn step_match(&mut self, forward: bool) -> Option<(usize, Option<usize>)> {
+        let (ix, column) = self.current?;
+        let rows = self.found.rows.len();
+        let columns = self.matching_columns(self.found.rows[ix]);
+        let pos = columns.iter().position(|&c| c == column);
+        self.current = if forward {
+            match pos.and_then(|pos| columns.get(pos + 1)) {
+                Some(&next) => Some((ix, next)),
+                None => self.first_match((ix + 1) % rows),
+            }
+        } else {
+            match pos.filter(|&pos| pos > 0) {
+                Some(pos) => Some((ix, columns[pos - 1])),
+                None => self.last_match((ix + rows - 1) % rows),
+            }
+        };
+        self.current_cell()
     }
 }
 
@@ -63,7 +397,7 @@ impl TableDelegate for QueryResultsDelegate {
     }
 
     fn rows_count(&self, _: &App) -> usize {
-        self.results.rows.len()
+        self.row_count()
     }
 
     fn column(&self, col_ix: usize, _: &App) -> &Column {
@@ -80,126 +414,147 @@ impl TableDelegate for QueryResultsDelegate {
         use gpui::prelude::*;
         use gpui::*;
 
-        let content = if let Some(row) = self.results.rows.get(row_ix) {
-            if let Some(cell) = row.get(col_ix) {
-                cell.clone()
-            } else {
-                String::new()
-            }
-        } else {
-            String::new()
+        let row = self.row(row_ix);
+        let Some(index) = self.result_column(col_ix).map(|c| c.index) else {
+            return div();
         };
+        let content = self.row_cells(row).get(index).cloned().unwrap_or_default();
+
+        let current = self
+            .current
+            .is_some_and(|(ix, column)| self.found.rows[ix] == row_ix && column == index);
+        let matched = self.search.as_ref().is_some_and(|search| {
+            self.search_column.is_none_or(|c| c == index) && search.is_match(&content)
+        });
 
         div()
             .px_2()
             .py_1()
             .min_w(px(50.))
             .text_color(rgb(0xffffff))
+            .when(matched, |el| el.bg(rgb(0x623315)))
+            .when(current, |el| el.bg(rgb(0x9e6a03)))
             .child(content)
     }
 
+    fn render_th(&self, col_ix: usize, _: &mut Window, cx: &mut App) -> impl IntoElement {
+        use gpui::prelude::*;
+        use gpui::*;
+        use gpui_component::menu::{ContextMenuExt, PopupMenuItem};
+
+        let name = self.column(col_ix, cx).name.clone();
+        // Number the columns when sorting by more than one.
+        let position = self
+            .sort_keys
+            .iter()
+            .position(|k| k.column.as_str() == name.as_ref())
+            .filter(|_| self.sort_keys.len() > 1);
+
+        let label = match position {
+            Some(ix) => format!("{} ({})", name, ix + 1),
+            None => name.to_string(),
+        };
+        let label = match self.result_column(col_ix) {
+            Some(c) if c.state.pinned => format!("📌 {}", label),
+            _ => label,
+        };
+
+        let table = self.table.clone();
+        let column = self.result_column(col_ix).cloned();
+        let can_hide = self.visible().count() > 1;
+        div()
+            .size_full()
+            .child(label)
+            .context_menu(move |menu, _, _| {
+                let (Some(table), Some(column)) = (table.clone(), column.clone()) else {
+                    return menu;
+                };
+                let index = column.index;
+                let update = move |change: fn(&mut LayoutColumn)| {
+                    let table = table.clone();
+                    move |_: &ClickEvent, _: &mut Window, cx: &mut App| {
+                        let _ = table.update(cx, |table, cx| {
+                            table.delegate_mut().update_column(index, change, cx);
+                            table.refresh(cx);
+                        });
+                    }
+                };
+                menu.item(
+                    PopupMenuItem::new(if column.state.pinned {
+                        "Unpin Column"
+                    } else {
+                        "Pin Column"
+                    })
+                    .on_click(update(|c| c.state.pinned = !c.state.pinned)),
+                )
+                .item(
+                    PopupMenuItem::new("Hide Column")
+                        .disabled(!can_hide)
+                        .on_click(update(|c| c.state.hidden = true)),
+                )
+                .item(
+                    PopupMenuItem::new("Auto-size Column")
+                        .on_click(update(|c| c.state.width = None)),
+                )
+            })
+    }
+
     fn perform_sort(
         &mut self,
         col_ix: usize,
         sort: ColumnSort,
-        _: &mut Window,
+        window: &mut Window,
         cx: &mut Context<TableState<Self>>,
     ) {
-        // Store the sort state
-        let column_name = if let Some(col_name) = self.results.columns.get(col_ix) {
-            col_name.clone()
-        } else {
+        let Some(column_name) = self.result_column(col_ix).map(|c| c.state.name.clone()) else {
             return;
         };
 
-        // Determine sort direction and override Default to toggle based on current state
-        let (sort, ascending) = match sort {
-            ColumnSort::Ascending => (ColumnSort::Ascending, true),
-            ColumnSort::Descending => (ColumnSort::Descending, false),
-            ColumnSort::Default => {
-                // If Default is sent, toggle based on current state
-                if self.current_sort_col == Some(col_ix)
-                    && self.current_sort_order == ColumnSort::Ascending
-                {
-                    (ColumnSort::Descending, false)
-                } else {
-                    (ColumnSort::Ascending, true)
-                }
-            }
+        let direction = match sort {
+            ColumnSort::Ascending => Some(true),
+            ColumnSort::Descending => Some(false),
+            ColumnSort::Default => None,
         };
+        // Shift-click sorts by an additional column.
+        let additive = window.modifiers().shift;
 
-        self.current_sort_col = Some(col_ix);
-        self.current_sort_order = sort;
+        let mut sort_keys = self.sort_keys.clone();
+        update_sort_keys(&mut sort_keys, &column_name, direction, additive);
 
-        // Update columns to reflect sort state
-        for (i, col) in self.columns.iter_mut().enumerate() {
-            if i == col_ix {
-                *col = col.clone().sort(sort);
-            } else {
-                *col = col.clone().sort(ColumnSort::Default);
+        let app_state = self.app_state.0.clone();
+        let tab_id = self.tab_id;
+        let pager = app_state.update(cx, |state, cx| {
+            let tab = state.tab_mut(tab_id)?;
+            tab.sort_keys = sort_keys.clone();
+            let pager = tab.table_pager.as_mut().map(|pager| {
+                pager.set_sort_keys(sort_keys);
+                pager.clone()
+            });
+            if pager.is_none() {
+                // Complete results: rebuild the table and sort in memory.
+                tab.result_id += 1;
             }
+            cx.notify();
+            pager
+        });
+
+        if pager.is_some() {
+            crate::ui::results::load_page(app_state, tab_id, PageNav::First, cx);
         }
+    }
 
-        // Get the current query and client from app state
-        let app_state = self.app_state.clone();
-        let async_cx = cx.to_async();
-
-        cx.spawn(move |_table_state, _: &mut AsyncApp| async move {
-            let mut cx = async_cx.clone();
-
-            let _ = app_state.0.update(&mut cx, |state, cx| {
-                // Get the base query
-                if let Some(base_query) = &state.current_query {
-                    // Generate ORDER BY clause
-                    let order_direction = if ascending { "ASC" } else { "DESC" };
-                    let safe_column = column_name.replace('"', "\"\"");
-
-                    // Check if query already has ORDER BY and remove it
-                    let base_query_clean =
-                        if let Some(order_by_pos) = base_query.to_uppercase().rfind("ORDER BY") {
-                            base_query[..order_by_pos].trim().to_string()
-                        } else {
-                            base_query.clone()
-                        };
-
-                    let sorted_query = format!(
-                        "{} ORDER BY \"{}\" {}",
-                        base_query_clean, safe_column, order_direction
-                    );
-
-                    // Store sort state
-                    state.sort_column = Some(column_name.clone());
-                    state.sort_ascending = ascending;
-
-                    // Execute the sorted query
-                    if let Some(client) = &state.active_connection {
-                        let client = client.clone();
-                        let app_state_inner = app_state.clone();
-                        let async_cx_inner = cx.to_async();
-
-                        cx.spawn(|_, _: &mut AsyncApp| async move {
-                            let mut cx = async_cx_inner.clone();
-                            let result = client.execute_query(&sorted_query).await;
-
-                            let _ = app_state_inner.0.update(&mut cx, |state, cx| {
-                                match result {
-                                    Ok(res) => {
-                                        state.query_results = Some(res);
-                                        state.result_id += 1;
-                                    }
-                                    Err(e) => {
-                                        state.error_message = Some(format!("Sort failed: {}", e));
-                                    }
-                                }
-                                cx.notify();
-                            });
-                        })
-                        .detach();
-                    }
-                }
-                cx.notify();
-            });
-        })
-        .detach();
+    fn move_column(
+        &mut self,
+        col_ix: usize,
+        to_ix: usize,
+        window: &mut Window,
+        cx: &mut Context<TableState<Self>>,
+    ) {
+        column_layout::move_visible(&mut self.layout, col_ix, to_ix);
+        self.update_columns();
+        self.save_layout(cx);
+        // The table moves its own copy of the column after this returns;
+        // reload them in case the pinned edge put it somewhere else.
+        cx.defer_in(window, |table, _, cx| table.refresh(cx));
     }
 }
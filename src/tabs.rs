@@ -0,0 +1,165 @@
+// Query editor tabs, and the session file that reopens them on restart.
+//
+// Each tab keeps its own results and paging so switching tabs never loses
+// work. The editor buffers themselves belong to the UI; the session stores
+// their text alongside what is needed to rebuild the tab list.
+use crate::column_layout::{query_key, table_key};
+use crate::db::{QueryResult, SortKey, TablePager};
+use crate::error::AppError;
+use crate::vault::write_private;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SESSION_FILE_NAME: &str = "session.json";
+
+pub struct QueryTab {
+    /// Stable for the life of the tab, unlike its position.
+    pub id: usize,
+    pub title: String,
+    /// Connection the tab runs against; set the first time it runs.
+    pub connection: Option<String>,
+    /// Folder and name of the saved query the buffer was last saved as.
+    pub saved_query: Option<(String, String)>,
+    /// Buffer text when last saved; the tab shows as modified while its
+    /// buffer differs.
+    pub saved_text: String,
+    pub query_results: Option<QueryResult>,
+    pub result_id: usize,
+    pub current_query: Option<String>,
+    pub current_table: Option<String>,
+    /// Paging position while browsing a table; `None` for ad-hoc queries.
+    pub table_pager: Option<TablePager>,
+    /// Result ordering, in priority order; empty means as returned.
+    pub sort_keys: Vec<SortKey>,
+    /// A query started from this tab has not finished yet.
+    pub running: bool,
+    /// Why the last query run from this tab failed; cleared by the next run.
+    pub error: Option<AppError>,
+}
+
+impl QueryTab {
+    pub fn new(id: usize, title: String) -> Self {
+        Self {
+            id,
+            title,
+            connection: None,
+            saved_query: None,
+            saved_text: String::new(),
+            query_results: None,
+            result_id: 0,
+            current_query: None,
+            current_table: None,
+            table_pager: None,
+            sort_keys: Vec::new(),
+            running: false,
+            error: None,
+        }
+    }
+
+    /// Where the column layout of the results is saved: per table while one
+    /// is being browsed, otherwise per query.
+    pub fn layout_key(&self) -> Option<String> {
+        let connection = self.connection.as_deref().unwrap_or_default();
+        match (&self.table_pager, &self.current_query) {
+            (Some(pager), _) => Some(table_key(connection, &pager.table)),
+            (None, Some(query)) => Some(query_key(connection, query)),
+            (None, None) => None,
+        }
+    }
+}
+
+/// The first "Query N" title not already taken.
+pub fn untitled_name<'a>(titles: impl IntoIterator<Item = &'a str>) -> String {
+    let taken: Vec<&str> = titles.into_iter().collect();
+    (1..)
+        .map(|n| format!("Query {}", n))
+        .find(|title| !taken.contains(&title.as_str()))
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionTab {
+    pub title: String,
+    pub text: String,
+    pub connection: Option<String>,
+    pub saved_query: Option<(String, String)>,
+    pub saved_text: String,
+}
+
+/// Open tabs, in order, as they were when the app last saved them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub tabs: Vec<SessionTab>,
+    /// Index of the selected tab.
+    pub active: usize,
+}
+
+impl Session {
+    pub fn load() -> Result<Self> {
+        Self::read(&session_file_path()?)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.write(&session_file_path()?)
+    }
+
+    fn read(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    fn write(&self, path: &Path) -> Result<()> {
+        write_private(path, &serde_json::to_string_pretty(self)?)
+    }
+}
+
+fn session_file_path() -> Result<PathBuf> {
+    Ok(crate::persistence::get_config_dir()?.join(SESSION_FILE_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_untitled_name() {
+        assert_eq!(untitled_name([]), "Query 1");
+        assert_eq!(untitled_name(["Query 1", "Totals", "Query 3"]), "Query 2");
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let path =
+            std::env::temp_dir().join(format!("slick-dataui-session-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        assert_eq!(Session::read(&path).unwrap(), Session::default());
+
+        let session = Session {
+            tabs: vec![
+                SessionTab {
+                    title: "Query 1".to_string(),
+                    text: "SELECT 1".to_string(),
+                    ..Default::default()
+                },
+                SessionTab {
+                    title: "Active users".to_string(),
+                    text: "SELECT * FROM users".to_string(),
+                    connection: Some("prod".to_string()),
+                    saved_query: Some(("reports".to_string(), "Active users".to_string())),
+                    saved_text: "SELECT * FROM users".to_string(),
+                },
+            ],
+            active: 1,
+        };
+        session.write(&path).unwrap();
+        assert_eq!(Session::read(&path).unwrap(), session);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,84 @@
+//! Completion provider wiring the SQL completion engine into the query editor.
+use crate::sql::SchemaCache;
+use crate::sql::completion::{CandidateKind, Completions, complete};
+use crate::sql::lexer::is_word_char;
+use crate::state::GlobalAppState;
+use anyhow::Result;
+use gpui::{Context, Task, Window};
+use gpui_component::RopeExt;
+use gpui_component::input::{CompletionProvider, InputState, Rope};
+use lsp_types::{
+    CompletionContext, CompletionItem, CompletionItemKind, CompletionResponse, CompletionTextEdit,
+    TextEdit,
+};
+
+pub struct SqlCompletionProvider {
+    state: GlobalAppState,
+}
+
+impl SqlCompletionProvider {
+    pub fn new(state: GlobalAppState) -> Self {
+        Self { state }
+    }
+}
+
+impl CompletionProvider for SqlCompletionProvider {
+    fn completions(
+        &self,
+        text: &Rope,
+        offset: usize,
+        _trigger: CompletionContext,
+        _window: &mut Window,
+        cx: &mut Context<InputState>,
+    ) -> Task<Result<CompletionResponse>> {
+        let state = self.state.0.read(cx);
+        // Both are cheap to clone: the rope shares its chunks and the schema
+        // is behind an `Arc`. The text is flattened and its statement parsed
+        // off the UI thread, so long scripts don't stall typing.
+        let schema = state.active_schema();
+        let dialect = state.active_db_type().cloned();
+        let text = text.clone();
+        cx.background_executor().spawn(async move {
+            let empty = SchemaCache::default();
+            let schema = schema.as_deref().unwrap_or(&empty);
+            let completions = complete(&text.to_string(), offset, schema, dialect.as_ref());
+            Ok(CompletionResponse::Array(to_items(completions, &text)))
+        })
+    }
+
+    fn is_completion_trigger(
+        &self,
+        _offset: usize,
+        new_text: &str,
+        _cx: &mut Context<InputState>,
+    ) -> bool {
+        new_text == "." || (!new_text.is_empty() && new_text.chars().all(is_word_char))
+    }
+}
+
+fn to_items(completions: Completions, text: &Rope) -> Vec<CompletionItem> {
+    let range = lsp_types::Range {
+        start: text.offset_to_position(completions.replace.start),
+        end: text.offset_to_position(completions.replace.end),
+    };
+    completions
+        .candidates
+        .into_iter()
+        .map(|candidate| CompletionItem {
+            kind: Some(match candidate.kind {
+                CandidateKind::Keyword => CompletionItemKind::KEYWORD,
+                CandidateKind::Schema => CompletionItemKind::MODULE,
+                CandidateKind::Table => CompletionItemKind::STRUCT,
+                CandidateKind::Column => CompletionItemKind::FIELD,
+                CandidateKind::Function => CompletionItemKind::FUNCTION,
+            }),
+            detail: candidate.detail,
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                range,
+                new_text: candidate.label.clone(),
+            })),
+            label: candidate.label,
+            ..Default::default()
+        })
+        .collect()
+}
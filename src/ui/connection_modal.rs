@@ -1,16 +1,47 @@
+use crate::db::{
+    DEFAULT_PORT, FieldError, PgField, PgUrlParts, ProbeError, ProbeReport, probe_postgres,
+    probe_sqlite, split_password,
+};
 use crate::state::{ConnectionConfig, DatabaseType};
-/// Connection modal component for creating new database connections.
+use crate::vault::VaultLocked;
+/// Connection modal component for creating and editing database connections.
 use gpui::prelude::*;
 use gpui::*;
 use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::checkbox::Checkbox;
 use gpui_component::input::{Input, InputState};
 use gpui_component::radio::{Radio, RadioGroup};
 
+/// Outcome of the Test button.
+pub enum ConnectionTest {
+    Running,
+    Passed(ProbeReport),
+    Failed(ProbeError),
+}
+
 pub struct ConnectionForm {
     pub name_input: Entity<InputState>,
     pub conn_string_input: Entity<InputState>,
+    pub host_input: Entity<InputState>,
+    pub port_input: Entity<InputState>,
+    pub database_input: Entity<InputState>,
+    pub user_input: Entity<InputState>,
+    pub password_input: Entity<InputState>,
+    pub options_input: Entity<InputState>,
+    /// Postgres only: type the URL directly instead of the separate fields.
+    pub raw_url: bool,
+    /// Save was attempted, so missing required fields are flagged too.
+    pub submitted: bool,
+    /// Postgres only: never store the password, ask for it on connect.
+    pub ask_password: bool,
     pub db_type: DatabaseType,
     pub selected_path: Option<String>,
+    /// Name of the connection being edited; `None` creates a new one.
+    pub editing: Option<String>,
+    pub error: Option<String>,
+    pub test: Option<ConnectionTest>,
+    /// Bumped per test so a result arriving after the form moved on is dropped.
+    test_run: usize,
 }
 
 impl ConnectionForm {
@@ -26,8 +57,132 @@ impl ConnectionForm {
             conn_string_input: cx
                 .new(|cx| InputState::new(window, cx).placeholder("Connection string or file"))
                 .into(),
+            host_input: cx
+                .new(|cx| InputState::new(window, cx).placeholder("localhost"))
+                .into(),
+            port_input: cx
+                .new(|cx| InputState::new(window, cx).placeholder(DEFAULT_PORT.to_string()))
+                .into(),
+            database_input: cx
+                .new(|cx| InputState::new(window, cx).placeholder("postgres"))
+                .into(),
+            user_input: cx
+                .new(|cx| InputState::new(window, cx).placeholder("postgres"))
+                .into(),
+            password_input: cx.new(|cx| InputState::new(window, cx).masked(true)).into(),
+            options_input: cx
+                .new(|cx| InputState::new(window, cx).placeholder("sslmode=require"))
+                .into(),
+            raw_url: false,
+            submitted: false,
+            ask_password: false,
             db_type: DatabaseType::Sqlite,
             selected_path: None,
+            editing: None,
+            error: None,
+            test: None,
+            test_run: 0,
+        }
+    }
+
+    /// Reset the form, filled in from `config` when editing it.
+    pub fn show(&mut self, config: Option<&ConnectionConfig>, window: &mut Window, cx: &mut App) {
+        self.name_input.update(cx, |input, cx| {
+            input.set_value(
+                config.map(|c| c.name.clone()).unwrap_or_default(),
+                window,
+                cx,
+            );
+        });
+        // The form edits the password as part of the URL.
+        let url = config
+            .map(|c| {
+                c.connect_url(&c.password)
+                    .unwrap_or_else(|_| c.connection_string.clone())
+            })
+            .unwrap_or_default();
+        self.conn_string_input
+            .update(cx, |input, cx| input.set_value(url.clone(), window, cx));
+        self.db_type = config.map_or(DatabaseType::Sqlite, |c| c.db_type.clone());
+        self.ask_password = config.is_some_and(|c| c.ask_password);
+        let parts = match config {
+            Some(c) if c.db_type == DatabaseType::Postgres => PgUrlParts::parse(&url),
+            _ => Ok(PgUrlParts::default()),
+        };
+        // Keep URLs the fields can't represent as typed.
+        self.raw_url = parts.is_err();
+        self.set_pg_parts(&parts.unwrap_or_default(), window, cx);
+        self.submitted = false;
+        self.selected_path = None;
+        self.editing = config.map(|c| c.name.clone());
+        self.error = None;
+        self.test = None;
+        self.test_run += 1;
+    }
+
+    pub fn pg_parts(&self, cx: &App) -> PgUrlParts {
+        let value = |input: &Entity<InputState>| input.read(cx).value().to_string();
+        PgUrlParts {
+            host: value(&self.host_input),
+            port: value(&self.port_input),
+            database: value(&self.database_input),
+            user: value(&self.user_input),
+            password: value(&self.password_input),
+            options: value(&self.options_input),
+        }
+    }
+
+    fn set_pg_parts(&self, parts: &PgUrlParts, window: &mut Window, cx: &mut App) {
+        for (input, value) in [
+            (&self.host_input, &parts.host),
+            (&self.port_input, &parts.port),
+            (&self.database_input, &parts.database),
+            (&self.user_input, &parts.user),
+            (&self.password_input, &parts.password),
+            (&self.options_input, &parts.options),
+        ] {
+            input.update(cx, |input, cx| input.set_value(value.clone(), window, cx));
+        }
+    }
+
+    /// Switch between the URL and the separate fields, carrying the
+    /// connection details across.
+    fn toggle_raw_url(&mut self, window: &mut Window, cx: &mut App) {
+        self.error = None;
+        if self.raw_url {
+            let url = self.conn_string_input.read(cx).value().to_string();
+            if !url.trim().is_empty() {
+                match PgUrlParts::parse(&url) {
+                    Ok(parts) => self.set_pg_parts(&parts, window, cx),
+                    Err(e) => {
+                        self.error = Some(format!("Couldn't read the URL: {}", e));
+                        return;
+                    }
+                }
+            }
+        } else if let Ok(url) = self.pg_parts(cx).to_url() {
+            self.conn_string_input
+                .update(cx, |input, cx| input.set_value(url, window, cx));
+        }
+        self.raw_url = !self.raw_url;
+    }
+
+    /// The connection string to save, or `None` when the form has errors.
+    fn connection_string(&mut self, cx: &App) -> Option<String> {
+        if let Some(path) = &self.selected_path {
+            return Some(path.clone());
+        }
+        let value = self.conn_string_input.read(cx).value().to_string();
+        match self.db_type {
+            DatabaseType::Sqlite => Some(value),
+            DatabaseType::Postgres if self.raw_url => match PgUrlParts::parse(&value) {
+                Ok(_) => Some(value.trim().to_string()),
+                Err(e) => {
+                    self.error = Some(format!("Invalid connection URL: {}", e));
+                    None
+                }
+            },
+            DatabaseType::Postgres => self.pg_parts(cx).to_url().ok(),
         }
     }
 }
@@ -53,10 +208,32 @@ pub fn render_modal(
                 .flex()
                 .flex_col()
                 .gap_4()
-                .child(div().text_xl().child("New Connection"))
+                .child(div().text_xl().child(if layout.form.editing.is_some() {
+                    "Edit Connection"
+                } else {
+                    "New Connection"
+                }))
                 .child(render_connection_name_field(layout, cx))
                 .child(render_database_type_selector(layout, cx))
-                .child(render_connection_string_field(layout, cx))
+                .map(|el| {
+                    if layout.form.db_type == DatabaseType::Postgres {
+                        el.child(render_url_toggle(layout, cx))
+                            .child(if layout.form.raw_url {
+                                render_connection_string_field(layout, cx).into_any_element()
+                            } else {
+                                render_postgres_fields(layout, cx).into_any_element()
+                            })
+                            .child(render_ask_password_toggle(layout, cx))
+                    } else {
+                        el.child(render_connection_string_field(layout, cx))
+                    }
+                })
+                .when_some(layout.form.error.clone(), |el, error| {
+                    el.child(div().text_sm().text_color(rgb(0xff5555)).child(error))
+                })
+                .when_some(render_test_result(&layout.form), |el, result| {
+                    el.child(result)
+                })
                 .child(render_modal_actions(layout, cx)),
         )
 }
@@ -104,6 +281,133 @@ fn render_database_type_selector(
         )
 }
 
+fn render_url_toggle(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    Checkbox::new("raw_url")
+        .label("Paste connection URL")
+        .checked(layout.form.raw_url)
+        .on_click(cx.listener(|this, _, window, cx| {
+            this.form.toggle_raw_url(window, cx);
+            cx.notify();
+        }))
+}
+
+fn render_ask_password_toggle(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    Checkbox::new("ask_password")
+        .label("Ask for password on connect")
+        .checked(layout.form.ask_password)
+        .on_click(cx.listener(|this, checked: &bool, _, cx| {
+            this.form.ask_password = *checked;
+            cx.notify();
+        }))
+}
+
+/// Host, port, database, user, password and options, with each field's
+/// validation error underneath and the resulting URL below.
+fn render_postgres_fields(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    let form = &layout.form;
+    let parts = form.pg_parts(cx);
+    let errors = parts.validate();
+    // Required fields are only flagged once Save has been tried; bad values
+    // as soon as they are typed.
+    let error_for = |field: PgField, value: &str| -> Option<String> {
+        errors
+            .iter()
+            .find(|e: &&FieldError| e.field == field)
+            .filter(|_| form.submitted || !value.is_empty())
+            .map(|e| e.message.clone())
+    };
+    let field = |label: &'static str, input: &Entity<InputState>, error: Option<String>| {
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .child(div().text_sm().child(label))
+            .child(Input::new(input))
+            .when_some(error, |el, error| {
+                el.child(div().text_xs().text_color(rgb(0xff5555)).child(error))
+            })
+    };
+
+    div()
+        .flex()
+        .flex_col()
+        .gap_2()
+        .child(
+            div()
+                .flex()
+                .gap_2()
+                .child(
+                    field(
+                        "Host",
+                        &form.host_input,
+                        error_for(PgField::Host, &parts.host),
+                    )
+                    .flex_1(),
+                )
+                .child(
+                    field(
+                        "Port",
+                        &form.port_input,
+                        error_for(PgField::Port, &parts.port),
+                    )
+                    .w_24(),
+                ),
+        )
+        .child(field(
+            "Database",
+            &form.database_input,
+            error_for(PgField::Database, &parts.database),
+        ))
+        .child(
+            div()
+                .flex()
+                .gap_2()
+                .child(
+                    field(
+                        "User",
+                        &form.user_input,
+                        error_for(PgField::User, &parts.user),
+                    )
+                    .flex_1(),
+                )
+                .child(
+                    field(
+                        // Still used by Test.
+                        if form.ask_password {
+                            "Password (not saved)"
+                        } else {
+                            "Password"
+                        },
+                        &form.password_input,
+                        error_for(PgField::Password, &parts.password),
+                    )
+                    .flex_1(),
+                ),
+        )
+        .child(field(
+            "Options",
+            &form.options_input,
+            error_for(PgField::Options, &parts.options),
+        ))
+        .when(errors.is_empty(), |el| {
+            el.child(
+                div()
+                    .text_xs()
+                    .text_color(rgb(0x888888))
+                    .child(parts.preview()),
+            )
+        })
+}
+
 fn render_connection_string_field(
     layout: &mut super::MainLayout,
     cx: &mut Context<super::MainLayout>,
@@ -161,14 +465,70 @@ fn render_connection_string_field(
         })
 }
 
+fn render_test_result(form: &ConnectionForm) -> Option<Div> {
+    let (color, text) = match form.test.as_ref()? {
+        ConnectionTest::Running => return None,
+        ConnectionTest::Passed(report) => (
+            rgb(0x4ec9b0),
+            format!(
+                "Connected to {} in {} ms",
+                report.server_version,
+                report.latency.as_millis()
+            ),
+        ),
+        ConnectionTest::Failed(error) => (rgb(0xff5555), error.to_string()),
+    };
+    Some(div().text_sm().text_color(color).child(text))
+}
+
+/// Connect once with the form's settings and report how it went.
+fn test_connection(layout: &mut super::MainLayout, cx: &mut Context<super::MainLayout>) {
+    let form = &mut layout.form;
+    form.error = None;
+    let Some(url) = form.connection_string(cx) else {
+        form.submitted = true;
+        cx.notify();
+        return;
+    };
+    form.test_run += 1;
+    let run = form.test_run;
+    form.test = Some(ConnectionTest::Running);
+    let db_type = form.db_type.clone();
+    cx.notify();
+
+    cx.spawn(async move |this, cx| {
+        let result = match db_type {
+            DatabaseType::Sqlite => probe_sqlite(&url).await,
+            DatabaseType::Postgres => probe_postgres(&url).await,
+        };
+        let _ = this.update(cx, |this, cx| {
+            if this.form.test_run == run {
+                this.form.test = Some(match result {
+                    Ok(report) => ConnectionTest::Passed(report),
+                    Err(error) => ConnectionTest::Failed(error),
+                });
+                cx.notify();
+            }
+        });
+    })
+    .detach();
+}
+
 fn render_modal_actions(
-    _layout: &mut super::MainLayout,
+    layout: &mut super::MainLayout,
     cx: &mut Context<super::MainLayout>,
 ) -> impl IntoElement {
+    let testing = matches!(layout.form.test, Some(ConnectionTest::Running));
     div()
         .flex()
         .gap_2()
         .justify_end()
+        .child(
+            Button::new("test_connection")
+                .label("Test")
+                .loading(testing)
+                .on_click(cx.listener(|this, _, _, cx| test_connection(this, cx))),
+        )
         .child(
             Button::new("cancel")
                 .label("Cancel")
@@ -182,24 +542,73 @@ fn render_modal_actions(
             Button::new("save")
                 .primary()
                 .label("Save")
-                .on_click(cx.listener(|this, _, _, cx| {
+                .on_click(cx.listener(|this, _, window, cx| {
                     let name = this.form.name_input.read(cx).value().to_string();
-                    let conn_string = if let Some(ref path) = this.form.selected_path {
-                        path.clone()
-                    } else {
-                        this.form.conn_string_input.read(cx).value().to_string()
+                    this.form.submitted = true;
+                    this.form.error = None;
+                    let Some(url) = this.form.connection_string(cx) else {
+                        cx.notify();
+                        return;
                     };
 
+                    let ask_password =
+                        this.form.db_type == DatabaseType::Postgres && this.form.ask_password;
+                    let (connection_string, password) = match this.form.db_type {
+                        DatabaseType::Sqlite => (url, String::new()),
+                        DatabaseType::Postgres => split_password(&url),
+                    };
                     let config = ConnectionConfig {
-                        name,
+                        name: name.trim().to_string(),
                         db_type: this.form.db_type.clone(),
-                        connection_string: conn_string,
+                        connection_string,
+                        password: if ask_password {
+                            String::new()
+                        } else {
+                            password
+                        },
+                        ask_password,
+                        ..Default::default()
                     };
-                    this.state.0.update(cx, |state, cx| {
-                        state.add_connection(config);
-                        state.toggle_connecting(cx);
+                    let editing = this.form.editing.clone();
+                    let result = this.state.0.update(cx, |state, cx| {
+                        let result = match &editing {
+                            Some(original) => {
+                                // Stays linked to its shared file through edits.
+                                let shared_from = state
+                                    .connections
+                                    .iter()
+                                    .find(|c| &c.name == original)
+                                    .and_then(|c| c.shared_from.clone());
+                                state.update_connection(
+                                    original,
+                                    ConnectionConfig {
+                                        shared_from,
+                                        ..config
+                                    },
+                                )
+                            }
+                            None => state.add_connection(config),
+                        };
+                        if result.is_ok() {
+                            state.toggle_connecting(cx);
+                        }
+                        cx.notify();
+                        result
                     });
-                    this.form.selected_path = None;
+                    match result {
+                        Ok(()) => {
+                            this.form.selected_path = None;
+                            this.form.editing = None;
+                            this.form.error = None;
+                        }
+                        Err(e) => {
+                            if e.is::<VaultLocked>() {
+                                this.vault.show(window, cx);
+                            }
+                            this.form.error = Some(e.to_string());
+                        }
+                    }
+                    cx.notify();
                 })),
         )
 }
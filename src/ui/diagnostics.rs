@@ -0,0 +1,58 @@
+/// Editor underlines: parse errors while typing, and the token a failed query
+/// was rejected at.
+use crate::db::ErrorLocation;
+use crate::sql::diagnostics::{locate, syntax_errors};
+use gpui::Context;
+use gpui_component::RopeExt;
+use gpui_component::highlighter::{Diagnostic, DiagnosticSeverity};
+use gpui_component::input::InputState;
+use std::ops::Range;
+
+/// Replace the editor's diagnostics with the current parse errors.
+///
+/// These are warnings: the grammar does not cover every PostgreSQL and
+/// SQLite extension, so the database has the final word.
+pub fn refresh_syntax_diagnostics(input: &mut InputState, cx: &mut Context<InputState>) {
+    set_diagnostics(input, syntax_diagnostics(input), cx);
+}
+
+/// Underline the token `location` points at in the query that failed,
+/// keeping the parse errors alongside it.
+pub fn show_query_error(
+    input: &mut InputState,
+    location: &ErrorLocation,
+    message: &str,
+    cx: &mut Context<InputState>,
+) {
+    let mut diagnostics = syntax_diagnostics(input);
+    if let Some(range) = locate(&input.value(), location) {
+        diagnostics.push((range, message.to_string(), DiagnosticSeverity::Error));
+    }
+    set_diagnostics(input, diagnostics, cx);
+}
+
+fn syntax_diagnostics(input: &InputState) -> Vec<(Range<usize>, String, DiagnosticSeverity)> {
+    syntax_errors(&input.value())
+        .into_iter()
+        .map(|e| (e.range, e.message, DiagnosticSeverity::Warning))
+        .collect()
+}
+
+fn set_diagnostics(
+    input: &mut InputState,
+    mut diagnostics: Vec<(Range<usize>, String, DiagnosticSeverity)>,
+    cx: &mut Context<InputState>,
+) {
+    // The diagnostic set is searched by offset, so entries must be in order.
+    diagnostics.sort_by_key(|(range, _, _)| range.start);
+    let text = input.text().clone();
+    let Some(set) = input.diagnostics_mut() else {
+        return;
+    };
+    set.clear();
+    set.extend(diagnostics.into_iter().map(|(range, message, severity)| {
+        let range = text.offset_to_position(range.start)..text.offset_to_position(range.end);
+        Diagnostic::new(range, message).with_severity(severity)
+    }));
+    cx.notify();
+}
@@ -1,9 +1,16 @@
 /// SQL query editor component.
+use super::diagnostics;
+use crate::history::HistoryEntry;
+use crate::sql::format::format_sql;
+use crate::sql::lexer::statement_at;
+use chrono::Utc;
 use gpui::prelude::*;
 use gpui::*;
+use gpui_component::Sizable;
 use gpui_component::button::{Button, ButtonVariants};
-use gpui_component::input::Input;
+use gpui_component::input::{IndentInline, Input};
 use gpui_component::table::Table;
+use std::time::Instant;
 
 pub fn render_editor_section(
     layout: &mut super::MainLayout,
@@ -14,6 +21,7 @@ pub fn render_editor_section(
         .h_full()
         .flex()
         .flex_col()
+        .child(super::tabs::render_tab_bar(layout, cx))
         .child(render_editor_header(layout, cx))
         .child(render_query_results(layout, cx))
 }
@@ -32,9 +40,31 @@ fn render_editor_header(
         .gap_3()
         .child(
             div()
-                .text_sm()
-                .font_weight(gpui::FontWeight::SEMIBOLD)
-                .child("SQL Query Editor"),
+                .flex()
+                .gap_2()
+                .items_baseline()
+                .child(
+                    div()
+                        .text_sm()
+                        .font_weight(gpui::FontWeight::SEMIBOLD)
+                        .child("SQL Query Editor"),
+                )
+                .when_some(
+                    layout
+                        .state
+                        .0
+                        .read(cx)
+                        .active_tab_connection()
+                        .map(str::to_string),
+                    |el, connection| {
+                        el.child(
+                            div()
+                                .text_xs()
+                                .text_color(rgb(0x888888))
+                                .child(format!("on {}", connection)),
+                        )
+                    },
+                ),
         )
         .child(render_query_input(layout, cx))
 }
@@ -43,51 +73,204 @@ fn render_query_input(
     layout: &mut super::MainLayout,
     cx: &mut Context<super::MainLayout>,
 ) -> impl IntoElement {
+    let input = layout.query_input(cx);
     div()
         .flex()
         .flex_col()
         .gap_2()
         .flex_1()
-        .child(Input::new(&layout.query_input).h(px(150.)).appearance(true))
-        .child(render_run_button(layout, cx))
+        .child(
+            div()
+                .capture_action(cx.listener(|this, _: &IndentInline, window, cx| {
+                    if super::saved_queries::next_tab_stop(this, window, cx) {
+                        cx.stop_propagation();
+                    }
+                }))
+                .when_some(input, |el, input| {
+                    el.child(Input::new(&input).h(px(150.)).appearance(true))
+                }),
+        )
+        .child(
+            div()
+                .flex()
+                .flex_row()
+                .gap_2()
+                .child(render_run_button(layout, cx))
+                .child(Button::new("format_sql").label("Format SQL").on_click(
+                    cx.listener(|this, _, window, cx| format_query(this, true, window, cx)),
+                ))
+                .child(
+                    Button::new("format_statement")
+                        .label("Format Statement")
+                        .on_click(
+                            cx.listener(|this, _, window, cx| {
+                                format_query(this, false, window, cx)
+                            }),
+                        ),
+                )
+                .child(
+                    Button::new("save_query_from_editor")
+                        .label("Save Query")
+                        .on_click(cx.listener(|this, _, window, cx| {
+                            let active = this
+                                .state
+                                .0
+                                .read(cx)
+                                .active_tab_connection()
+                                .map(str::to_string);
+                            this.save_query.show(None, active, window, cx);
+                            cx.notify();
+                        })),
+                )
+                .child(
+                    Button::new("toggle_history")
+                        .label("History")
+                        .on_click(cx.listener(|this, _, _, cx| {
+                            this.history.open = !this.history.open;
+                            cx.notify();
+                        })),
+                ),
+        )
+}
+
+/// Pretty-print the whole editor buffer, or only the statement under the
+/// cursor, as a single undoable edit.
+fn format_query(
+    layout: &mut super::MainLayout,
+    whole_buffer: bool,
+    window: &mut Window,
+    cx: &mut Context<super::MainLayout>,
+) {
+    let state = layout.state.0.read(cx);
+    let options = state.settings.format.clone();
+    let dialect = state.active_db_type().cloned();
+    let Some(query_input) = layout.query_input(cx) else {
+        return;
+    };
+
+    query_input.update(cx, |input, cx| {
+        let text = input.value().to_string();
+        let range = if whole_buffer {
+            0..text.len()
+        } else {
+            statement_at(&text, input.cursor())
+        };
+        let source = &text[range.clone()];
+        let start = range.start + (source.len() - source.trim_start().len());
+        let end = range.start + source.trim_end().len();
+        if start >= end {
+            return;
+        }
+
+        let formatted = format_sql(&text[start..end], &options, dialect.as_ref());
+        if formatted != text[start..end] {
+            let utf16 = |offset: usize| text[..offset].encode_utf16().count();
+            input.replace_text_in_range(Some(utf16(start)..utf16(end)), &formatted, window, cx);
+        }
+    });
 }
 
 fn render_run_button(
-    _layout: &mut super::MainLayout,
+    layout: &mut super::MainLayout,
     cx: &mut Context<super::MainLayout>,
 ) -> impl IntoElement {
+    let running = layout
+        .state
+        .0
+        .read(cx)
+        .active_tab()
+        .is_some_and(|t| t.running);
+
     Button::new("run_query")
         .label("Run Query")
         .primary()
+        .loading(running)
         .on_click(cx.listener(|this, _, _, cx| {
             let app_state = this.state.0.clone();
-            let query = this.query_input.read(cx).value().to_string();
-            let client_opt = this.state.0.read(cx).active_connection.clone();
+            let Some(query_input) = this.query_input(cx) else {
+                return;
+            };
+            let query = query_input.read(cx).value().to_string();
+            let state = this.state.0.read(cx);
+            let Some(tab_id) = state.active_tab().map(|t| t.id) else {
+                return;
+            };
+            let client = state.tab_client(tab_id);
+            let connection_name = state.tab_connection(tab_id).map(str::to_string);
+
+            let client = match client {
+                Ok(client) => client,
+                Err(e) => {
+                    app_state.update(cx, |state, cx| {
+                        state.notifications.error("Can't run the query", e);
+                        cx.notify();
+                    });
+                    return;
+                }
+            };
+            app_state.update(cx, |state, cx| {
+                if let Some(tab) = state.tab_mut(tab_id) {
+                    tab.connection = connection_name.clone();
+                    tab.running = true;
+                    tab.error = None;
+                }
+                cx.notify();
+            });
+            this.save_session(cx);
 
             let async_cx = cx.to_async();
             cx.spawn(
-                |_this_weak: WeakEntity<super::MainLayout>, _: &mut AsyncApp| async move {
+                move |_this_weak: WeakEntity<super::MainLayout>, _: &mut AsyncApp| async move {
                     let mut cx = async_cx.clone();
 
-                    if let Some(client) = client_opt {
-                        let result = client.execute_query(&query).await;
-                        let _ = app_state.update(&mut cx, |state, cx| {
-                            match result {
-                                Ok(res) => {
-                                    state.query_results = Some(res);
-                                    state.result_id += 1;
-                                    state.current_query = Some(query.clone());
-                                    state.current_table = None; // Custom query, not a simple table
-                                    state.sort_column = None;
-                                    state.sort_ascending = true;
-                                }
-                                Err(e) => {
-                                    state.error_message = Some(format!("Query failed: {}", e))
-                                }
-                            }
-                            cx.notify();
-                        });
+                    let executed_at = Utc::now();
+                    let started = Instant::now();
+                    tracing::debug!(sql = %query, "Running query");
+                    let result = client.execute_query(&query).await;
+                    if let Err(e) = &result {
+                        tracing::debug!(error = %e, "Query failed");
                     }
+                    let entry = HistoryEntry {
+                        query: query.clone(),
+                        connection: connection_name.unwrap_or_default(),
+                        executed_at,
+                        duration_ms: started.elapsed().as_millis() as u64,
+                        rows: result.as_ref().ok().map(|r| r.row_count()),
+                        error: result.as_ref().err().map(|e| e.to_string()),
+                    };
+
+                    // Point at the failing token, unless the text has changed since.
+                    let _ = query_input.update(&mut cx, |input, cx| match &result {
+                        Err(e) if input.value() == query => match client.error_location(e) {
+                            Some(location) => {
+                                diagnostics::show_query_error(input, &location, &e.to_string(), cx)
+                            }
+                            None => diagnostics::refresh_syntax_diagnostics(input, cx),
+                        },
+                        _ => diagnostics::refresh_syntax_diagnostics(input, cx),
+                    });
+                    let _ = app_state.update(&mut cx, |state, cx| {
+                        if let Err(e) = state.history.record(entry) {
+                            tracing::error!(error = %e, "Failed to save query history");
+                        }
+                        let Some(tab) = state.tab_mut(tab_id) else {
+                            // The tab was closed while the query ran.
+                            return;
+                        };
+                        tab.running = false;
+                        match result {
+                            Ok(res) => {
+                                tab.query_results = Some(res);
+                                tab.result_id += 1;
+                                tab.current_query = Some(query.clone());
+                                tab.current_table = None; // Custom query, not a simple table
+                                tab.table_pager = None;
+                                tab.sort_keys.clear();
+                            }
+                            Err(e) => tab.error = Some(client.describe_error(&e)),
+                        }
+                        cx.notify();
+                    });
                 },
             )
             .detach();
@@ -98,6 +281,12 @@ pub fn render_query_results(
     layout: &mut super::MainLayout,
     cx: &mut Context<super::MainLayout>,
 ) -> impl IntoElement {
+    let error = layout
+        .state
+        .0
+        .read(cx)
+        .active_tab()
+        .and_then(|t| Some((t.error.clone()?, t.id)));
     div()
         .flex_1()
         .p_4()
@@ -106,64 +295,74 @@ pub fn render_query_results(
         .gap_2()
         .child(
             div()
-                .text_sm()
-                .font_weight(gpui::FontWeight::SEMIBOLD)
-                .child("Query Results"),
-        )
-        .child(if let Some(table_state) = &layout.table_state {
-            // Use the table state that was created in MainLayout::render
-            use gpui::prelude::*;
-            use gpui_component::StyledExt;
-            use gpui_component::scroll::ScrollbarAxis;
-
-            div()
-                .flex_1()
-                .h(px(400.)) // Fixed height to enable scrolling
-                .max_w_full()
                 .flex()
-                .flex_col()
-                .gap_2()
-                .gap_2()
-                .scrollable(ScrollbarAxis::Both)
-                .child(Table::new(table_state).stripe(true).bordered(true))
+                .justify_between()
+                .items_center()
                 .child(
-                    if let Some(results) = &layout.state.0.read(cx).query_results {
-                        div()
-                            .flex()
-                            .gap_2()
-                            .justify_between()
-                            .px_2()
-                            .py_2()
-                            .text_xs()
-                            .text_color(rgb(0x888888))
-                            .child(format!(
-                                "Total: {} rows | Click column headers to sort",
-                                results.rows.len()
-                            ))
-                            .into_element()
-                    } else {
-                        div().into_element()
-                    },
+                    div()
+                        .text_sm()
+                        .font_weight(gpui::FontWeight::SEMIBOLD)
+                        .child("Query Results"),
                 )
-                .into_any_element()
-        } else if layout.state.0.read(cx).query_results.is_some() {
-            // Results exist but table not ready yet
-            div()
-                .flex_1()
-                .flex()
-                .items_center()
-                .justify_center()
-                .text_color(rgb(0x888888))
-                .child("Loading table...")
-                .into_any_element()
-        } else {
-            div()
-                .flex_1()
-                .flex()
-                .items_center()
-                .justify_center()
-                .text_color(rgb(0x888888))
-                .child("No results yet. Run a query to see results here.")
-                .into_any_element()
+                .child(
+                    Button::new("toggle_find")
+                        .label("Find")
+                        .xsmall()
+                        .ghost()
+                        .on_click(cx.listener(|this, _, window, cx| {
+                            super::find::toggle(this, window, cx);
+                        })),
+                ),
+        )
+        .children(super::find::render_find_bar(layout, cx))
+        .when_some(error, |el, (error, tab_id)| {
+            el.child(super::error_panel::render_error_panel(&error, tab_id, cx))
         })
+        .child(
+            if let Some(table_state) = layout.active_editor(cx).and_then(|e| e.table_state.clone())
+            {
+                // Use the table state that was created in MainLayout::render
+                use gpui::prelude::*;
+                use gpui_component::StyledExt;
+                use gpui_component::scroll::ScrollbarAxis;
+
+                div()
+                    .flex_1()
+                    .h(px(400.)) // Fixed height to enable scrolling
+                    .max_w_full()
+                    .flex()
+                    .flex_col()
+                    .gap_2()
+                    .gap_2()
+                    .scrollable(ScrollbarAxis::Both)
+                    .child(Table::new(&table_state).stripe(true).bordered(true))
+                    .child(super::results::render_results_footer(layout, cx))
+                    .into_any_element()
+            } else if layout
+                .state
+                .0
+                .read(cx)
+                .active_tab()
+                .is_some_and(|t| t.query_results.is_some())
+            {
+                // Results exist but table not ready yet
+                div()
+                    .flex_1()
+                    .flex()
+                    .items_center()
+                    .justify_center()
+                    .text_color(rgb(0x888888))
+                    .child("Loading table...")
+                    .into_any_element()
+            } else {
+                div()
+                    .flex_1()
+                    .flex()
+                    .items_center()
+                    .justify_center()
+                    .text_color(rgb(0x888888))
+                    .child("No results yet. Run a query to see results here.")
+                    .into_any_element()
+            },
+        )
 }
@@ -0,0 +1,86 @@
+//! Structured panel beneath the editor for the selected tab's last query error.
+use crate::db::ErrorLocation;
+use crate::error::AppError;
+use gpui::prelude::*;
+use gpui::*;
+use gpui_component::Sizable;
+use gpui_component::button::{Button, ButtonVariants};
+
+pub fn render_error_panel(
+    error: &AppError,
+    tab_id: usize,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    let details = &error.details;
+    let position = details.position.as_ref().map(|position| match position {
+        ErrorLocation::Offset(offset) => format!("character {}", offset + 1),
+        ErrorLocation::Near(token) => format!("near \"{}\"", token),
+    });
+    let fields = [
+        ("Detail", details.detail.clone()),
+        ("Hint", details.hint.clone()),
+        ("Schema", details.schema.clone()),
+        ("Table", details.table.clone()),
+        ("Column", details.column.clone()),
+        ("Constraint", details.constraint.clone()),
+        ("Position", position),
+    ];
+
+    div()
+        .flex()
+        .flex_col()
+        .gap_1()
+        .p_3()
+        .rounded_md()
+        .border_1()
+        .border_color(rgb(0x5a1d1d))
+        .bg(rgb(0x2d1b1b))
+        .child(
+            div()
+                .flex()
+                .items_center()
+                .gap_2()
+                .child(
+                    div()
+                        .text_sm()
+                        .font_weight(gpui::FontWeight::SEMIBOLD)
+                        .text_color(rgb(0xff5555))
+                        .child(error.kind.label()),
+                )
+                .when_some(details.sqlstate.clone(), |el, sqlstate| {
+                    el.child(
+                        div()
+                            .text_xs()
+                            .text_color(rgb(0x888888))
+                            .child(format!("SQLSTATE {}", sqlstate)),
+                    )
+                })
+                .child(div().flex_1())
+                .child(
+                    Button::new("dismiss_query_error")
+                        .label("×")
+                        .ghost()
+                        .xsmall()
+                        .on_click(cx.listener(move |this, _, _, cx| {
+                            this.state.0.update(cx, |state, cx| {
+                                if let Some(tab) = state.tab_mut(tab_id) {
+                                    tab.error = None;
+                                }
+                                cx.notify();
+                            });
+                        })),
+                ),
+        )
+        .child(div().text_sm().child(error.message.clone()))
+        .children(fields.into_iter().filter_map(|(label, value)| {
+            let value = value?;
+            Some(
+                div()
+                    .flex()
+                    .gap_2()
+                    .text_xs()
+                    .child(div().w_20().text_color(rgb(0x888888)).child(label))
+                    .child(div().flex_1().child(value)),
+            )
+        }))
+}
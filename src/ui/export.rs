@@ -0,0 +1,164 @@
+/// Dialog that writes chosen connections to a shared file, without passwords.
+use crate::state::ConnectionConfig;
+use gpui::prelude::*;
+use gpui::*;
+use gpui_component::Disableable;
+use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::checkbox::Checkbox;
+
+#[derive(Default)]
+pub struct ExportForm {
+    pub open: bool,
+    /// Names of the connections ticked for export.
+    pub selected: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl ExportForm {
+    /// Open the dialog with every connection ticked.
+    pub fn show(&mut self, connections: &[ConnectionConfig]) {
+        self.selected = connections.iter().map(|c| c.name.clone()).collect();
+        self.error = None;
+        self.open = true;
+    }
+}
+
+pub fn render_export_modal(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    let names: Vec<String> = layout
+        .state
+        .0
+        .read(cx)
+        .connections
+        .iter()
+        .map(|c| c.name.clone())
+        .collect();
+    let rows: Vec<AnyElement> = names
+        .into_iter()
+        .enumerate()
+        .map(|(ix, name)| {
+            let checked = layout.export.selected.contains(&name);
+            Checkbox::new(("export_connection", ix))
+                .label(name.clone())
+                .checked(checked)
+                .on_click(cx.listener(move |this, checked: &bool, _, cx| {
+                    this.export.selected.retain(|n| n != &name);
+                    if *checked {
+                        this.export.selected.push(name.clone());
+                    }
+                    cx.notify();
+                }))
+                .into_any_element()
+        })
+        .collect();
+    let count = layout.export.selected.len();
+
+    div()
+        .absolute()
+        .size_full()
+        .bg(black().opacity(0.8))
+        .flex()
+        .items_center()
+        .justify_center()
+        .child(
+            div()
+                .w_96()
+                .bg(rgb(0x252526))
+                .border_1()
+                .border_color(rgb(0x454545))
+                .p_4()
+                .flex()
+                .flex_col()
+                .gap_4()
+                .child(div().text_xl().child("Export Connections"))
+                .child(div().text_sm().text_color(rgb(0xaaaaaa)).child(
+                    "Writes a JSON or TOML file to share or commit. Passwords are left out; \
+                     whoever imports it is asked for theirs on first connect. Importing it \
+                     again updates those connections, but one taken out of the file is only \
+                     flagged, not removed.",
+                ))
+                .child(
+                    div()
+                        .id("export_connections")
+                        .max_h(px(320.))
+                        .overflow_y_scroll()
+                        .flex()
+                        .flex_col()
+                        .gap_2()
+                        .children(rows),
+                )
+                .when_some(layout.export.error.clone(), |el, error| {
+                    el.child(div().text_sm().text_color(rgb(0xff5555)).child(error))
+                })
+                .child(
+                    div()
+                        .flex()
+                        .gap_2()
+                        .justify_end()
+                        .child(
+                            Button::new("export_cancel")
+                                .label("Cancel")
+                                .on_click(cx.listener(|this, _, _, cx| {
+                                    this.export.open = false;
+                                    cx.notify();
+                                })),
+                        )
+                        .child(
+                            Button::new("export_confirm")
+                                .primary()
+                                .label(format!("Export {}", count))
+                                .disabled(count == 0)
+                                .on_click(cx.listener(|this, _, _, cx| export(this, cx))),
+                        ),
+                ),
+        )
+}
+
+/// Ask where to save, then write the ticked connections in list order.
+fn export(layout: &mut super::MainLayout, cx: &mut Context<super::MainLayout>) {
+    let connections: Vec<ConnectionConfig> = layout
+        .state
+        .0
+        .read(cx)
+        .connections
+        .iter()
+        .filter(|c| layout.export.selected.contains(&c.name))
+        .cloned()
+        .collect();
+    let async_cx = cx.to_async();
+    cx.spawn(
+        |this: WeakEntity<super::MainLayout>, _: &mut AsyncApp| async move {
+            let mut cx = async_cx.clone();
+            let file = rfd::AsyncFileDialog::new()
+                .set_title("Export Connections")
+                .set_file_name("connections.json")
+                .add_filter("JSON", &["json"])
+                .add_filter("TOML", &["toml"])
+                .save_file()
+                .await;
+
+            if let Some(file) = file {
+                let result = crate::share::export_connections(&connections, file.path());
+                let _ = this.update(&mut cx, |this, cx| {
+                    match result {
+                        Ok(()) => {
+                            this.export.open = false;
+                            this.state.0.update(cx, |state, _| {
+                                state.notifications.success(format!(
+                                    "Exported {} connection(s) to {}",
+                                    connections.len(),
+                                    file.path().display()
+                                ));
+                            });
+                        }
+                        Err(e) => this.export.error = Some(format!("Failed to export: {}", e)),
+                    }
+                    cx.notify();
+                });
+            }
+        },
+    )
+    .detach();
+}
@@ -0,0 +1,330 @@
+/// Find bar over a tab's results: highlights matching cells, steps between
+/// them, and can hide the rows without a match. Works on the rows already
+/// fetched; the query is never re-run.
+use crate::db::{Matcher, SearchOptions};
+use crate::table_delegate::QueryResultsDelegate;
+use gpui::prelude::*;
+use gpui::*;
+use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::input::{Input, InputEvent, InputState};
+use gpui_component::menu::{DropdownMenu, PopupMenuItem};
+use gpui_component::table::TableState;
+use gpui_component::{Selectable, Sizable};
+
+pub struct ResultFind {
+    pub open: bool,
+    pub input: Entity<InputState>,
+    pub case_sensitive: bool,
+    pub regex: bool,
+    /// Column to search, by name; every visible column when `None`.
+    pub column: Option<String>,
+    /// Show only the rows with a match.
+    pub filter: bool,
+    /// Why the search text can't be used, such as a broken pattern.
+    pub error: Option<String>,
+}
+
+impl ResultFind {
+    /// A closed find bar for tab `tab_id`, searching as its text changes.
+    pub fn new(tab_id: usize, window: &mut Window, cx: &mut Context<super::MainLayout>) -> Self {
+        let input = cx.new(|cx| InputState::new(window, cx).placeholder("Find in results"));
+        cx.subscribe(&input, move |this, _, event: &InputEvent, cx| match event {
+            InputEvent::Change => {
+                apply_search(this, tab_id, true, true, cx);
+                cx.notify();
+            }
+            InputEvent::PressEnter { secondary } => step(this, tab_id, !secondary, cx),
+            _ => {}
+        })
+        .detach();
+        Self {
+            open: false,
+            input,
+            case_sensitive: false,
+            regex: false,
+            column: None,
+            filter: false,
+            error: None,
+        }
+    }
+
+    fn options(&self, cx: &App) -> SearchOptions {
+        SearchOptions {
+            // A closed bar neither highlights nor filters.
+            text: if self.open {
+                self.input.read(cx).value().to_string()
+            } else {
+                String::new()
+            },
+            case_sensitive: self.case_sensitive,
+            regex: self.regex,
+        }
+    }
+}
+
+/// Search the results of tab `tab_id` again after the text, options or
+/// results change, jumping to the first match if `scroll` is set.
+pub fn apply(
+    layout: &mut super::MainLayout,
+    tab_id: usize,
+    scroll: bool,
+    cx: &mut Context<super::MainLayout>,
+) {
+    apply_search(layout, tab_id, scroll, false, cx);
+    cx.notify();
+}
+
+/// `apply` without redrawing, for use while rendering. With `debounce` the
+/// search waits for a pause in typing.
+pub fn apply_search(
+    layout: &mut super::MainLayout,
+    tab_id: usize,
+    scroll: bool,
+    debounce: bool,
+    cx: &mut App,
+) {
+    let Some(editor) = layout.editors.get_mut(&tab_id) else {
+        return;
+    };
+    let find = &mut editor.find;
+    let matcher = match Matcher::new(&find.options(cx)) {
+        Ok(matcher) => {
+            find.error = None;
+            matcher
+        }
+        Err(e) => {
+            find.error = Some(e.to_string());
+            None
+        }
+    };
+    let Some(table) = editor.table_state.clone() else {
+        return;
+    };
+    let (column, filter) = (find.column.clone(), find.filter && matcher.is_some());
+    table.update(cx, |table, cx| {
+        let delegate = table.delegate_mut();
+        let column = column.and_then(|name| delegate.column_index(&name));
+        delegate.set_search(matcher, column, filter, scroll, debounce, cx);
+        cx.notify();
+    });
+}
+
+/// Jump to the next or previous match.
+pub fn step(
+    layout: &mut super::MainLayout,
+    tab_id: usize,
+    forward: bool,
+    cx: &mut Context<super::MainLayout>,
+) {
+    let Some(table) = layout
+        .editors
+        .get(&tab_id)
+        .and_then(|e| e.table_state.clone())
+    else {
+        return;
+    };
+    table.update(cx, |table, cx| {
+        if let Some((row, col)) = table.delegate_mut().step_match(forward) {
+            scroll_to(table, row, col, cx);
+        }
+        cx.notify();
+    });
+    cx.notify();
+}
+
+pub fn scroll_to(
+    table: &mut TableState<QueryResultsDelegate>,
+    row: usize,
+    col: Option<usize>,
+    cx: &mut Context<TableState<QueryResultsDelegate>>,
+) {
+    table.scroll_to_row(row, cx);
+    if let Some(col) = col {
+        table.scroll_to_col(col, cx);
+    }
+}
+
+/// Show or hide the find bar of the selected tab.
+pub fn toggle(
+    layout: &mut super::MainLayout,
+    window: &mut Window,
+    cx: &mut Context<super::MainLayout>,
+) {
+    let Some(tab_id) = layout.state.0.read(cx).active_tab().map(|t| t.id) else {
+        return;
+    };
+    let Some(find) = layout.editors.get_mut(&tab_id).map(|e| &mut e.find) else {
+        return;
+    };
+    find.open = !find.open;
+    let open = find.open;
+    if open {
+        find.input.update(cx, |input, cx| input.focus(window, cx));
+    }
+    apply(layout, tab_id, open, cx);
+}
+
+pub fn render_find_bar(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> Option<impl IntoElement> {
+    let tab_id = layout.state.0.read(cx).active_tab()?.id;
+    let editor = layout.editors.get(&tab_id)?;
+    let find = &editor.find;
+    if !find.open {
+        return None;
+    }
+    let table = editor.table_state.clone();
+    let (count, current, searching, columns) =
+        table
+            .as_ref()
+            .map_or((0, None, false, Vec::new()), |table| {
+                let delegate = table.read(cx).delegate();
+                let columns: Vec<String> = delegate
+                    .layout
+                    .iter()
+                    .filter(|c| !c.state.hidden)
+                    .map(|c| c.state.name.clone())
+                    .collect();
+                let count = delegate.found.cells;
+                (count, delegate.current_match(), delegate.searching, columns)
+            });
+    let status = match (&find.error, current) {
+        (Some(error), _) => error.clone(),
+        _ if find.input.read(cx).value().is_empty() => String::new(),
+        _ if searching => "Searching…".to_string(),
+        (None, Some(ix)) => format!("{} of {}", ix + 1, count),
+        (None, None) => "No matches".to_string(),
+    };
+
+    Some(
+        div()
+            .flex()
+            .gap_2()
+            .items_center()
+            .text_xs()
+            .child(
+                div()
+                    .w_64()
+                    .child(Input::new(&find.input).cleanable(true).small()),
+            )
+            .child(
+                Button::new("find_case")
+                    .label("Aa")
+                    .xsmall()
+                    .ghost()
+                    .selected(find.case_sensitive)
+                    .tooltip("Match case")
+                    .on_click(cx.listener(move |this, _, _, cx| {
+                        update(
+                            this,
+                            tab_id,
+                            |find| find.case_sensitive = !find.case_sensitive,
+                            cx,
+                        );
+                    })),
+            )
+            .child(
+                Button::new("find_regex")
+                    .label(".*")
+                    .xsmall()
+                    .ghost()
+                    .selected(find.regex)
+                    .tooltip("Regular expression")
+                    .on_click(cx.listener(move |this, _, _, cx| {
+                        update(this, tab_id, |find| find.regex = !find.regex, cx);
+                    })),
+            )
+            .child(render_column_menu(tab_id, find.column.clone(), columns, cx))
+            .child(
+                Button::new("find_filter")
+                    .label("Filter Rows")
+                    .xsmall()
+                    .ghost()
+                    .selected(find.filter)
+                    .tooltip("Show only rows with a match")
+                    .on_click(cx.listener(move |this, _, _, cx| {
+                        update(this, tab_id, |find| find.filter = !find.filter, cx);
+                    })),
+            )
+            .child(
+                Button::new("find_prev")
+                    .label("↑")
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(move |this, _, _, cx| step(this, tab_id, false, cx))),
+            )
+            .child(
+                Button::new("find_next")
+                    .label("↓")
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(move |this, _, _, cx| step(this, tab_id, true, cx))),
+            )
+            .child(
+                div()
+                    .flex_1()
+                    .text_color(if find.error.is_some() {
+                        rgb(0xf48771)
+                    } else {
+                        rgb(0x888888)
+                    })
+                    .child(status),
+            )
+            .child(
+                Button::new("find_close")
+                    .label("×")
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(|this, _, window, cx| toggle(this, window, cx))),
+            ),
+    )
+}
+
+/// Change an option and search again.
+fn update(
+    layout: &mut super::MainLayout,
+    tab_id: usize,
+    change: impl FnOnce(&mut ResultFind),
+    cx: &mut Context<super::MainLayout>,
+) {
+    if let Some(editor) = layout.editors.get_mut(&tab_id) {
+        change(&mut editor.find);
+    }
+    apply(layout, tab_id, true, cx);
+}
+
+fn render_column_menu(
+    tab_id: usize,
+    selected: Option<String>,
+    columns: Vec<String>,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    let view = cx.entity();
+    Button::new("find_column")
+        .label(match &selected {
+            Some(column) => format!("In {}", column),
+            None => "All Columns".to_string(),
+        })
+        .xsmall()
+        .ghost()
+        .dropdown_menu(move |menu, _, _| {
+            let choices = std::iter::once(None).chain(columns.iter().cloned().map(Some));
+            choices.fold(menu.scrollable(true).max_h(px(400.)), |menu, column| {
+                let label = column.clone().unwrap_or_else(|| "All Columns".to_string());
+                menu.item(
+                    PopupMenuItem::new(label)
+                        .checked(column == selected)
+                        .on_click({
+                            let view = view.clone();
+                            move |_, _, cx| {
+                                let column = column.clone();
+                                view.update(cx, |this, cx| {
+                                    update(this, tab_id, |find| find.column = column, cx);
+                                });
+                            }
+                        }),
+                )
+            })
+        })
+}
@@ -0,0 +1,240 @@
+/// Query history panel: search past queries and reopen them in the editor.
+use crate::history::{HistoryEntry, HistoryFilter, StatusFilter};
+use chrono::Local;
+use gpui::prelude::*;
+use gpui::*;
+use gpui_component::StyledExt;
+use gpui_component::button::{Button, ButtonGroup};
+use gpui_component::input::{Input, InputState};
+use gpui_component::scroll::ScrollbarAxis;
+use gpui_component::{Selectable, Sizable};
+
+/// Rows rendered at once; narrowing the search reaches older entries.
+const MAX_VISIBLE_ENTRIES: usize = 200;
+
+const STATUS_FILTERS: [(StatusFilter, &str); 3] = [
+    (StatusFilter::All, "All"),
+    (StatusFilter::Succeeded, "Succeeded"),
+    (StatusFilter::Failed, "Failed"),
+];
+
+pub struct HistoryPanel {
+    pub open: bool,
+    pub search_input: Entity<InputState>,
+    pub connection: Option<String>,
+    pub status: StatusFilter,
+}
+
+impl HistoryPanel {
+    pub fn new<C>(window: &mut Window, cx: &mut C) -> Self
+    where
+        C: AppContext,
+        C::Result<Entity<InputState>>: Into<Entity<InputState>>,
+    {
+        Self {
+            open: false,
+            search_input: cx
+                .new(|cx| InputState::new(window, cx).placeholder("Search history"))
+                .into(),
+            connection: None,
+            status: StatusFilter::All,
+        }
+    }
+
+    fn filter(&self, cx: &App) -> HistoryFilter {
+        HistoryFilter {
+            text: self.search_input.read(cx).value().to_string(),
+            connection: self.connection.clone(),
+            status: self.status,
+        }
+    }
+}
+
+pub fn render_history_panel(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    div()
+        .w_96()
+        .h_full()
+        .border_l_1()
+        .border_color(rgb(0x333333))
+        .p_4()
+        .flex()
+        .flex_col()
+        .gap_2()
+        .child(
+            div()
+                .flex()
+                .justify_between()
+                .items_center()
+                .child(
+                    div()
+                        .text_sm()
+                        .font_weight(gpui::FontWeight::SEMIBOLD)
+                        .child("Query History"),
+                )
+                .child(
+                    Button::new("close_history")
+                        .label("Close")
+                        .xsmall()
+                        .on_click(cx.listener(|this, _, _, cx| {
+                            this.history.open = false;
+                            cx.notify();
+                        })),
+                ),
+        )
+        .child(
+            Input::new(&layout.history.search_input)
+                .appearance(true)
+                .cleanable(true),
+        )
+        .child(render_status_filter(layout, cx))
+        .child(render_connection_filter(layout, cx))
+        .child(render_entries(layout, cx))
+}
+
+fn render_status_filter(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    ButtonGroup::new("history_status")
+        .xsmall()
+        .children(STATUS_FILTERS.iter().map(|&(status, label)| {
+            Button::new(label)
+                .label(label)
+                .selected(status == layout.history.status)
+        }))
+        .on_click(cx.listener(|this, clicks: &Vec<usize>, _, cx| {
+            if let Some(&(status, _)) = clicks.first().and_then(|&ix| STATUS_FILTERS.get(ix)) {
+                this.history.status = status;
+                cx.notify();
+            }
+        }))
+}
+
+fn render_connection_filter(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    let names: Vec<String> = layout
+        .state
+        .0
+        .read(cx)
+        .history
+        .connections()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    let selected = layout.history.connection.clone();
+
+    ButtonGroup::new("history_connection")
+        .xsmall()
+        .child(
+            Button::new("history_all_connections")
+                .label("All connections")
+                .selected(selected.is_none()),
+        )
+        .children(names.iter().enumerate().map(|(i, name)| {
+            Button::new(("history_connection", i))
+                .label(name.clone())
+                .selected(selected.as_ref() == Some(name))
+        }))
+        .on_click(cx.listener(move |this, clicks: &Vec<usize>, _, cx| {
+            if let Some(&ix) = clicks.first() {
+                // Index 0 is "All connections".
+                this.history.connection = ix.checked_sub(1).and_then(|i| names.get(i).cloned());
+                cx.notify();
+            }
+        }))
+}
+
+fn render_entries(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    let filter = layout.history.filter(cx);
+    let entries: Vec<HistoryEntry> = layout
+        .state
+        .0
+        .read(cx)
+        .history
+        .search(&filter)
+        .into_iter()
+        .take(MAX_VISIBLE_ENTRIES)
+        .cloned()
+        .collect();
+
+    let list = div().flex_1().flex().flex_col();
+    if entries.is_empty() {
+        return list
+            .text_sm()
+            .text_color(rgb(0x888888))
+            .child("No matching queries.")
+            .into_any_element();
+    }
+
+    list.scrollable(ScrollbarAxis::Vertical)
+        .children(
+            entries
+                .into_iter()
+                .enumerate()
+                .map(|(i, entry)| render_entry(i, entry, cx)),
+        )
+        .into_any_element()
+}
+
+fn render_entry(
+    ix: usize,
+    entry: HistoryEntry,
+    cx: &mut Context<super::MainLayout>,
+) -> Stateful<Div> {
+    let mut meta = format!(
+        "{} · {} · {} ms",
+        entry.connection,
+        entry
+            .executed_at
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S"),
+        entry.duration_ms
+    );
+    if let Some(rows) = entry.rows {
+        meta.push_str(&format!(" · {} rows", rows));
+    }
+
+    div()
+        .id(("history_entry", ix))
+        .py_2()
+        .border_b_1()
+        .border_color(rgb(0x333333))
+        .cursor_pointer()
+        .hover(|style| style.bg(rgb(0x2a2d2e)))
+        .child(
+            div()
+                .text_sm()
+                .overflow_hidden()
+                .text_ellipsis()
+                .whitespace_nowrap()
+                .child(entry.query.lines().next().unwrap_or_default().to_string()),
+        )
+        .child(div().text_xs().text_color(rgb(0x888888)).child(meta))
+        .when_some(entry.error.clone(), |el, error| {
+            el.child(
+                div()
+                    .text_xs()
+                    .text_color(rgb(0xff5555))
+                    .overflow_hidden()
+                    .text_ellipsis()
+                    .whitespace_nowrap()
+                    .child(error),
+            )
+        })
+        .on_click(cx.listener(move |this, _, window, cx| {
+            let query = entry.query.clone();
+            if let Some(input) = this.query_input(cx) {
+                input.update(cx, |input, cx| {
+                    input.set_value(query, window, cx);
+                });
+            }
+        }))
+}
@@ -0,0 +1,301 @@
+/// Preview of connections found by the importers, to pick which to add.
+use crate::import::{self, ImportCandidate};
+use crate::state::{ConnectionConfig, is_shared_update, removed_from_shared};
+use crate::vault::VaultLocked;
+use gpui::prelude::*;
+use gpui::*;
+use gpui_component::Disableable;
+use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::checkbox::Checkbox;
+
+#[derive(Default)]
+pub struct ImportPreview {
+    pub open: bool,
+    /// Each found connection and whether it is ticked for import.
+    pub candidates: Vec<(ImportCandidate, bool)>,
+    /// Files that couldn't be read, connections gone from a shared file, or
+    /// why the import failed.
+    pub errors: Vec<String>,
+}
+
+impl ImportPreview {
+    /// Open the preview with everything ticked that doesn't clash with an
+    /// existing connection.
+    pub fn show(
+        &mut self,
+        candidates: Vec<ImportCandidate>,
+        mut errors: Vec<String>,
+        connections: &[ConnectionConfig],
+    ) {
+        let configs: Vec<ConnectionConfig> = candidates.iter().map(|c| c.config.clone()).collect();
+        errors.extend(removed_from_shared(connections, &configs).into_iter().map(|c| {
+            format!(
+                "'{}' is no longer in the shared file; remove it from the sidebar if it isn't needed",
+                c.name
+            )
+        }));
+        self.candidates = candidates
+            .into_iter()
+            .map(|candidate| {
+                let clash = outcome(connections, &candidate.config) == Outcome::Clash;
+                (candidate, !clash)
+            })
+            .collect();
+        self.errors = errors;
+        self.open = true;
+    }
+}
+
+/// What importing a candidate would do to the connection list.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Outcome {
+    Add,
+    /// Refresh a connection imported from the same shared file.
+    Update,
+    /// Another connection already has the name.
+    Clash,
+}
+
+fn outcome(connections: &[ConnectionConfig], config: &ConnectionConfig) -> Outcome {
+    match connections.iter().find(|c| c.name == config.name) {
+        None => Outcome::Add,
+        Some(existing) if is_shared_update(existing, config) => Outcome::Update,
+        Some(_) => Outcome::Clash,
+    }
+}
+
+/// Look for connections in the usual places and preview them.
+pub fn import_detected(layout: &mut super::MainLayout, cx: &mut Context<super::MainLayout>) {
+    let project_dir = std::env::current_dir().unwrap_or_default();
+    let (candidates, errors) = import::discover(&project_dir);
+    if candidates.is_empty() && errors.is_empty() {
+        layout.state.0.update(cx, |state, cx| {
+            state
+                .notifications
+                .info("No connections found in ~/.pgpass, pg_service.conf or .env");
+            cx.notify();
+        });
+        return;
+    }
+    show(layout, candidates, errors, cx);
+}
+
+/// Ask for an export file from another tool and preview what it holds.
+pub fn import_from_file(cx: &mut Context<super::MainLayout>) {
+    let async_cx = cx.to_async();
+    cx.spawn(
+        |this: WeakEntity<super::MainLayout>, _: &mut AsyncApp| async move {
+            let mut cx = async_cx.clone();
+            let file = rfd::AsyncFileDialog::new()
+                .set_title("Import Connections")
+                .pick_file()
+                .await;
+
+            if let Some(file) = file {
+                let (candidates, errors) = match import::import_file(file.path()) {
+                    Ok(found) => found,
+                    Err(e) => (
+                        Vec::new(),
+                        vec![format!("{}: {}", file.path().display(), e)],
+                    ),
+                };
+                let _ = this.update(&mut cx, |this, cx| {
+                    show(this, candidates, errors, cx);
+                });
+            }
+        },
+    )
+    .detach();
+}
+
+fn show(
+    layout: &mut super::MainLayout,
+    candidates: Vec<ImportCandidate>,
+    errors: Vec<String>,
+    cx: &mut Context<super::MainLayout>,
+) {
+    let connections = &layout.state.0.read(cx).connections;
+    layout.import.show(candidates, errors, connections);
+    cx.notify();
+}
+
+pub fn render_import_modal(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    let connections = &layout.state.0.read(cx).connections;
+    let outcomes: Vec<Outcome> = layout
+        .import
+        .candidates
+        .iter()
+        .map(|(candidate, _)| outcome(connections, &candidate.config))
+        .collect();
+    let selected = layout.import.candidates.iter().filter(|(_, s)| *s).count();
+    let rows: Vec<AnyElement> = layout
+        .import
+        .candidates
+        .iter()
+        .zip(outcomes)
+        .enumerate()
+        .map(|(ix, ((candidate, checked), outcome))| {
+            render_candidate(ix, candidate, *checked, outcome, cx).into_any_element()
+        })
+        .collect();
+
+    div()
+        .absolute()
+        .size_full()
+        .bg(black().opacity(0.8))
+        .flex()
+        .items_center()
+        .justify_center()
+        .child(
+            div()
+                .w_96()
+                .bg(rgb(0x252526))
+                .border_1()
+                .border_color(rgb(0x454545))
+                .p_4()
+                .flex()
+                .flex_col()
+                .gap_4()
+                .child(div().text_xl().child("Import Connections"))
+                .when(!rows.is_empty(), |el| {
+                    el.child(div().text_sm().text_color(rgb(0xaaaaaa)).child(
+                        "Passwords are saved to the system keyring, not to the connections file.",
+                    ))
+                })
+                .child(
+                    div()
+                        .id("import_candidates")
+                        .max_h(px(320.))
+                        .overflow_y_scroll()
+                        .flex()
+                        .flex_col()
+                        .gap_2()
+                        .children(rows),
+                )
+                .children(layout.import.errors.iter().map(|error| {
+                    div()
+                        .text_sm()
+                        .text_color(rgb(0xff5555))
+                        .child(error.clone())
+                }))
+                .child(
+                    div()
+                        .flex()
+                        .gap_2()
+                        .justify_end()
+                        .child(
+                            Button::new("import_cancel")
+                                .label("Cancel")
+                                .on_click(cx.listener(|this, _, _, cx| {
+                                    this.import = ImportPreview::default();
+                                    cx.notify();
+                                })),
+                        )
+                        .child(
+                            Button::new("import_confirm")
+                                .primary()
+                                .label(format!("Import {}", selected))
+                                .disabled(selected == 0)
+                                .on_click(cx.listener(|this, _, window, cx| {
+                                    import_selected(this, window, cx);
+                                })),
+                        ),
+                ),
+        )
+}
+
+fn render_candidate(
+    ix: usize,
+    candidate: &ImportCandidate,
+    checked: bool,
+    outcome: Outcome,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    let config = &candidate.config;
+    let detail = match outcome {
+        Outcome::Clash => "A connection with this name already exists".to_string(),
+        Outcome::Update => format!(
+            "{} · updates the existing connection",
+            config.connection_string
+        ),
+        Outcome::Add => {
+            let password = if config.password.is_empty() {
+                ""
+            } else {
+                " · with password"
+            };
+            format!(
+                "{}{} · {}",
+                config.connection_string, password, candidate.source
+            )
+        }
+    };
+
+    div()
+        .flex()
+        .flex_col()
+        .gap_1()
+        .child(
+            Checkbox::new(("import_candidate", ix))
+                .label(config.name.clone())
+                .checked(checked)
+                .disabled(outcome == Outcome::Clash)
+                .on_click(cx.listener(move |this, checked: &bool, _, cx| {
+                    if let Some((_, selected)) = this.import.candidates.get_mut(ix) {
+                        *selected = *checked;
+                    }
+                    cx.notify();
+                })),
+        )
+        .child(
+            div()
+                .pl_6()
+                .text_xs()
+                .text_color(rgb(0x888888))
+                .child(detail),
+        )
+}
+
+fn import_selected(
+    layout: &mut super::MainLayout,
+    window: &mut Window,
+    cx: &mut Context<super::MainLayout>,
+) {
+    let configs: Vec<_> = layout
+        .import
+        .candidates
+        .iter()
+        .filter(|(_, selected)| *selected)
+        .map(|(candidate, _)| candidate.config.clone())
+        .collect();
+    let count = configs.len();
+    let result = layout.state.0.update(cx, |state, cx| {
+        let result = state.import_connections(configs);
+        if let Ok(skipped) = &result {
+            state
+                .notifications
+                .success(format!("Imported {} connection(s)", count - skipped.len()));
+            if !skipped.is_empty() {
+                state.notifications.warning(
+                    format!("Skipped {} connection(s)", skipped.len()),
+                    skipped.join("\n"),
+                );
+            }
+        }
+        cx.notify();
+        result
+    });
+    match result {
+        Ok(_) => layout.import = ImportPreview::default(),
+        Err(e) => {
+            if e.is::<VaultLocked>() {
+                layout.vault.show(window, cx);
+            }
+            layout.import.errors = vec![e.to_string()];
+        }
+    }
+    cx.notify();
+}
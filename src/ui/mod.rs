@@ -1,82 +1,336 @@
 /// UI components and layouts for the application.
 /// This module is organized into submodules for maintainability:
-/// - connection_modal: New connection dialog
+/// - completion: SQL completion provider for the query editor
+/// - connection_modal: New and edit connection dialog
+/// - diagnostics: Syntax and query error underlines in the query editor
 /// - sidebar: Left sidebar with connections and tables
 /// - editor: SQL query editor
+/// - error_panel: Structured query error panel beneath the editor
+/// - export: Export of connections to a shared file
+/// - find: Find and filter within the selected tab's results
+/// - history: Searchable query history panel
+/// - import: Preview of connections imported from other tools
+/// - notifications: Toasts and the notification history drawer
+/// - password_prompt: Password prompt for connections that don't store it
 /// - results: Query results display
+/// - saved_queries: Saved query library, save dialog and snippet tab stops
+/// - tabs: Editor tab bar
+/// - vault: Passphrase prompt for the credential vault
 /// - main_layout: Main UI layout orchestrator
+pub mod completion;
 pub mod connection_modal;
+pub mod diagnostics;
 pub mod editor;
+pub mod error_panel;
+pub mod export;
+pub mod find;
+pub mod history;
+pub mod import;
+pub mod notifications;
+pub mod password_prompt;
 pub mod results;
+pub mod saved_queries;
 pub mod sidebar;
+pub mod tabs;
+pub mod vault;
 
+use crate::launch::LaunchTarget;
 use crate::state::GlobalAppState;
 use crate::table_delegate::QueryResultsDelegate;
+use crate::tabs::{Session, SessionTab};
 use gpui::prelude::*;
 use gpui::*;
-use gpui_component::input::InputState;
-use gpui_component::table::TableState;
+use gpui_component::input::{InputEvent, InputState};
+use gpui_component::table::{TableEvent, TableState};
 
+use completion::SqlCompletionProvider;
 pub use connection_modal::ConnectionForm;
+use export::ExportForm;
+use history::HistoryPanel;
+use import::ImportPreview;
+use notifications::NotificationCenter;
+use password_prompt::PasswordPrompt;
+use saved_queries::{SaveQueryForm, SnippetSession};
+use std::collections::HashMap;
+use std::rc::Rc;
+use vault::VaultForm;
 
 /// Main application layout component
 pub struct MainLayout {
     pub state: GlobalAppState,
     pub form: ConnectionForm,
-    pub query_input: Entity<InputState>,
+    pub history: HistoryPanel,
+    pub notifications: NotificationCenter,
+    pub save_query: SaveQueryForm,
+    pub import: ImportPreview,
+    pub export: ExportForm,
+    pub vault: VaultForm,
+    pub password_prompt: PasswordPrompt,
+    /// Connection whose Delete is waiting for confirmation in the sidebar.
+    pub confirm_delete_connection: Option<String>,
+    /// Editor state of each tab, by tab id.
+    pub editors: HashMap<usize, TabEditor>,
+    completion_provider: Rc<SqlCompletionProvider>,
+}
+
+/// The parts of an editor tab that belong to the view.
+pub struct TabEditor {
+    pub input: Entity<InputState>,
     pub table_state: Option<Entity<TableState<QueryResultsDelegate>>>,
     pub last_result_id: usize,
+    pub find: find::ResultFind,
+    /// Tab stops left in the last snippet inserted into this tab.
+    pub snippet: Option<SnippetSession>,
 }
 
 impl MainLayout {
-    pub fn new<C>(state: GlobalAppState, window: &mut Window, cx: &mut C) -> Self
-    where
-        C: AppContext,
-        C::Result<Entity<InputState>>: Into<Entity<InputState>>,
-    {
-        let query_input = cx
-            .new(|cx| {
-                InputState::new(window, cx)
-                    .multi_line()
-                    .code_editor("sql")
-                    .line_number(true)
-                    .searchable(true)
-                    .placeholder("-- Enter your SQL query here...")
-            })
-            .into();
-
-        Self {
+    pub fn new(state: GlobalAppState, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let mut layout = Self {
+            completion_provider: Rc::new(SqlCompletionProvider::new(state.clone())),
             state,
             form: ConnectionForm::new(window, cx),
-            query_input,
-            table_state: None,
-            last_result_id: 0,
+            history: HistoryPanel::new(window, cx),
+            notifications: NotificationCenter::default(),
+            save_query: SaveQueryForm::new(window, cx),
+            import: ImportPreview::default(),
+            export: ExportForm::default(),
+            vault: VaultForm::new(window, cx),
+            password_prompt: PasswordPrompt::new(window, cx),
+            confirm_delete_connection: None,
+            editors: HashMap::new(),
+        };
+
+        let session = Session::load().unwrap_or_else(|e| {
+            tracing::error!(error = %e, "Failed to load session");
+            Session::default()
+        });
+        for tab in session.tabs {
+            layout.open_tab(tab, window, cx);
+        }
+        if layout.editors.is_empty() {
+            layout.open_tab(SessionTab::default(), window, cx);
+        }
+        layout.state.0.update(cx, |state, _| {
+            state.active_tab = session.active.min(state.tabs.len() - 1);
+        });
+
+        if layout.state.0.read(cx).vault_needed() {
+            layout.vault.show(window, cx);
+        }
+
+        cx.on_app_quit(|this, cx| {
+            this.save_session(cx);
+            async {}
+        })
+        .detach();
+        layout
+    }
+
+    /// Add a tab restored from `tab`, after the existing ones, and return its id.
+    pub fn open_tab(
+        &mut self,
+        tab: SessionTab,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> usize {
+        let id = self.state.0.update(cx, |state, _| {
+            let title = if tab.title.is_empty() {
+                crate::tabs::untitled_name(state.tabs.iter().map(|t| t.title.as_str()))
+            } else {
+                tab.title
+            };
+            let id = state.add_tab(title);
+            if let Some(query_tab) = state.tab_mut(id) {
+                query_tab.connection = tab.connection;
+                query_tab.saved_query = tab.saved_query;
+                query_tab.saved_text = tab.saved_text;
+            }
+            id
+        });
+
+        let completion_provider = self.completion_provider.clone();
+        let input = cx.new(|cx| {
+            let mut input = InputState::new(window, cx)
+                .multi_line()
+                .code_editor("sql")
+                .line_number(true)
+                .searchable(true)
+                .placeholder("-- Enter your SQL query here...")
+                .default_value(tab.text);
+            input.lsp.completion_provider = Some(completion_provider);
+            diagnostics::refresh_syntax_diagnostics(&mut input, cx);
+            cx.subscribe_self(|input, event: &InputEvent, cx| {
+                if matches!(event, InputEvent::Change) {
+                    diagnostics::refresh_syntax_diagnostics(input, cx);
+                }
+            })
+            .detach();
+            input
+        });
+
+        self.editors.insert(
+            id,
+            TabEditor {
+                input,
+                table_state: None,
+                last_result_id: 0,
+                find: find::ResultFind::new(id, window, cx),
+                snippet: None,
+            },
+        );
+        id
+    }
+
+    /// Open what was passed on the command line: scripts in new tabs, then
+    /// databases as unsaved connections, connected in the selected tab.
+    pub fn open_launch_targets(
+        &mut self,
+        targets: Vec<LaunchTarget>,
+        errors: Vec<String>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if !errors.is_empty() {
+            self.state.0.update(cx, |state, _| {
+                state
+                    .notifications
+                    .error("Couldn't open everything passed in", errors.join("\n"));
+            });
+        }
+        let (databases, scripts): (Vec<_>, Vec<_>) = targets
+            .into_iter()
+            .partition(|target| matches!(target, LaunchTarget::Database(_)));
+        for target in scripts {
+            let LaunchTarget::SqlFile { path, text } = target else {
+                continue;
+            };
+            let title = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let id = self.open_tab(
+                SessionTab {
+                    title,
+                    text,
+                    ..Default::default()
+                },
+                window,
+                cx,
+            );
+            self.state.0.update(cx, |state, _| {
+                if let Some(ix) = state.tabs.iter().position(|t| t.id == id) {
+                    state.active_tab = ix;
+                }
+            });
+        }
+        for target in databases {
+            let LaunchTarget::Database(config) = target else {
+                continue;
+            };
+            let conn = self.state.0.update(cx, |state, _| {
+                let name = state.add_ephemeral_connection(config);
+                state.connections.iter().find(|c| c.name == name).cloned()
+            });
+            if let Some(conn) = conn {
+                sidebar::select_connection(self, conn, None, window, cx);
+            }
+        }
+    }
+
+    /// The selected tab's editor.
+    pub fn active_editor(&self, cx: &App) -> Option<&TabEditor> {
+        let id = self.state.0.read(cx).active_tab()?.id;
+        self.editors.get(&id)
+    }
+
+    pub fn active_editor_mut(&mut self, cx: &App) -> Option<&mut TabEditor> {
+        let id = self.state.0.read(cx).active_tab()?.id;
+        self.editors.get_mut(&id)
+    }
+
+    /// The selected tab's buffer.
+    pub fn query_input(&self, cx: &App) -> Option<Entity<InputState>> {
+        self.active_editor(cx).map(|editor| editor.input.clone())
+    }
+
+    /// Write the open tabs to the session file.
+    pub fn save_session(&self, cx: &App) {
+        let state = self.state.0.read(cx);
+        let session = Session {
+            tabs: state
+                .tabs
+                .iter()
+                .map(|tab| SessionTab {
+                    title: tab.title.clone(),
+                    text: self
+                        .editors
+                        .get(&tab.id)
+                        .map(|e| e.input.read(cx).value().to_string())
+                        .unwrap_or_default(),
+                    connection: tab.connection.clone(),
+                    saved_query: tab.saved_query.clone(),
+                    saved_text: tab.saved_text.clone(),
+                })
+                .collect(),
+            active: state.active_tab,
+        };
+        if let Err(e) = session.save() {
+            tracing::error!(error = %e, "Failed to save session");
         }
     }
 }
 
 impl Render for MainLayout {
     fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        // Create or recreate table state when results change
-        let current_result_id = self.state.0.read(cx).result_id;
-        if current_result_id != self.last_result_id {
-            self.last_result_id = current_result_id;
-            let app_state = self.state.0.read(cx);
-            if let Some(results) = app_state.query_results.clone() {
-                let sort_column = app_state.sort_column.clone();
-                let sort_ascending = app_state.sort_ascending;
+        // Create or recreate the selected tab's table when its results change
+        let state = self.state.0.read(cx);
+        let changed = state.active_tab().filter(|tab| {
+            self.editors
+                .get(&tab.id)
+                .is_some_and(|editor| editor.last_result_id != tab.result_id)
+        });
+        if let Some(tab) = changed {
+            let (tab_id, result_id) = (tab.id, tab.result_id);
+            let results = tab.query_results.clone();
+            let sort_keys = tab.sort_keys.clone();
+            // Browsed tables arrive already sorted by the database.
+            let sorted_by_server = tab.table_pager.is_some();
+            let layout_key = tab.layout_key();
+            let saved_layout = layout_key
+                .as_deref()
+                .and_then(|key| state.column_layouts.get(key))
+                .cloned()
+                .unwrap_or_default();
 
+            let table_state = results.map(|results| {
                 let delegate = QueryResultsDelegate::new(
                     results,
                     self.state.clone(),
-                    sort_column,
-                    sort_ascending,
+                    tab_id,
+                    sort_keys,
+                    sorted_by_server,
+                    layout_key,
+                    &saved_layout,
                 );
-                let state = cx.new(|cx| TableState::new(delegate, window, cx));
-                self.table_state = Some(state);
-            } else {
-                self.table_state = None;
+                let table = cx.new(|cx| {
+                    let mut table = TableState::new(delegate, window, cx);
+                    table.delegate_mut().table = Some(cx.entity().downgrade());
+                    table
+                });
+                cx.subscribe(&table, |_, table, event: &TableEvent, cx| {
+                    if let TableEvent::ColumnWidthsChanged(widths) = event {
+                        table.update(cx, |table, cx| table.delegate_mut().set_widths(widths, cx));
+                    }
+                })
+                .detach();
+                table
+            });
+            if let Some(editor) = self.editors.get_mut(&tab_id) {
+                editor.last_result_id = result_id;
+                editor.table_state = table_state;
             }
+            // Keep searching the new results for what the find bar holds.
+            find::apply_search(self, tab_id, false, false, cx);
         }
 
         div()
@@ -87,10 +341,46 @@ impl Render for MainLayout {
             .text_color(rgb(0xffffff))
             .child(sidebar::render_sidebar(self, cx))
             .child(editor::render_editor_section(self, cx))
+            .children(if self.history.open {
+                Some(history::render_history_panel(self, cx))
+            } else {
+                None
+            })
+            .children(if self.notifications.open {
+                Some(notifications::render_notification_drawer(self, cx))
+            } else {
+                None
+            })
+            .children(if self.save_query.open {
+                Some(saved_queries::render_save_modal(self, cx))
+            } else {
+                None
+            })
             .children(if self.state.0.read(cx).is_connecting {
                 Some(connection_modal::render_modal(self, cx))
             } else {
                 None
             })
+            .children(if self.import.open {
+                Some(import::render_import_modal(self, cx))
+            } else {
+                None
+            })
+            .children(if self.export.open {
+                Some(export::render_export_modal(self, cx))
+            } else {
+                None
+            })
+            .children(if self.password_prompt.connection.is_some() {
+                Some(password_prompt::render_password_prompt(self, cx))
+            } else {
+                None
+            })
+            .children(if self.vault.open {
+                Some(vault::render_vault_modal(self, cx))
+            } else {
+                None
+            })
+            .child(notifications::render_toasts(self, cx))
     }
 }
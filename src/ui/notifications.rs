@@ -0,0 +1,253 @@
+/// Toasts in the corner of the window and the notification history drawer.
+use crate::notifications::{Level, Notification};
+use chrono::Local;
+use gpui::prelude::*;
+use gpui::*;
+use gpui_component::Sizable;
+use gpui_component::StyledExt;
+use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::scroll::ScrollbarAxis;
+use std::time::Instant;
+
+#[derive(Default)]
+pub struct NotificationCenter {
+    /// Whether the history drawer is shown.
+    pub open: bool,
+    /// Wakes up when the next toast expires, to hide it.
+    expiry_timer: Option<(Instant, Task<()>)>,
+}
+
+fn level_color(level: Level) -> Rgba {
+    match level {
+        Level::Info => rgb(0x007acc),
+        Level::Success => rgb(0x4ec9b0),
+        Level::Warning => rgb(0xd7ba7d),
+        Level::Error => rgb(0xff5555),
+    }
+}
+
+/// Start a timer for the next toast to expire, unless one is already set
+/// for that moment.
+fn schedule_expiry(layout: &mut super::MainLayout, cx: &mut Context<super::MainLayout>) {
+    let next = layout.state.0.read(cx).notifications.next_expiry();
+    let scheduled = layout
+        .notifications
+        .expiry_timer
+        .as_ref()
+        .map(|(at, _)| *at);
+    if next == scheduled {
+        return;
+    }
+    layout.notifications.expiry_timer = next.map(|at| {
+        let task = cx.spawn(async move |this, cx| {
+            cx.background_executor()
+                .timer(at.saturating_duration_since(Instant::now()))
+                .await;
+            let _ = this.update(cx, |this, cx| {
+                this.notifications.expiry_timer = None;
+                this.state.0.update(cx, |state, cx| {
+                    state.notifications.dismiss_expired(Instant::now());
+                    cx.notify();
+                });
+                cx.notify();
+            });
+        });
+        (at, task)
+    });
+}
+
+fn copy_button(id: impl Into<ElementId>, notification: &Notification) -> Button {
+    let text = notification.copy_text();
+    Button::new(id)
+        .label("Copy")
+        .ghost()
+        .xsmall()
+        .on_click(move |_, _, cx| cx.write_to_clipboard(ClipboardItem::new_string(text.clone())))
+}
+
+pub fn render_toasts(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    schedule_expiry(layout, cx);
+    let toasts: Vec<Notification> = layout
+        .state
+        .0
+        .read(cx)
+        .notifications
+        .toasts()
+        .cloned()
+        .collect();
+
+    div()
+        .absolute()
+        .bottom_4()
+        .right_4()
+        .w_80()
+        .flex()
+        .flex_col()
+        .gap_2()
+        .children(toasts.into_iter().map(|toast| {
+            let id = toast.id;
+            div()
+                .flex()
+                .flex_col()
+                .gap_1()
+                .p_3()
+                .rounded_md()
+                .bg(rgb(0x252526))
+                .border_1()
+                .border_color(rgb(0x454545))
+                .border_l_4()
+                .border_color(level_color(toast.level))
+                .child(
+                    div()
+                        .flex()
+                        .items_center()
+                        .gap_2()
+                        .child(div().flex_1().text_sm().child(toast.title.clone()))
+                        .when(toast.details.is_some(), |el| {
+                            el.child(copy_button(("copy_toast", id), &toast))
+                        })
+                        .child(
+                            Button::new(("dismiss_toast", id))
+                                .label("×")
+                                .ghost()
+                                .xsmall()
+                                .on_click(cx.listener(move |this, _, _, cx| {
+                                    this.state.0.update(cx, |state, cx| {
+                                        state.notifications.dismiss(id);
+                                        cx.notify();
+                                    });
+                                })),
+                        ),
+                )
+                .when_some(toast.details.clone(), |el, details| {
+                    el.child(
+                        div()
+                            .text_xs()
+                            .text_color(rgb(0xaaaaaa))
+                            .line_clamp(3)
+                            .child(details),
+                    )
+                })
+        }))
+}
+
+pub fn render_notification_drawer(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    let notifications: Vec<Notification> = layout
+        .state
+        .0
+        .read(cx)
+        .notifications
+        .history()
+        .iter()
+        .rev()
+        .cloned()
+        .collect();
+
+    div()
+        .w_96()
+        .h_full()
+        .border_l_1()
+        .border_color(rgb(0x333333))
+        .p_4()
+        .flex()
+        .flex_col()
+        .gap_2()
+        .child(
+            div()
+                .flex()
+                .justify_between()
+                .items_center()
+                .child(
+                    div()
+                        .text_sm()
+                        .font_weight(gpui::FontWeight::SEMIBOLD)
+                        .child("Notifications"),
+                )
+                .child(
+                    div()
+                        .flex()
+                        .gap_1()
+                        .child(
+                            Button::new("clear_notifications")
+                                .label("Clear")
+                                .xsmall()
+                                .on_click(cx.listener(|this, _, _, cx| {
+                                    this.state.0.update(cx, |state, cx| {
+                                        state.notifications.clear();
+                                        cx.notify();
+                                    });
+                                })),
+                        )
+                        .child(
+                            Button::new("close_notifications")
+                                .label("Close")
+                                .xsmall()
+                                .on_click(cx.listener(|this, _, _, cx| {
+                                    this.notifications.open = false;
+                                    cx.notify();
+                                })),
+                        ),
+                ),
+        )
+        .child(if notifications.is_empty() {
+            div()
+                .text_sm()
+                .text_color(rgb(0x888888))
+                .child("No notifications yet.")
+                .into_any_element()
+        } else {
+            div()
+                .flex_1()
+                .flex()
+                .flex_col()
+                .gap_2()
+                .scrollable(ScrollbarAxis::Vertical)
+                .children(notifications.iter().map(render_history_entry))
+                .into_any_element()
+        })
+}
+
+fn render_history_entry(notification: &Notification) -> impl IntoElement {
+    div()
+        .flex()
+        .flex_col()
+        .gap_1()
+        .pb_2()
+        .border_b_1()
+        .border_color(rgb(0x333333))
+        .child(
+            div()
+                .flex()
+                .items_center()
+                .gap_2()
+                .child(
+                    div()
+                        .size_2()
+                        .rounded_full()
+                        .bg(level_color(notification.level)),
+                )
+                .child(div().flex_1().text_sm().child(notification.title.clone()))
+                .child(copy_button(
+                    ("copy_notification", notification.id),
+                    notification,
+                )),
+        )
+        .child(
+            div().text_xs().text_color(rgb(0x888888)).child(
+                notification
+                    .created_at
+                    .with_timezone(&Local)
+                    .format("%H:%M:%S")
+                    .to_string(),
+            ),
+        )
+        .when_some(notification.details.clone(), |el, details| {
+            el.child(div().text_xs().text_color(rgb(0xaaaaaa)).child(details))
+        })
+}
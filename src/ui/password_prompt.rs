@@ -0,0 +1,149 @@
+/// Password prompt for connections that ask for it on every connect, or that
+/// were imported without one.
+use crate::state::ConnectionConfig;
+use crate::vault::VaultLocked;
+use gpui::prelude::*;
+use gpui::*;
+use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::input::{Input, InputState};
+
+pub struct PasswordPrompt {
+    /// Connection waiting for its password; the prompt is open while set.
+    pub connection: Option<ConnectionConfig>,
+    pub input: Entity<InputState>,
+}
+
+impl PasswordPrompt {
+    pub fn new<C>(window: &mut Window, cx: &mut C) -> Self
+    where
+        C: AppContext,
+        C::Result<Entity<InputState>>: Into<Entity<InputState>>,
+    {
+        Self {
+            connection: None,
+            input: cx
+                .new(|cx| {
+                    InputState::new(window, cx)
+                        .masked(true)
+                        .placeholder("Password")
+                })
+                .into(),
+        }
+    }
+
+    pub fn show(&mut self, conn: ConnectionConfig, window: &mut Window, cx: &mut App) {
+        self.input.update(cx, |input, cx| {
+            input.set_value("", window, cx);
+            input.focus(window, cx);
+        });
+        self.connection = Some(conn);
+    }
+}
+
+pub fn render_password_prompt(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    let (name, remember) = layout
+        .password_prompt
+        .connection
+        .as_ref()
+        .map(|c| (c.name.clone(), c.needs_credentials))
+        .unwrap_or_default();
+    let explanation = if remember {
+        "This connection was imported without a password. Enter yours; it is saved to the \
+         keyring for next time."
+    } else {
+        "The password is used for this connection only and not saved."
+    };
+
+    div()
+        .absolute()
+        .size_full()
+        .bg(black().opacity(0.8))
+        .flex()
+        .items_center()
+        .justify_center()
+        .child(
+            div()
+                .w_96()
+                .bg(rgb(0x252526))
+                .border_1()
+                .border_color(rgb(0x454545))
+                .p_4()
+                .flex()
+                .flex_col()
+                .gap_4()
+                .child(div().text_xl().child(format!("Connect to {}", name)))
+                .child(div().text_sm().text_color(rgb(0xaaaaaa)).child(explanation))
+                .child(Input::new(&layout.password_prompt.input).appearance(true))
+                .child(
+                    div()
+                        .flex()
+                        .gap_2()
+                        .justify_end()
+                        .child(Button::new("password_cancel").label("Cancel").on_click(
+                            cx.listener(|this, _, _, cx| {
+                                this.password_prompt.connection = None;
+                                cx.notify();
+                            }),
+                        ))
+                        .child(
+                            Button::new("password_connect")
+                                .primary()
+                                .label("Connect")
+                                .on_click(cx.listener(|this, _, window, cx| {
+                                    let Some(mut conn) = this.password_prompt.connection.take()
+                                    else {
+                                        return;
+                                    };
+                                    let password =
+                                        this.password_prompt.input.read(cx).value().to_string();
+                                    this.password_prompt
+                                        .input
+                                        .update(cx, |input, cx| input.set_value("", window, cx));
+                                    if conn.needs_credentials {
+                                        remember_password(this, &mut conn, &password, window, cx);
+                                    }
+                                    super::sidebar::select_connection(
+                                        this,
+                                        conn,
+                                        Some(password),
+                                        window,
+                                        cx,
+                                    );
+                                    cx.notify();
+                                })),
+                        ),
+                ),
+        )
+}
+
+/// Save the password of a connection imported without one. Connecting goes
+/// ahead even if it can't be saved; it is asked for again next time.
+fn remember_password(
+    layout: &mut super::MainLayout,
+    conn: &mut ConnectionConfig,
+    password: &str,
+    window: &mut Window,
+    cx: &mut Context<super::MainLayout>,
+) {
+    let result = layout
+        .state
+        .0
+        .update(cx, |state, _| state.remember_password(&conn.name, password));
+    match result {
+        Ok(()) => {
+            conn.password = password.to_string();
+            conn.needs_credentials = false;
+        }
+        Err(e) => {
+            if e.is::<VaultLocked>() {
+                layout.vault.show(window, cx);
+            }
+            layout.state.0.update(cx, |state, _| {
+                state.notifications.error("Failed to save password", e);
+            });
+        }
+    }
+}
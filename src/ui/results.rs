@@ -1,11 +1,348 @@
 //! Query results display component.
 //!
-//! This module handles rendering and displaying query results in a table format.
-//! Currently, results rendering is handled in the editor module via render_query_results.
+//! The results table itself is rendered by the editor module via render_query_results;
+//! this module owns the footer beneath it and the server-side paging used when
+//! browsing a table from the sidebar.
 //! Future enhancements:
 //! - Custom result formatting and export
 //! - Result caching and history
-//! - Pagination for large result sets
 //! - Column-specific rendering (e.g., JSON, dates)
+use crate::db::{
+    DEFAULT_PAGE_SIZE, DatabaseClient, PAGE_SIZES, PageNav, RowCount, Table, TablePager,
+};
+use crate::state::AppState;
+use gpui::prelude::*;
+use gpui::*;
+use gpui_component::Disableable;
+use gpui_component::Selectable;
+use gpui_component::Sizable;
+use gpui_component::button::{Button, ButtonGroup, ButtonVariants};
+use gpui_component::menu::{DropdownMenu, PopupMenuItem};
+use std::sync::Arc;
 
-// Placeholder module for future expansion
+/// Start browsing `table` from `connection` in the selected tab, which then
+/// runs against that connection.
+pub fn open_table(
+    app_state: Entity<AppState>,
+    connection: String,
+    table: Table,
+    cx: &mut Context<super::MainLayout>,
+) {
+    let client = app_state.update(cx, |state, _| {
+        let tab = state.active_tab_mut()?;
+        tab.connection = Some(connection);
+        let tab_id = tab.id;
+        Some((tab_id, state.tab_client(tab_id).ok()?))
+    });
+    let Some((tab_id, client)) = client else {
+        return;
+    };
+
+    let async_cx = cx.to_async();
+    cx.spawn(move |_, _: &mut AsyncApp| async move {
+        let mut cx = async_cx.clone();
+        // A table we cannot introspect can still be paged with OFFSET.
+        let columns = client.get_columns(&table).await.unwrap_or_default();
+        let mut pager = TablePager::new(&table, &columns, DEFAULT_PAGE_SIZE);
+        pager.total = client.count_rows(&table).await.ok();
+
+        fetch_page(client, pager, PageNav::First, app_state, tab_id, &mut cx).await;
+    })
+    .detach();
+}
+
+/// Move the table being browsed in a tab to another page.
+///
+/// Takes `App` rather than a view context so the results table can reload
+/// pages after a sort change.
+pub fn load_page(app_state: Entity<AppState>, tab_id: usize, nav: PageNav, cx: &mut App) {
+    let state = app_state.read(cx);
+    let Some(pager) = state.tab(tab_id).and_then(|t| t.table_pager.clone()) else {
+        return;
+    };
+    let client = match state.tab_client(tab_id) {
+        Ok(client) => client,
+        Err(e) => {
+            app_state.update(cx, |state, cx| {
+                state.notifications.error("Can't load the page", e);
+                cx.notify();
+            });
+            return;
+        }
+    };
+
+    cx.spawn(async move |cx| {
+        fetch_page(client, pager, nav, app_state, tab_id, cx).await;
+    })
+    .detach();
+}
+
+fn set_page_size(
+    app_state: Entity<AppState>,
+    tab_id: usize,
+    page_size: usize,
+    cx: &mut Context<super::MainLayout>,
+) {
+    app_state.update(cx, |state, _| {
+        if let Some(pager) = state.tab_mut(tab_id).and_then(|t| t.table_pager.as_mut()) {
+            pager.set_page_size(page_size);
+        }
+    });
+    load_page(app_state, tab_id, PageNav::First, cx);
+}
+
+async fn fetch_page(
+    client: Arc<dyn DatabaseClient>,
+    mut pager: TablePager,
+    nav: PageNav,
+    app_state: Entity<AppState>,
+    tab_id: usize,
+    cx: &mut AsyncApp,
+) {
+    let _ = app_state.update(cx, |state, cx| {
+        if let Some(tab) = state.tab_mut(tab_id) {
+            tab.running = true;
+            tab.error = None;
+        }
+        cx.notify();
+    });
+
+    let query = pager.query(nav);
+    let result = client.execute_query(&query.sql).await;
+
+    let _ = app_state.update(cx, |state, cx| {
+        let Some(tab) = state.tab_mut(tab_id) else {
+            // The tab was closed while the page loaded.
+            return;
+        };
+        tab.running = false;
+        match result {
+            Ok(mut res) => {
+                pager.apply(nav, &query, &mut res);
+                tab.query_results = Some(res);
+                tab.result_id += 1;
+                tab.current_query = Some(query.sql.clone());
+                tab.current_table = Some(pager.table.name.clone());
+                tab.sort_keys = pager.sort_keys.clone();
+                tab.table_pager = Some(pager);
+            }
+            Err(e) => tab.error = Some(client.describe_error(&e)),
+        }
+        cx.notify();
+    });
+}
+
+/// Footer beneath the results table: row totals, plus paging controls while
+/// a table is being browsed.
+pub fn render_results_footer(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    let tab = layout.state.0.read(cx).active_tab();
+    let tab_id = tab.map_or(0, |t| t.id);
+    let results = tab.and_then(|t| t.query_results.as_ref());
+    let row_count = results.map_or(0, |results| results.row_count());
+    let truncated = results.is_some_and(|results| results.is_truncated());
+    let read_error = results.and_then(|results| results.read_error());
+    // Rows left by the find bar's filter.
+    let shown = layout
+        .active_editor(cx)
+        .and_then(|e| e.table_state.as_ref())
+        .map_or(row_count, |table| table.read(cx).delegate().row_count());
+    let pager = tab.and_then(|t| t.table_pager.clone());
+    let columns_menu = render_columns_menu(layout, cx);
+
+    let footer = div()
+        .flex()
+        .gap_2()
+        .justify_between()
+        .items_center()
+        .px_2()
+        .py_2()
+        .text_xs()
+        .text_color(rgb(0x888888));
+
+    let footer = footer.children(columns_menu).children(read_error.map(|e| {
+        div()
+            .text_color(rgb(0xf48771))
+            .child(format!("Some rows couldn't be read back from disk: {}", e))
+    }));
+    let Some(pager) = pager else {
+        return footer
+            .child(format!(
+                "{}{} | Click column headers to sort",
+                if shown < row_count {
+                    format!("Showing {} of {} rows", shown, row_count)
+                } else {
+                    format!("Total: {} rows", row_count)
+                },
+                if truncated {
+                    " (stopped at the memory budget)"
+                } else {
+                    ""
+                }
+            ))
+            .into_any_element();
+    };
+
+    let page_label = match pager.page_count() {
+        Some(count) => format!("Page {} of {}", pager.page + 1, count),
+        None => format!("Page {}", pager.page + 1),
+    };
+
+    footer
+        .child(
+            div()
+                .flex()
+                .gap_2()
+                .items_center()
+                .child(render_page_button(
+                    "page_first",
+                    "First",
+                    PageNav::First,
+                    pager.has_previous(),
+                    tab_id,
+                    cx,
+                ))
+                .child(render_page_button(
+                    "page_prev",
+                    "Prev",
+                    PageNav::Previous,
+                    pager.has_previous(),
+                    tab_id,
+                    cx,
+                ))
+                .child(page_label)
+                .child(render_page_button(
+                    "page_next",
+                    "Next",
+                    PageNav::Next,
+                    pager.has_next(),
+                    tab_id,
+                    cx,
+                ))
+                .child(render_page_button(
+                    "page_last",
+                    "Last",
+                    PageNav::Last,
+                    pager.has_next() && pager.total.is_some(),
+                    tab_id,
+                    cx,
+                )),
+        )
+        .child(
+            div()
+                .flex()
+                .gap_2()
+                .items_center()
+                .child("Rows per page")
+                .child(render_page_size_selector(pager.page_size, tab_id, cx)),
+        )
+        .child(format_total(pager.total, row_count))
+        .into_any_element()
+}
+
+/// Every result column, ticked while shown, plus ways to undo layout changes.
+fn render_columns_menu(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> Option<impl IntoElement> {
+    let table = layout.active_editor(cx)?.table_state.clone()?;
+    let button = Button::new("columns_menu")
+        .label("Columns")
+        .ghost()
+        .xsmall()
+        .dropdown_menu(move |menu, _, cx| {
+            let delegate = table.read(cx).delegate();
+            let shown = delegate.columns.len();
+            let menu = delegate.layout.iter().fold(
+                menu.scrollable(true).max_h(px(400.)),
+                |menu, column| {
+                    let (index, hidden) = (column.index, column.state.hidden);
+                    menu.item(
+                        PopupMenuItem::new(column.state.name.clone())
+                            .checked(!hidden)
+                            // Keep at least one column on screen.
+                            .disabled(!hidden && shown == 1)
+                            .on_click({
+                                let table = table.clone();
+                                move |_, _, cx| {
+                                    table.update(cx, |table, cx| {
+                                        table.delegate_mut().update_column(
+                                            index,
+                                            |c| c.state.hidden = !hidden,
+                                            cx,
+                                        );
+                                        table.refresh(cx);
+                                    });
+                                }
+                            }),
+                    )
+                },
+            );
+            menu.separator()
+                .item(PopupMenuItem::new("Auto-size All Columns").on_click({
+                    let table = table.clone();
+                    move |_, _, cx| {
+                        table.update(cx, |table, cx| {
+                            table.delegate_mut().auto_size(cx);
+                            table.refresh(cx);
+                        });
+                    }
+                }))
+                .item(PopupMenuItem::new("Reset Layout").on_click({
+                    let table = table.clone();
+                    move |_, _, cx| {
+                        table.update(cx, |table, cx| {
+                            table.delegate_mut().reset_layout(cx);
+                            table.refresh(cx);
+                        });
+                    }
+                }))
+        });
+    Some(button)
+}
+
+fn render_page_button(
+    id: &'static str,
+    label: &'static str,
+    nav: PageNav,
+    enabled: bool,
+    tab_id: usize,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    Button::new(id)
+        .label(label)
+        .xsmall()
+        .disabled(!enabled)
+        .on_click(cx.listener(move |this, _, _, cx| {
+            load_page(this.state.0.clone(), tab_id, nav, cx);
+        }))
+}
+
+fn render_page_size_selector(
+    page_size: usize,
+    tab_id: usize,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    ButtonGroup::new("page_size")
+        .xsmall()
+        .children(PAGE_SIZES.iter().map(|&size| {
+            Button::new(("page_size", size))
+                .label(size.to_string())
+                .selected(size == page_size)
+        }))
+        .on_click(cx.listener(move |this, clicks: &Vec<usize>, _, cx| {
+            if let Some(&size) = clicks.first().and_then(|&ix| PAGE_SIZES.get(ix)) {
+                set_page_size(this.state.0.clone(), tab_id, size, cx);
+            }
+        }))
+}
+
+fn format_total(total: Option<RowCount>, page_rows: usize) -> String {
+    match total {
+        Some(RowCount { rows, exact: true }) => format!("Total: {} rows", rows),
+        Some(RowCount { rows, exact: false }) => format!("Total: ~{} rows (estimated)", rows),
+        None => format!("{} rows on this page", page_rows),
+    }
+}
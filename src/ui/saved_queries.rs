@@ -0,0 +1,527 @@
+/// Saved queries: the sidebar library, the save/edit dialog, and tab-stop
+/// navigation for snippets inserted into the editor.
+use crate::saved_queries::{SavedQuery, expand_snippet};
+use gpui::prelude::*;
+use gpui::*;
+use gpui_component::RopeExt;
+use gpui_component::button::{Button, ButtonGroup, ButtonVariants};
+use gpui_component::input::{Input, InputState};
+use gpui_component::{Selectable, Sizable};
+use std::ops::Range;
+
+pub struct SaveQueryForm {
+    pub open: bool,
+    /// Folder and name of the query being edited; `None` saves the editor
+    /// text as a new query.
+    pub editing: Option<(String, String)>,
+    pub name_input: Entity<InputState>,
+    pub folder_input: Entity<InputState>,
+    pub tags_input: Entity<InputState>,
+    pub description_input: Entity<InputState>,
+    pub connection: Option<String>,
+}
+
+impl SaveQueryForm {
+    pub fn new<C>(window: &mut Window, cx: &mut C) -> Self
+    where
+        C: AppContext,
+        C::Result<Entity<InputState>>: Into<Entity<InputState>>,
+    {
+        Self {
+            open: false,
+            editing: None,
+            name_input: cx
+                .new(|cx| InputState::new(window, cx).placeholder("Query name"))
+                .into(),
+            folder_input: cx
+                .new(|cx| InputState::new(window, cx).placeholder("e.g. reports/daily"))
+                .into(),
+            tags_input: cx
+                .new(|cx| InputState::new(window, cx).placeholder("Comma-separated tags"))
+                .into(),
+            description_input: cx
+                .new(|cx| InputState::new(window, cx).placeholder("Optional"))
+                .into(),
+            connection: None,
+        }
+    }
+
+    /// Open the dialog, filled in from `query` when editing one.
+    pub fn show(
+        &mut self,
+        query: Option<&SavedQuery>,
+        default_connection: Option<String>,
+        window: &mut Window,
+        cx: &mut App,
+    ) {
+        let fields = [
+            (&self.name_input, query.map(|q| q.name.clone())),
+            (&self.folder_input, query.map(|q| q.folder.clone())),
+            (&self.tags_input, query.map(|q| q.tags.join(", "))),
+            (
+                &self.description_input,
+                query.and_then(|q| q.description.clone()),
+            ),
+        ];
+        for (input, value) in fields {
+            input.update(cx, |input, cx| {
+                input.set_value(value.unwrap_or_default(), window, cx);
+            });
+        }
+        self.editing = query.map(|q| (q.folder.clone(), q.name.clone()));
+        self.connection = match query {
+            Some(q) => q.connection.clone(),
+            None => default_connection,
+        };
+        self.open = true;
+    }
+}
+
+/// Tab stops of the last inserted snippet that Tab has not reached yet.
+pub struct SnippetSession {
+    /// Byte range of the stop the cursor is in, as inserted.
+    current: Range<usize>,
+    /// Buffer length when `current` was entered, to measure what was typed.
+    buffer_len: usize,
+    remaining: Vec<Range<usize>>,
+}
+
+pub fn render_saved_queries_section(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    let state = layout.state.0.read(cx);
+    let folders: Vec<(String, Vec<SavedQuery>)> = state
+        .saved_queries
+        .folders()
+        .into_iter()
+        .map(|folder| {
+            let queries = state
+                .saved_queries
+                .queries
+                .iter()
+                .filter(|q| q.folder == folder)
+                .cloned()
+                .collect();
+            (folder.to_string(), queries)
+        })
+        .collect();
+
+    let mut ix = 0;
+    let mut list = div().flex().flex_col().gap_1();
+    for (folder, queries) in folders {
+        if !folder.is_empty() {
+            list = list.child(
+                div()
+                    .mt_1()
+                    .text_xs()
+                    .text_color(rgb(0x888888))
+                    .child(folder),
+            );
+        }
+        for query in queries {
+            list = list.child(render_saved_query(ix, query, cx));
+            ix += 1;
+        }
+    }
+
+    div()
+        .mt_4()
+        .flex()
+        .flex_col()
+        .gap_1()
+        .child(
+            div()
+                .flex()
+                .justify_between()
+                .items_center()
+                .child("Saved Queries")
+                .child(Button::new("save_query").label("+").on_click(cx.listener(
+                    |this, _, window, cx| {
+                        let active = this
+                            .state
+                            .0
+                            .read(cx)
+                            .active_tab_connection()
+                            .map(str::to_string);
+                        this.save_query.show(None, active, window, cx);
+                        cx.notify();
+                    },
+                ))),
+        )
+        .child(list)
+}
+
+fn render_saved_query(
+    ix: usize,
+    query: SavedQuery,
+    cx: &mut Context<super::MainLayout>,
+) -> Stateful<Div> {
+    let mut meta: Vec<String> = query.tags.iter().map(|t| format!("#{}", t)).collect();
+    if let Some(connection) = &query.connection {
+        meta.push(format!("@{}", connection));
+    }
+
+    div()
+        .id(("saved_query", ix))
+        .ml_2()
+        .flex()
+        .items_center()
+        .gap_1()
+        .child(
+            div()
+                .id(("saved_query_insert", ix))
+                .flex_1()
+                .overflow_hidden()
+                .cursor_pointer()
+                .hover(|style| style.bg(rgb(0x2a2d2e)))
+                .when_some(query.description.clone(), |el, description| {
+                    el.tooltip(move |window, cx| {
+                        gpui_component::tooltip::Tooltip::new(description.clone()).build(window, cx)
+                    })
+                })
+                .child(
+                    div()
+                        .text_sm()
+                        .text_ellipsis()
+                        .whitespace_nowrap()
+                        .child(query.name.clone()),
+                )
+                .when(!meta.is_empty(), |el| {
+                    el.child(
+                        div()
+                            .text_xs()
+                            .text_color(rgb(0x888888))
+                            .text_ellipsis()
+                            .whitespace_nowrap()
+                            .child(meta.join(" ")),
+                    )
+                })
+                .on_click(cx.listener({
+                    let body = query.body.clone();
+                    move |this, _, window, cx| insert_snippet(this, &body, window, cx)
+                })),
+        )
+        .child(
+            Button::new(("edit_saved_query", ix))
+                .label("Edit")
+                .xsmall()
+                .on_click(cx.listener({
+                    let query = query.clone();
+                    move |this, _, window, cx| {
+                        this.save_query.show(Some(&query), None, window, cx);
+                        cx.notify();
+                    }
+                })),
+        )
+        .child(
+            Button::new(("delete_saved_query", ix))
+                .label("Delete")
+                .xsmall()
+                .on_click(cx.listener(move |this, _, _, cx| {
+                    this.state.0.update(cx, |state, cx| {
+                        if let Err(e) = state.saved_queries.delete(&query.folder, &query.name) {
+                            state.notifications.error("Failed to delete query", e);
+                        }
+                        cx.notify();
+                    });
+                })),
+        )
+}
+
+pub fn render_save_modal(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    let title = if layout.save_query.editing.is_some() {
+        "Edit Saved Query"
+    } else {
+        "Save Query"
+    };
+    let form = &layout.save_query;
+
+    div()
+        .absolute()
+        .size_full()
+        .bg(black().opacity(0.8))
+        .flex()
+        .items_center()
+        .justify_center()
+        .child(
+            div()
+                .w_96()
+                .bg(rgb(0x252526))
+                .border_1()
+                .border_color(rgb(0x454545))
+                .p_4()
+                .flex()
+                .flex_col()
+                .gap_4()
+                .child(div().text_xl().child(title))
+                .child(render_field("Name", &form.name_input))
+                .child(render_field("Folder", &form.folder_input))
+                .child(render_field("Tags", &form.tags_input))
+                .child(render_field("Description", &form.description_input))
+                .child(render_connection_selector(layout, cx))
+                .child(render_modal_actions(layout, cx)),
+        )
+}
+
+fn render_field(label: &'static str, input: &Entity<InputState>) -> impl IntoElement {
+    div()
+        .flex()
+        .flex_col()
+        .gap_1()
+        .child(div().text_sm().child(label))
+        .child(Input::new(input).appearance(true))
+}
+
+fn render_connection_selector(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    let names: Vec<String> = layout
+        .state
+        .0
+        .read(cx)
+        .connections
+        .iter()
+        .map(|c| c.name.clone())
+        .collect();
+    let selected = layout.save_query.connection.clone();
+
+    div()
+        .flex()
+        .flex_col()
+        .gap_1()
+        .child(div().text_sm().child("Connection"))
+        .child(
+            ButtonGroup::new("saved_query_connection")
+                .xsmall()
+                .child(
+                    Button::new("saved_query_any_connection")
+                        .label("Any")
+                        .selected(selected.is_none()),
+                )
+                .children(names.iter().enumerate().map(|(i, name)| {
+                    Button::new(("saved_query_connection", i))
+                        .label(name.clone())
+                        .selected(selected.as_ref() == Some(name))
+                }))
+                .on_click(cx.listener(move |this, clicks: &Vec<usize>, _, cx| {
+                    if let Some(&ix) = clicks.first() {
+                        // Index 0 is "Any".
+                        this.save_query.connection =
+                            ix.checked_sub(1).and_then(|i| names.get(i).cloned());
+                        cx.notify();
+                    }
+                })),
+        )
+}
+
+fn render_modal_actions(
+    _layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    div()
+        .flex()
+        .gap_2()
+        .justify_end()
+        .child(
+            Button::new("cancel_save_query")
+                .label("Cancel")
+                .on_click(cx.listener(|this, _, _, cx| {
+                    this.save_query.open = false;
+                    cx.notify();
+                })),
+        )
+        .child(
+            Button::new("confirm_save_query")
+                .primary()
+                .label("Save")
+                .on_click(cx.listener(|this, _, _, cx| save_query(this, cx))),
+        )
+}
+
+fn save_query(layout: &mut super::MainLayout, cx: &mut Context<super::MainLayout>) {
+    let form = &layout.save_query;
+    let value = |input: &Entity<InputState>| input.read(cx).value().trim().to_string();
+    let description = value(&form.description_input);
+    let mut query = SavedQuery {
+        name: value(&form.name_input),
+        folder: value(&form.folder_input).trim_matches('/').to_string(),
+        tags: value(&form.tags_input)
+            .split(',')
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect(),
+        connection: form.connection.clone(),
+        description: Some(description).filter(|d| !d.is_empty()),
+        body: String::new(),
+    };
+    let editing = form.editing.clone();
+    let editor_text = layout
+        .query_input(cx)
+        .map(|input| input.read(cx).value().to_string())
+        .unwrap_or_default();
+
+    let result = layout.state.0.update(cx, |state, cx| {
+        let key = (query.folder.clone(), query.name.clone());
+        let library = &mut state.saved_queries;
+        let result = match &editing {
+            Some((folder, name)) => {
+                // Editing changes the metadata and location; the SQL stays.
+                query.body = library
+                    .find(folder, name)
+                    .map(|q| q.body.clone())
+                    .unwrap_or_default();
+                library.update(folder, name, query).map(|()| {
+                    // Tabs saved as this query follow the rename.
+                    let old_key = (folder.clone(), name.clone());
+                    for tab in &mut state.tabs {
+                        if tab.saved_query.as_ref() == Some(&old_key) {
+                            tab.title = key.1.clone();
+                            tab.saved_query = Some(key.clone());
+                        }
+                    }
+                })
+            }
+            None => {
+                query.body = editor_text.clone();
+                library.save(query).map(|()| {
+                    // The tab is now saved as this query.
+                    if let Some(tab) = state.active_tab_mut() {
+                        tab.title = key.1.clone();
+                        tab.saved_query = Some(key.clone());
+                        tab.saved_text = editor_text;
+                    }
+                })
+            }
+        };
+        if let Err(e) = &result {
+            state.notifications.error("Failed to save query", e);
+        }
+        cx.notify();
+        result
+    });
+    if result.is_ok() {
+        layout.save_query.open = false;
+        layout.save_session(cx);
+        cx.notify();
+    }
+}
+
+/// Insert a saved query's body at the cursor, replacing any selection, and
+/// select its first tab stop.
+fn insert_snippet(
+    layout: &mut super::MainLayout,
+    body: &str,
+    window: &mut Window,
+    cx: &mut Context<super::MainLayout>,
+) {
+    let Some(query_input) = layout.query_input(cx) else {
+        return;
+    };
+    let snippet = expand_snippet(body);
+    let start = query_input.update(cx, |input, cx| {
+        let selection = input
+            .selected_text_range(false, window, cx)
+            .map(|s| s.range);
+        let start = match &selection {
+            Some(range) => input.text().offset_utf16_to_offset(range.start),
+            None => input.cursor(),
+        };
+        input.replace_text_in_range(selection, &snippet.text, window, cx);
+        input.focus(window, cx);
+        start
+    });
+
+    let mut stops = snippet
+        .stops
+        .into_iter()
+        .map(|stop| start + stop.start..start + stop.end);
+    let session = stops.next().map(|first| {
+        let buffer_len = select_stop(&query_input, first.clone(), window, cx);
+        SnippetSession {
+            current: first,
+            buffer_len,
+            remaining: stops.collect(),
+        }
+    });
+    if let Some(editor) = layout.active_editor_mut(cx) {
+        editor.snippet = session;
+    }
+}
+
+/// Move to the snippet's next tab stop. Returns false, letting Tab indent as
+/// usual, once the cursor has left the snippet's current stop.
+pub fn next_tab_stop(
+    layout: &mut super::MainLayout,
+    window: &mut Window,
+    cx: &mut Context<super::MainLayout>,
+) -> bool {
+    let Some(editor) = layout.active_editor_mut(cx) else {
+        return false;
+    };
+    let Some(mut session) = editor.snippet.take() else {
+        return false;
+    };
+    let query_input = editor.input.clone();
+    let input = query_input.read(cx);
+    let delta = input.text().len() as isize - session.buffer_len as isize;
+    let cursor = input.cursor();
+    let current_end = session.current.end.saturating_add_signed(delta);
+    if cursor < session.current.start || cursor > current_end {
+        return false;
+    }
+
+    // Only what was typed in the current stop has moved the others.
+    let edited_end = session.current.end;
+    let shift = |offset: usize| {
+        if offset >= edited_end {
+            offset.saturating_add_signed(delta)
+        } else {
+            offset
+        }
+    };
+    let mut remaining = session
+        .remaining
+        .into_iter()
+        .map(|stop| shift(stop.start)..shift(stop.end));
+    let Some(next) = remaining.next() else {
+        return true;
+    };
+    session.buffer_len = select_stop(&query_input, next.clone(), window, cx);
+    session.current = next;
+    session.remaining = remaining.collect();
+    if !session.remaining.is_empty()
+        && let Some(editor) = layout.active_editor_mut(cx)
+    {
+        editor.snippet = Some(session);
+    }
+    true
+}
+
+/// Select `stop` so typing replaces its default text; returns the buffer
+/// length at that point.
+fn select_stop(
+    query_input: &Entity<InputState>,
+    stop: Range<usize>,
+    window: &mut Window,
+    cx: &mut Context<super::MainLayout>,
+) -> usize {
+    let (chars, buffer_len) = query_input.update(cx, |input, cx| {
+        let text = input.text().clone();
+        let stop = stop.start.min(text.len())..stop.end.min(text.len());
+        input.set_cursor_position(text.offset_to_position(stop.start), window, cx);
+        (text.slice(stop).chars().count(), text.len())
+    });
+    // The editor has no public API to set a selection, so extend it the way
+    // Shift+Right would.
+    if let Ok(action) = cx.build_action("ui::SelectRight", None) {
+        for _ in 0..chars {
+            window.dispatch_action(action.boxed_clone(), cx);
+        }
+    }
+    buffer_len
+}
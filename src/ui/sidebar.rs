@@ -1,10 +1,17 @@
-use crate::db::{DatabaseClient, PostgresClient, SqliteClient};
-use crate::state::DatabaseType;
-/// Sidebar component: connections list and tables list.
+use crate::db::Table;
+use crate::error::AppError;
+use crate::sql::SchemaCache;
+use crate::state::{AppState, ConnectionConfig, ConnectionSession, ConnectionStatus};
+use crate::vault::VaultLocked;
+/// Sidebar component: open connections with their object trees, and saved
+/// queries.
 use gpui::prelude::*;
 use gpui::*;
-use gpui_component::button::Button;
+use gpui_component::Sizable;
+use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::checkbox::Checkbox;
 use gpui_component::collapsible::Collapsible;
+use gpui_component::menu::{DropdownMenu, PopupMenuItem};
 use std::sync::Arc;
 
 pub fn render_sidebar(
@@ -14,9 +21,53 @@ pub fn render_sidebar(
     div()
         .w_64()
         .h_full()
+        .flex()
+        .flex_col()
         .border_r_1()
         .border_color(rgb(0x333333))
-        .child(render_sidebar_content(layout, cx))
+        .child(div().flex_1().child(render_sidebar_content(layout, cx)))
+        .child(render_sidebar_footer(layout, cx))
+}
+
+fn render_sidebar_footer(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    let state = layout.state.0.read(cx);
+    let enabled = state.settings.telemetry.crash_reports;
+    let notification_count = state.notifications.history().len();
+    div()
+        .p_4()
+        .border_t_1()
+        .border_color(rgb(0x333333))
+        .text_xs()
+        .flex()
+        .justify_between()
+        .items_center()
+        .child(
+            Checkbox::new("crash_reports")
+                .label("Send crash reports")
+                .checked(enabled)
+                .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                    let enabled = *checked;
+                    this.state.0.update(cx, |state, cx| {
+                        if let Err(e) = state.set_crash_reports(enabled) {
+                            state.notifications.error("Failed to save settings", e);
+                        }
+                        cx.notify();
+                    });
+                })),
+        )
+        .child(
+            Button::new("toggle_notifications")
+                .label(format!("🔔 {}", notification_count))
+                .ghost()
+                .xsmall()
+                .on_click(cx.listener(|this, _, _, cx| {
+                    this.notifications.open = !this.notifications.open;
+                    cx.notify();
+                })),
+        )
 }
 
 fn render_sidebar_content(
@@ -30,12 +81,9 @@ fn render_sidebar_content(
         .gap_2()
         .child(render_connections_header(layout, cx))
         .children(render_connections_list(layout, cx))
-        .child(
-            div().when_some(layout.state.0.read(cx).error_message.clone(), |el, msg| {
-                el.my_2().text_sm().text_color(rgb(0xff5555)).child(msg)
-            }),
-        )
-        .child(render_tables_section(layout, cx))
+        .child(super::saved_queries::render_saved_queries_section(
+            layout, cx,
+        ))
 }
 
 fn render_connections_header(
@@ -48,175 +96,588 @@ fn render_connections_header(
         .items_center()
         .child("Connections")
         .child(
-            Button::new("add_conn")
-                .label("+")
-                .on_click(cx.listener(|this, _, _, cx| {
-                    this.state.0.update(cx, |state, cx| {
-                        state.toggle_connecting(cx);
-                    });
-                })),
+            div().flex().gap_1().child(render_import_menu(cx)).child(
+                Button::new("add_conn")
+                    .label("+")
+                    .on_click(cx.listener(|this, _, window, cx| {
+                        this.form.show(None, window, cx);
+                        this.state.0.update(cx, |state, cx| {
+                            state.toggle_connecting(cx);
+                        });
+                    })),
+            ),
         )
 }
 
+fn render_import_menu(cx: &mut Context<super::MainLayout>) -> impl IntoElement {
+    let view = cx.entity();
+    Button::new("import_conn")
+        .label("⋯")
+        .ghost()
+        .dropdown_menu(move |menu, _, _| {
+            menu.item(
+                PopupMenuItem::new("Import Detected (pgpass, services, .env)").on_click({
+                    let view = view.clone();
+                    move |_, _, cx| {
+                        view.update(cx, super::import::import_detected);
+                    }
+                }),
+            )
+            .item(PopupMenuItem::new("Import From File…").on_click({
+                let view = view.clone();
+                move |_, _, cx| {
+                    view.update(cx, |_, cx| super::import::import_from_file(cx));
+                }
+            }))
+            .separator()
+            .item(PopupMenuItem::new("Export…").on_click({
+                let view = view.clone();
+                move |_, _, cx| {
+                    view.update(cx, |this, cx| {
+                        let connections = &this.state.0.read(cx).connections;
+                        this.export.show(connections);
+                        cx.notify();
+                    });
+                }
+            }))
+        })
+}
+
 fn render_connections_list(
     layout: &super::MainLayout,
     cx: &mut Context<super::MainLayout>,
-) -> Vec<impl IntoElement> {
-    layout
-        .state
-        .0
-        .read(cx)
+) -> Vec<AnyElement> {
+    let state = layout.state.0.read(cx);
+    let rows: Vec<(ConnectionConfig, Option<ConnectionStatus>, bool, Vec<Table>)> = state
         .connections
         .iter()
+        .map(|conn| {
+            let session = state.session(&conn.name);
+            (
+                conn.clone(),
+                session.map(|s| s.status.clone()),
+                session.is_some_and(|s| s.expanded),
+                session.map(|s| s.tables.clone()).unwrap_or_default(),
+            )
+        })
+        .collect();
+    let active = state.active_connection_name.clone();
+    let confirm_delete = layout.confirm_delete_connection.clone();
+
+    rows.into_iter()
         .enumerate()
-        .map(|(i, conn)| {
+        .map(|(i, (conn, status, expanded, tables))| {
+            let is_active = active.as_ref() == Some(&conn.name);
+            div()
+                .flex()
+                .flex_col()
+                .child(render_connection_row(
+                    i,
+                    &conn,
+                    status.as_ref(),
+                    expanded,
+                    is_active,
+                    cx,
+                ))
+                .when_some(
+                    match &status {
+                        Some(ConnectionStatus::Failed(message)) => Some(message.clone()),
+                        _ => None,
+                    },
+                    |el, message| {
+                        el.child(
+                            div()
+                                .ml_4()
+                                .text_xs()
+                                .text_color(rgb(0xff5555))
+                                .child(message),
+                        )
+                    },
+                )
+                .when(confirm_delete.as_ref() == Some(&conn.name), |el| {
+                    el.child(render_delete_confirmation(i, &conn.name, cx))
+                })
+                .when(
+                    expanded && status == Some(ConnectionStatus::Connected),
+                    |el| el.child(render_object_tree(i, &conn.name, tables, cx)),
+                )
+                .into_any_element()
+        })
+        .collect()
+}
+
+fn render_connection_row(
+    ix: usize,
+    conn: &ConnectionConfig,
+    status: Option<&ConnectionStatus>,
+    expanded: bool,
+    is_active: bool,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    let indicator = match status {
+        None => rgb(0x555555),
+        Some(ConnectionStatus::Connecting) => rgb(0xd7ba7d),
+        Some(ConnectionStatus::Connected) => rgb(0x4ec9b0),
+        Some(ConnectionStatus::Failed(_)) => rgb(0xff5555),
+    };
+    let name = conn.name.clone();
+
+    div()
+        .id(ix)
+        .flex()
+        .items_center()
+        .gap_2()
+        .cursor_pointer()
+        .when(is_active, |el| el.bg(rgb(0x2a2d2e)))
+        .child(
             div()
-                .id(i)
-                .child(conn.name.clone())
-                .cursor_pointer()
-                .on_click(cx.listener({
-                    let conn = conn.clone();
-                    move |this, _, _, cx| {
-                        let app_state = this.state.0.clone();
-                        let conn = conn.clone();
-
-                        let async_cx = cx.to_async();
-                        cx.spawn(|_, _: &mut AsyncApp| async move {
-                            let mut cx = async_cx.clone();
-                            let _ = app_state.update(&mut cx, |_state, cx| {
-                                cx.notify();
-                            });
-
-                            if conn.connection_string.is_empty() {
-                                let _ = app_state.update(&mut cx, |state, cx| {
-                                    state.is_connecting = false;
-                                    state.error_message = Some("Connection string/password missing. Please delete and re-create the connection.".to_string());
+                .id(("toggle_tree", ix))
+                .w_3()
+                .text_xs()
+                .text_color(rgb(0x888888))
+                .when(status == Some(&ConnectionStatus::Connected), |el| {
+                    el.child(if expanded { "▾" } else { "▸" })
+                        .on_click(cx.listener({
+                            let name = name.clone();
+                            move |this, _, _, cx| {
+                                cx.stop_propagation();
+                                this.state.0.update(cx, |state, cx| {
+                                    if let Some(session) = state.session_mut(&name) {
+                                        session.expanded = !session.expanded;
+                                    }
                                     cx.notify();
                                 });
-                                return;
                             }
+                        }))
+                }),
+        )
+        .child(div().size_2().rounded_full().bg(indicator))
+        .child(div().flex_1().child(name.clone()))
+        .when(conn.shared_from.is_some(), |el| {
+            el.child(div().text_xs().text_color(rgb(0x888888)).child("shared"))
+        })
+        .when(conn.ephemeral, |el| {
+            el.child(div().text_xs().text_color(rgb(0x888888)).child("unsaved"))
+        })
+        .when(status.is_some(), |el| {
+            el.child(
+                Button::new(("disconnect", ix))
+                    .label("Disconnect")
+                    .xsmall()
+                    .on_click(cx.listener({
+                        let name = name.clone();
+                        move |this, _, _, cx| {
+                            cx.stop_propagation();
+                            disconnect(this, &name, cx);
+                        }
+                    })),
+            )
+        })
+        .child(
+            // Opening the menu should not also select the connection.
+            div()
+                .on_mouse_down(MouseButton::Left, |_, _, cx| cx.stop_propagation())
+                .child(render_connection_menu(ix, conn, cx)),
+        )
+        .on_click(cx.listener({
+            let conn = conn.clone();
+            move |this, _, window, cx| select_connection(this, conn.clone(), None, window, cx)
+        }))
+        .on_drag(
+            DraggedConnection {
+                ix,
+                name: name.into(),
+            },
+            |drag, _, _, cx| {
+                cx.stop_propagation();
+                cx.new(|_| drag.clone())
+            },
+        )
+        .drag_over::<DraggedConnection>(|style, _, _, _| {
+            style.border_t_2().border_color(rgb(0x007acc))
+        })
+        .on_drop(cx.listener(move |this, drag: &DraggedConnection, _, cx| {
+            this.state.0.update(cx, |state, cx| {
+                if let Err(e) = state.move_connection(drag.ix, ix) {
+                    state.notifications.error("Failed to save connections", e);
+                }
+                cx.notify();
+            });
+        }))
+}
 
-                            println!("Connecting to '{}' with string: '{}'", conn.name, conn.connection_string);
-                            let client_result = match conn.db_type {
-                                DatabaseType::Sqlite => {
-                                    SqliteClient::new(&conn.connection_string)
-                                        .await
-                                        .map(|c| Arc::new(c) as Arc<dyn DatabaseClient>)
-                                }
-                                DatabaseType::Postgres => {
-                                    PostgresClient::new(&conn.connection_string)
-                                        .await
-                                        .map(|c| Arc::new(c) as Arc<dyn DatabaseClient>)
-                                }
-                            };
-
-                            match client_result {
-                                Ok(client) => {
-                                    let tables_result = client.get_tables().await;
-                                    let _ = app_state.update(&mut cx, |state, cx| {
-                                        state.is_connecting = false;
-                                        match tables_result {
-                                            Ok(tables) => {
-                                                state.active_connection = Some(client);
-                                                state.active_connection_name = Some(conn.name);
-                                                state.tables = tables;
-                                            }
-                                            Err(e) => {
-                                                state.error_message =
-                                                    Some(format!("Failed to fetch tables: {}", e));
-                                            }
-                                        }
-                                        cx.notify();
-                                    });
-                                }
-                                Err(e) => {
-                                    let _ = app_state.update(&mut cx, |state, cx| {
-                                        state.is_connecting = false;
-                                        println!("Connection failed: {}", e);
-                                        state.error_message = Some(format!("Failed to connect: {}", e));
-                                        cx.notify();
-                                    });
-                                }
-                            }
-                        })
-                        .detach();
+/// Payload while a connection is dragged to a new position.
+#[derive(Clone)]
+struct DraggedConnection {
+    ix: usize,
+    name: SharedString,
+}
+
+impl Render for DraggedConnection {
+    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .px_3()
+            .py_1()
+            .bg(rgb(0x252526))
+            .border_1()
+            .border_color(rgb(0x454545))
+            .opacity(0.75)
+            .child(self.name.clone())
+    }
+}
+
+fn render_connection_menu(
+    ix: usize,
+    conn: &ConnectionConfig,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    let view = cx.entity();
+    let conn = conn.clone();
+
+    Button::new(("connection_menu", ix))
+        .label("⋯")
+        .ghost()
+        .xsmall()
+        .dropdown_menu(move |menu, _, _| {
+            let menu = if conn.ephemeral {
+                menu.item(PopupMenuItem::new("Save Connection").on_click({
+                    let (view, name) = (view.clone(), conn.name.clone());
+                    move |_, window, cx| {
+                        view.update(cx, |this, cx| keep_connection(this, &name, window, cx));
                     }
                 }))
+                .separator()
+            } else {
+                menu
+            };
+            menu.item(PopupMenuItem::new("Edit").on_click({
+                let (view, conn) = (view.clone(), conn.clone());
+                move |_, window, cx| {
+                    view.update(cx, |this, cx| {
+                        this.form.show(Some(&conn), window, cx);
+                        this.state.0.update(cx, |state, cx| {
+                            state.is_connecting = true;
+                            cx.notify();
+                        });
+                    });
+                }
+            }))
+            .item(PopupMenuItem::new("Duplicate").on_click({
+                let view = view.clone();
+                move |_, _, cx| {
+                    view.update(cx, |this, cx| {
+                        this.state.0.update(cx, |state, cx| {
+                            if let Err(e) = state.duplicate_connection(ix) {
+                                state
+                                    .notifications
+                                    .error("Failed to duplicate connection", e);
+                            }
+                            cx.notify();
+                        });
+                    });
+                }
+            }))
+            .separator()
+            .item(PopupMenuItem::new("Delete").on_click({
+                let (view, name) = (view.clone(), conn.name.clone());
+                move |_, _, cx| {
+                    view.update(cx, |this, cx| {
+                        this.confirm_delete_connection = Some(name.clone());
+                        cx.notify();
+                    });
+                }
+            }))
         })
-        .collect()
 }
 
-fn render_tables_section(
+/// Save a connection opened from the command line.
+fn keep_connection(
     layout: &mut super::MainLayout,
+    name: &str,
+    window: &mut Window,
+    cx: &mut Context<super::MainLayout>,
+) {
+    let result = layout.state.0.update(cx, |state, cx| {
+        let result = state.keep_connection(name);
+        match &result {
+            Ok(()) => state.notifications.success(format!("Saved '{}'", name)),
+            Err(e) if !e.is::<VaultLocked>() => {
+                state.notifications.error("Failed to save connection", e)
+            }
+            Err(_) => {}
+        }
+        cx.notify();
+        result
+    });
+    if result.is_err_and(|e| e.is::<VaultLocked>()) {
+        layout.vault.show(window, cx);
+    }
+}
+
+fn render_delete_confirmation(
+    ix: usize,
+    name: &str,
     cx: &mut Context<super::MainLayout>,
 ) -> impl IntoElement {
-    div().mt_4().child("Tables").child(
+    let name = name.to_string();
+    div()
+        .ml_4()
+        .my_1()
+        .flex()
+        .flex_col()
+        .gap_1()
+        .text_xs()
+        .child(format!(
+            "Delete '{}' and its saved password? Open tabs keep their text.",
+            name
+        ))
+        .child(
+            div()
+                .flex()
+                .gap_2()
+                .child(
+                    Button::new(("confirm_delete_connection", ix))
+                        .label("Delete")
+                        .danger()
+                        .xsmall()
+                        .on_click(cx.listener(move |this, _, _, cx| {
+                            this.confirm_delete_connection = None;
+                            delete_connection(this, &name, cx);
+                        })),
+                )
+                .child(
+                    Button::new(("cancel_delete_connection", ix))
+                        .label("Cancel")
+                        .xsmall()
+                        .on_click(cx.listener(|this, _, _, cx| {
+                            this.confirm_delete_connection = None;
+                            cx.notify();
+                        })),
+                ),
+        )
+}
+
+fn delete_connection(
+    layout: &mut super::MainLayout,
+    name: &str,
+    cx: &mut Context<super::MainLayout>,
+) {
+    let client = layout.state.0.update(cx, |state, cx| {
+        let client = state.remove_connection(name).unwrap_or_else(|e| {
+            state.notifications.error("Failed to delete connection", e);
+            None
+        });
+        cx.notify();
+        client
+    });
+    if let Some(client) = client {
+        cx.background_spawn(async move { client.close().await })
+            .detach();
+    }
+}
+
+/// Make `conn` the connection the selected tab runs against, opening it
+/// unless it is already open. `password` is the one typed into the prompt for
+/// connections that ask for it on connect.
+pub fn select_connection(
+    layout: &mut super::MainLayout,
+    conn: ConnectionConfig,
+    password: Option<String>,
+    window: &mut Window,
+    cx: &mut Context<super::MainLayout>,
+) {
+    let app_state = layout.state.0.clone();
+    let state = app_state.read(cx);
+    let vault = &state.vault;
+    if conn.password.is_empty() && vault.contains(&conn.name) && !vault.is_unlocked() {
+        layout.vault.show(window, cx);
+        cx.notify();
+        return;
+    }
+    let connected = state
+        .session(&conn.name)
+        .is_some_and(|s| !matches!(s.status, ConnectionStatus::Failed(_)));
+    if (conn.ask_password || conn.needs_credentials) && password.is_none() && !connected {
+        layout.password_prompt.show(conn, window, cx);
+        cx.notify();
+        return;
+    }
+    let password = password.unwrap_or_else(|| conn.password.clone());
+    let needs_connect = app_state.update(cx, |state, cx| {
+        state.active_connection_name = Some(conn.name.clone());
+        if let Some(tab) = state.active_tab_mut()
+            && !tab.running
+        {
+            // Paging would continue against the wrong database.
+            if tab.connection.as_ref() != Some(&conn.name) {
+                tab.table_pager = None;
+            }
+            tab.connection = Some(conn.name.clone());
+        }
+        let needs_connect = match state.session_mut(&conn.name) {
+            Some(session) if matches!(session.status, ConnectionStatus::Failed(_)) => {
+                session.status = ConnectionStatus::Connecting;
+                true
+            }
+            Some(_) => false,
+            None => {
+                state
+                    .sessions
+                    .push(ConnectionSession::new(conn.name.clone()));
+                true
+            }
+        };
+        cx.notify();
+        needs_connect
+    });
+    layout.save_session(cx);
+
+    if needs_connect {
+        connect(app_state, conn, password, cx);
+    }
+}
+
+fn connect(
+    app_state: Entity<AppState>,
+    conn: ConnectionConfig,
+    password: String,
+    cx: &mut Context<super::MainLayout>,
+) {
+    let async_cx = cx.to_async();
+    cx.spawn(|_, _: &mut AsyncApp| async move {
+        let mut cx = async_cx.clone();
+        let fail = |message: String, cx: &mut AsyncApp| {
+            let _ = app_state.update(cx, |state, cx| {
+                if let Some(session) = state.session_mut(&conn.name) {
+                    session.status = ConnectionStatus::Failed(message);
+                }
+                cx.notify();
+            });
+        };
+
+        if conn.connection_string.is_empty() {
+            fail(
+                "Connection string missing. Edit the connection to set it.".to_string(),
+                &mut cx,
+            );
+            return;
+        }
+        let url = match conn.connect_url(&password) {
+            Ok(url) => url,
+            Err(e) => {
+                fail(format!("Invalid connection string: {}", e), &mut cx);
+                return;
+            }
+        };
+
+        tracing::info!(connection = %conn.name, "Connecting");
+        let client = match conn.open_client(&url).await {
+            Ok(client) => client,
+            Err(e) => {
+                tracing::warn!(connection = %conn.name, error = %e, "Connection failed");
+                fail(AppError::from_error(&e).to_string(), &mut cx);
+                return;
+            }
+        };
+
+        let tables = match client.get_tables().await {
+            Ok(tables) => tables,
+            Err(e) => {
+                let error = client.describe_error(&e);
+                client.close().await;
+                fail(format!("Failed to fetch tables: {}", error), &mut cx);
+                return;
+            }
+        };
+        let opened = app_state
+            .update(&mut cx, |state, cx| {
+                // Disconnected while connecting.
+                let session = state.session_mut(&conn.name)?;
+                session.status = ConnectionStatus::Connected;
+                session.client = Some(client.clone());
+                session.tables = tables.clone();
+                session.schema = None;
+                cx.notify();
+                Some(())
+            })
+            .ok()
+            .flatten()
+            .is_some();
+        if !opened {
+            client.close().await;
+            return;
+        }
+
+        // Load columns and functions for completions in the background.
+        if let Ok(schema) = SchemaCache::load(client.as_ref(), tables).await {
+            let _ = app_state.update(&mut cx, |state, _| {
+                // Skip if the connection was closed or reopened meanwhile.
+                if let Some(session) = state.session_mut(&conn.name)
+                    && session
+                        .client
+                        .as_ref()
+                        .is_some_and(|c| Arc::ptr_eq(c, &client))
+                {
+                    session.schema = Some(Arc::new(schema));
+                }
+            });
+        }
+    })
+    .detach();
+}
+
+/// Close a connection's pool and drop what was loaded from it. Tabs bound to
+/// it keep their results.
+fn disconnect(layout: &mut super::MainLayout, name: &str, cx: &mut Context<super::MainLayout>) {
+    let client = layout.state.0.update(cx, |state, cx| {
+        let client = state.disconnect(name);
+        cx.notify();
+        client
+    });
+    if let Some(client) = client {
+        cx.background_spawn(async move { client.close().await })
+            .detach();
+    }
+}
+
+fn render_object_tree(
+    ix: usize,
+    connection: &str,
+    tables: Vec<Table>,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    div().ml_4().child(
         Collapsible::new()
             .open(true)
-            .content(render_tables_list(layout, cx)),
+            .content(render_tables_list(ix, connection, tables, cx)),
     )
 }
 
 fn render_tables_list(
-    layout: &mut super::MainLayout,
+    conn_ix: usize,
+    connection: &str,
+    tables: Vec<Table>,
     cx: &mut Context<super::MainLayout>,
 ) -> impl IntoElement {
-    div().children(
-        layout
-            .state
-            .0
-            .read(cx)
-            .tables
-            .iter()
-            .enumerate()
-            .map(|(i, table)| {
-                let table = table.clone();
-                Button::new(("table", i))
-                    .label(table.name.clone())
-                    .ml_2()
-                    .cursor_pointer()
-                    .on_click(cx.listener({
-                        let table = table.clone();
-                        move |this, event: &gpui::ClickEvent, _, cx| {
-                            if event.click_count() != 2 {
-                                return;
-                            }
+    div().children(tables.into_iter().enumerate().map(|(i, table)| {
+        Button::new(SharedString::from(format!("table_{}_{}", conn_ix, i)))
+            .label(table.name.clone())
+            .ml_2()
+            .cursor_pointer()
+            .on_click(cx.listener({
+                let connection = connection.to_string();
+                move |this, event: &gpui::ClickEvent, _, cx| {
+                    if event.click_count() != 2 {
+                        return;
+                    }
 
-                            let app_state = this.state.0.clone();
-                            let client_opt = this.state.0.read(cx).active_connection.clone();
-                            let table_name = table.name.clone();
-
-                            let async_cx = cx.to_async();
-                            cx.spawn(|_, _: &mut AsyncApp| async move {
-                                let mut cx = async_cx.clone();
-                                if let Some(client) = client_opt {
-                                    let safe_name = table_name.replace('"', "\"\"");
-                                    let query = format!("SELECT * FROM \"{}\"", safe_name);
-                                    let result = client.execute_query(&query).await;
-                                    let _ = app_state.update(&mut cx, |state, cx| {
-                                        match result {
-                                            Ok(res) => {
-                                                state.query_results = Some(res);
-                                                state.result_id += 1;
-                                                state.current_query = Some(query.clone());
-                                                state.current_table = Some(table_name.clone());
-                                                state.sort_column = None;
-                                                state.sort_ascending = true;
-                                            }
-                                            Err(e) => {
-                                                state.error_message = Some(format!(
-                                                    "Failed to fetch table data: {}",
-                                                    e
-                                                ))
-                                            }
-                                        }
-                                        cx.notify();
-                                    });
-                                }
-                            })
-                            .detach();
-                        }
-                    }))
-            }),
-    )
+                    super::results::open_table(
+                        this.state.0.clone(),
+                        connection.clone(),
+                        table.clone(),
+                        cx,
+                    );
+                }
+            }))
+    }))
 }
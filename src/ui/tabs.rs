@@ -0,0 +1,149 @@
+/// Editor tab bar: switch, open, close and drag tabs to reorder them.
+use crate::tabs::SessionTab;
+use gpui::prelude::*;
+use gpui::*;
+use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::tab::{Tab, TabBar};
+use gpui_component::{IconName, Sizable};
+
+/// Payload while a tab is dragged along the bar.
+#[derive(Clone)]
+struct DraggedTab {
+    ix: usize,
+    title: SharedString,
+}
+
+impl Render for DraggedTab {
+    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .px_3()
+            .py_1()
+            .bg(rgb(0x252526))
+            .border_1()
+            .border_color(rgb(0x454545))
+            .opacity(0.75)
+            .text_sm()
+            .child(self.title.clone())
+    }
+}
+
+pub fn render_tab_bar(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    let state = layout.state.0.read(cx);
+    let tabs: Vec<(usize, SharedString, bool, bool)> = state
+        .tabs
+        .iter()
+        .map(|tab| {
+            let modified = layout
+                .editors
+                .get(&tab.id)
+                .is_some_and(|e| e.input.read(cx).value() != tab.saved_text.as_str());
+            (tab.id, tab.title.clone().into(), modified, tab.running)
+        })
+        .collect();
+    let active = state.active_tab;
+
+    TabBar::new("editor_tabs")
+        .small()
+        .selected_index(active)
+        .children(
+            tabs.into_iter()
+                .enumerate()
+                .map(|(ix, (id, title, modified, running))| {
+                    render_tab(ix, id, title, modified, running, cx)
+                }),
+        )
+        .suffix(
+            Button::new("new_tab")
+                .icon(IconName::Plus)
+                .ghost()
+                .xsmall()
+                .tooltip("New tab")
+                .on_click(cx.listener(|this, _, window, cx| {
+                    let id = this.open_tab(SessionTab::default(), window, cx);
+                    select_tab(this, id, window, cx);
+                })),
+        )
+}
+
+fn render_tab(
+    ix: usize,
+    id: usize,
+    title: SharedString,
+    modified: bool,
+    running: bool,
+    cx: &mut Context<super::MainLayout>,
+) -> Tab {
+    let mut label = title.to_string();
+    if running {
+        label.push_str(" …");
+    }
+    if modified {
+        // Unsaved changes since the tab was last saved as a query.
+        label.push_str(" ●");
+    }
+
+    Tab::new()
+        .label(label)
+        .suffix(
+            Button::new(("close_tab", id))
+                .icon(IconName::Close)
+                .ghost()
+                .xsmall()
+                .on_click(cx.listener(move |this, _, window, cx| {
+                    cx.stop_propagation();
+                    close_tab(this, id, window, cx);
+                })),
+        )
+        .on_click(cx.listener(move |this, _, window, cx| select_tab(this, id, window, cx)))
+        .on_drag(DraggedTab { ix, title }, |drag, _, _, cx| {
+            cx.stop_propagation();
+            cx.new(|_| drag.clone())
+        })
+        .drag_over::<DraggedTab>(|style, _, _, _| style.border_l_2().border_color(rgb(0x007acc)))
+        .on_drop(cx.listener(move |this, drag: &DraggedTab, _, cx| {
+            this.state.0.update(cx, |state, cx| {
+                state.move_tab(drag.ix, ix);
+                cx.notify();
+            });
+            this.save_session(cx);
+        }))
+}
+
+fn select_tab(
+    layout: &mut super::MainLayout,
+    id: usize,
+    window: &mut Window,
+    cx: &mut Context<super::MainLayout>,
+) {
+    layout.state.0.update(cx, |state, cx| {
+        if let Some(ix) = state.tabs.iter().position(|t| t.id == id) {
+            state.active_tab = ix;
+        }
+        cx.notify();
+    });
+    if let Some(input) = layout.query_input(cx) {
+        input.update(cx, |input, cx| input.focus(window, cx));
+    }
+    layout.save_session(cx);
+}
+
+/// Close a tab, opening an empty one when it was the last.
+fn close_tab(
+    layout: &mut super::MainLayout,
+    id: usize,
+    window: &mut Window,
+    cx: &mut Context<super::MainLayout>,
+) {
+    layout.state.0.update(cx, |state, cx| {
+        state.close_tab(id);
+        cx.notify();
+    });
+    layout.editors.remove(&id);
+    if layout.editors.is_empty() {
+        layout.open_tab(SessionTab::default(), window, cx);
+    }
+    layout.save_session(cx);
+}
@@ -0,0 +1,142 @@
+/// Passphrase prompt that creates or unlocks the credential vault.
+use gpui::prelude::*;
+use gpui::*;
+use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::input::{Input, InputState};
+
+pub struct VaultForm {
+    pub open: bool,
+    pub passphrase_input: Entity<InputState>,
+    /// Asked for only when creating the vault.
+    pub confirm_input: Entity<InputState>,
+    pub error: Option<String>,
+}
+
+impl VaultForm {
+    pub fn new<C>(window: &mut Window, cx: &mut C) -> Self
+    where
+        C: AppContext,
+        C::Result<Entity<InputState>>: Into<Entity<InputState>>,
+    {
+        Self {
+            open: false,
+            passphrase_input: cx
+                .new(|cx| {
+                    InputState::new(window, cx)
+                        .masked(true)
+                        .placeholder("Master passphrase")
+                })
+                .into(),
+            confirm_input: cx
+                .new(|cx| {
+                    InputState::new(window, cx)
+                        .masked(true)
+                        .placeholder("Repeat passphrase")
+                })
+                .into(),
+            error: None,
+        }
+    }
+
+    pub fn show(&mut self, window: &mut Window, cx: &mut App) {
+        for input in [&self.passphrase_input, &self.confirm_input] {
+            input.update(cx, |input, cx| input.set_value("", window, cx));
+        }
+        self.passphrase_input
+            .update(cx, |input, cx| input.focus(window, cx));
+        self.error = None;
+        self.open = true;
+    }
+}
+
+pub fn render_vault_modal(
+    layout: &mut super::MainLayout,
+    cx: &mut Context<super::MainLayout>,
+) -> impl IntoElement {
+    let creating = !layout.state.0.read(cx).vault.exists();
+    let form = &layout.vault;
+    let (title, explanation, action) = if creating {
+        (
+            "Create Credential Vault",
+            "The system keyring is unavailable, so passwords are kept in an encrypted file \
+             instead. Choose a passphrase to protect it; it can't be recovered if lost.",
+            "Create",
+        )
+    } else {
+        (
+            "Unlock Credential Vault",
+            "Some connection passwords are kept in the encrypted vault. Enter its passphrase \
+             to use them.",
+            "Unlock",
+        )
+    };
+
+    div()
+        .absolute()
+        .size_full()
+        .bg(black().opacity(0.8))
+        .flex()
+        .items_center()
+        .justify_center()
+        .child(
+            div()
+                .w_96()
+                .bg(rgb(0x252526))
+                .border_1()
+                .border_color(rgb(0x454545))
+                .p_4()
+                .flex()
+                .flex_col()
+                .gap_4()
+                .child(div().text_xl().child(title))
+                .child(div().text_sm().text_color(rgb(0xaaaaaa)).child(explanation))
+                .child(Input::new(&form.passphrase_input).appearance(true))
+                .when(creating, |el| {
+                    el.child(Input::new(&form.confirm_input).appearance(true))
+                })
+                .when_some(form.error.clone(), |el, error| {
+                    el.child(div().text_sm().text_color(rgb(0xff5555)).child(error))
+                })
+                .child(
+                    div()
+                        .flex()
+                        .gap_2()
+                        .justify_end()
+                        .child(
+                            Button::new("vault_cancel")
+                                .label("Not now")
+                                .on_click(cx.listener(|this, _, _, cx| {
+                                    this.vault.open = false;
+                                    cx.notify();
+                                })),
+                        )
+                        .child(
+                            Button::new("vault_unlock")
+                                .primary()
+                                .label(action)
+                                .on_click(cx.listener(move |this, _, _, cx| {
+                                    unlock(this, creating, cx);
+                                })),
+                        ),
+                ),
+        )
+}
+
+fn unlock(layout: &mut super::MainLayout, creating: bool, cx: &mut Context<super::MainLayout>) {
+    let passphrase = layout.vault.passphrase_input.read(cx).value().to_string();
+    if creating && layout.vault.confirm_input.read(cx).value() != passphrase.as_str() {
+        layout.vault.error = Some("The passphrases don't match".to_string());
+        cx.notify();
+        return;
+    }
+    let result = layout.state.0.update(cx, |state, cx| {
+        let result = state.unlock_vault(&passphrase);
+        cx.notify();
+        result
+    });
+    match result {
+        Ok(()) => layout.vault.open = false,
+        Err(e) => layout.vault.error = Some(e.to_string()),
+    }
+    cx.notify();
+}
@@ -0,0 +1,344 @@
+// Encrypted file for connection secrets when the system keyring is unusable.
+//
+// A master passphrase is stretched with Argon2id into the key that seals each
+// secret with XChaCha20-Poly1305. Connection names stay readable so a locked
+// vault still knows which connections it holds; only the secrets need the
+// passphrase. Each secret is bound to its name as associated data, so
+// entries can't be swapped between connections in the file.
+use anyhow::{Result, anyhow, bail};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+const VAULT_FILE_NAME: &str = "vault.json";
+/// Sealed in every vault so a wrong passphrase is caught on unlock rather
+/// than on the first secret read.
+const VERIFIER: &[u8] = b"slick-dataui vault";
+
+/// Returned when a secret has to go to the vault but it is locked, or was
+/// never created.
+#[derive(Debug)]
+pub struct VaultLocked;
+
+impl fmt::Display for VaultLocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(
+            "The system keyring is unavailable. Unlock the credential vault to save passwords.",
+        )
+    }
+}
+
+impl std::error::Error for VaultLocked {}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Sealed {
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct KdfParams {
+    salt: String,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct VaultFile {
+    /// `None` until a passphrase has been chosen.
+    kdf: Option<KdfParams>,
+    verifier: Option<Sealed>,
+    entries: BTreeMap<String, Sealed>,
+}
+
+pub struct Vault {
+    path: PathBuf,
+    file: VaultFile,
+    /// Present while unlocked.
+    key: Option<Zeroizing<[u8; 32]>>,
+}
+
+impl Default for Vault {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::new(),
+            file: VaultFile::default(),
+            key: None,
+        }
+    }
+}
+
+impl Vault {
+    /// The vault in the config directory, locked.
+    pub fn load() -> Result<Self> {
+        Self::open(crate::persistence::get_config_dir()?.join(VAULT_FILE_NAME))
+    }
+
+    pub(crate) fn open(path: PathBuf) -> Result<Self> {
+        let file = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            VaultFile::default()
+        };
+        Ok(Self {
+            path,
+            file,
+            key: None,
+        })
+    }
+
+    /// Whether a passphrase has been set.
+    pub fn exists(&self) -> bool {
+        self.file.kdf.is_some()
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.key.is_some()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.file.entries.contains_key(name)
+    }
+
+    /// Set the passphrase of a vault that does not exist yet, leaving it
+    /// unlocked.
+    pub fn create(&mut self, passphrase: &str) -> Result<()> {
+        if self.exists() {
+            bail!("The vault already exists");
+        }
+        if passphrase.is_empty() {
+            bail!("The passphrase can't be empty");
+        }
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let defaults = Params::default();
+        let kdf = KdfParams {
+            salt: BASE64.encode(salt),
+            memory_kib: defaults.m_cost(),
+            iterations: defaults.t_cost(),
+            parallelism: defaults.p_cost(),
+        };
+        let key = derive_key(passphrase, &kdf)?;
+        self.file = VaultFile {
+            verifier: Some(seal(&key, VERIFIER, b"")?),
+            kdf: Some(kdf),
+            entries: BTreeMap::new(),
+        };
+        self.key = Some(key);
+        self.write()
+    }
+
+    pub fn unlock(&mut self, passphrase: &str) -> Result<()> {
+        let (Some(kdf), Some(verifier)) = (&self.file.kdf, &self.file.verifier) else {
+            bail!("No vault has been created");
+        };
+        let key = derive_key(passphrase, kdf)?;
+        if open(&key, verifier, b"").ok().as_deref() != Some(VERIFIER) {
+            bail!("Wrong passphrase");
+        }
+        self.key = Some(key);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Result<Option<String>> {
+        let key = self.key.as_ref().ok_or(VaultLocked)?;
+        self.file
+            .entries
+            .get(name)
+            .map(|sealed| Ok(String::from_utf8(open(key, sealed, name.as_bytes())?)?))
+            .transpose()
+    }
+
+    pub fn set(&mut self, name: &str, secret: &str) -> Result<()> {
+        let key = self.key.as_ref().ok_or(VaultLocked)?;
+        let sealed = seal(key, secret.as_bytes(), name.as_bytes())?;
+        self.file.entries.insert(name.to_string(), sealed);
+        self.write()
+    }
+
+    /// Forget a secret; works while locked too.
+    pub fn remove(&mut self, name: &str) -> Result<()> {
+        if self.file.entries.remove(name).is_some() {
+            self.write()?;
+        }
+        Ok(())
+    }
+
+    fn write(&self) -> Result<()> {
+        write_private(&self.path, &serde_json::to_string_pretty(&self.file)?)
+    }
+}
+
+fn derive_key(passphrase: &str, kdf: &KdfParams) -> Result<Zeroizing<[u8; 32]>> {
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|e| anyhow!("Invalid vault parameters: {}", e))?;
+    let salt = BASE64.decode(&kdf.salt)?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut())
+        .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+/// Encrypt `plaintext`, authenticating `aad` along with it.
+fn seal(key: &[u8; 32], plaintext: &[u8], aad: &[u8]) -> Result<Sealed> {
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| anyhow!("Encryption failed"))?;
+    Ok(Sealed {
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+/// Decrypt what `seal` made with the same `aad`.
+fn open(key: &[u8; 32], sealed: &Sealed, aad: &[u8]) -> Result<Vec<u8>> {
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let nonce = BASE64.decode(&sealed.nonce)?;
+    if nonce.len() != 24 {
+        bail!("Corrupt vault entry");
+    }
+    cipher
+        .decrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &BASE64.decode(&sealed.ciphertext)?,
+                aad,
+            },
+        )
+        .map_err(|_| anyhow!("Could not decrypt vault entry"))
+}
+
+/// Write a file only the current user can read. The contents go to a
+/// sibling temp file that is synced and then renamed over `path`, so a crash
+/// mid-write leaves either the old file or the new one, never half of each.
+pub(crate) fn write_private(path: &Path, contents: &str) -> Result<()> {
+    use std::io::Write;
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    let temp = path.with_file_name(name);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let written = options.open(&temp).and_then(|mut file| {
+        // `mode` only applies when the file is created; tighten a leftover.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        }
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|()| fs::rename(&temp, path)) {
+        let _ = fs::remove_file(&temp);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_vault(label: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "slick-dataui-vault-{}-{}.json",
+            label,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_create_unlock_and_read() {
+        let path = temp_vault("round-trip");
+        let mut vault = Vault::open(path.clone()).unwrap();
+        assert!(!vault.exists());
+        assert!(vault.set("prod", "secret").is_err());
+
+        vault.create("correct horse").unwrap();
+        vault.set("prod", "postgres://u:p@h/db").unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("u:p@h"));
+
+        let mut reopened = Vault::open(path.clone()).unwrap();
+        assert!(reopened.exists() && !reopened.is_unlocked());
+        assert!(reopened.contains("prod"));
+        assert!(reopened.get("prod").unwrap_err().is::<VaultLocked>());
+        assert!(reopened.unlock("wrong").is_err());
+
+        reopened.unlock("correct horse").unwrap();
+        assert_eq!(
+            reopened.get("prod").unwrap().as_deref(),
+            Some("postgres://u:p@h/db")
+        );
+        assert_eq!(reopened.get("other").unwrap(), None);
+
+        // An entry moved to another name in the file no longer opens.
+        let sealed = reopened.file.entries["prod"].clone();
+        reopened.file.entries.insert("staging".to_string(), sealed);
+        assert!(reopened.get("staging").is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_remove_while_locked() {
+        let path = temp_vault("remove");
+        let mut vault = Vault::open(path.clone()).unwrap();
+        vault.create("pass").unwrap();
+        vault.set("a", "1").unwrap();
+        vault.set("b", "2").unwrap();
+
+        let mut locked = Vault::open(path.clone()).unwrap();
+        locked.remove("a").unwrap();
+
+        let mut reopened = Vault::open(path.clone()).unwrap();
+        reopened.unlock("pass").unwrap();
+        assert!(!reopened.contains("a"));
+        assert_eq!(reopened.get("b").unwrap().as_deref(), Some("2"));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_replaces_the_file_privately() {
+        use std::os::unix::fs::PermissionsExt;
+        let path = temp_vault("private");
+        let mut vault = Vault::open(path.clone()).unwrap();
+        vault.create("pass").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        vault.set("prod", "secret").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(!path.with_extension("json.tmp").exists());
+        assert!(Vault::open(path.clone()).unwrap().contains("prod"));
+
+        fs::remove_file(&path).unwrap();
+    }
+}